@@ -0,0 +1,198 @@
+use crate::compress::gzip;
+use crate::manifest::output_files;
+use crate::{CompileResult, WasmRustResult};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::fs;
+use std::path::Path;
+
+/// Percentage of a budget at which a check is reported as a warning.
+pub const BUDGET_WARNING_PERCENT: u64 = 90;
+
+// Typical on-the-fly level used by web servers and CDNs.
+const GZIP_BUDGET_LEVEL: u32 = 6;
+
+/// Per-artifact size limits, declared on `CompileConfig` or under
+/// `[rust.budgets]` in `wasmrun.toml`.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct SizeBudgets {
+    #[serde(default)]
+    pub max_wasm_bytes: Option<u64>,
+    #[serde(default)]
+    pub max_gzip_bytes: Option<u64>,
+    #[serde(default)]
+    pub max_total_bundle_bytes: Option<u64>,
+}
+
+impl SizeBudgets {
+    pub fn is_empty(&self) -> bool {
+        self.max_wasm_bytes.is_none()
+            && self.max_gzip_bytes.is_none()
+            && self.max_total_bundle_bytes.is_none()
+    }
+
+    /// Fills any limit not set on `self` from `fallback`.
+    pub fn or(&self, fallback: &SizeBudgets) -> SizeBudgets {
+        SizeBudgets {
+            max_wasm_bytes: self.max_wasm_bytes.or(fallback.max_wasm_bytes),
+            max_gzip_bytes: self.max_gzip_bytes.or(fallback.max_gzip_bytes),
+            max_total_bundle_bytes: self
+                .max_total_bundle_bytes
+                .or(fallback.max_total_bundle_bytes),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub enum BudgetStatus {
+    Ok,
+    Warning,
+    Exceeded,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct BudgetCheck {
+    pub budget: String,
+    pub artifact: String,
+    pub actual: u64,
+    pub allowed: u64,
+    pub status: BudgetStatus,
+}
+
+impl BudgetCheck {
+    fn new(budget: &str, artifact: String, actual: u64, allowed: u64) -> Self {
+        let status = if actual > allowed {
+            BudgetStatus::Exceeded
+        } else if actual.saturating_mul(100) >= allowed.saturating_mul(BUDGET_WARNING_PERCENT) {
+            BudgetStatus::Warning
+        } else {
+            BudgetStatus::Ok
+        };
+
+        Self {
+            budget: budget.to_string(),
+            artifact,
+            actual,
+            allowed,
+            status,
+        }
+    }
+
+    pub fn percent_used(&self) -> u64 {
+        if self.allowed == 0 {
+            return if self.actual == 0 { 0 } else { u64::MAX };
+        }
+        self.actual.saturating_mul(100) / self.allowed
+    }
+}
+
+impl fmt::Display for BudgetCheck {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} ({}): {} bytes of {} allowed ({}%)",
+            self.budget,
+            self.artifact,
+            self.actual,
+            self.allowed,
+            self.percent_used()
+        )
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct BudgetReport {
+    pub checks: Vec<BudgetCheck>,
+}
+
+impl BudgetReport {
+    pub fn warnings(&self) -> Vec<&BudgetCheck> {
+        self.with_status(BudgetStatus::Warning)
+    }
+
+    pub fn violations(&self) -> Vec<&BudgetCheck> {
+        self.with_status(BudgetStatus::Exceeded)
+    }
+
+    pub fn is_exceeded(&self) -> bool {
+        !self.violations().is_empty()
+    }
+
+    fn with_status(&self, status: BudgetStatus) -> Vec<&BudgetCheck> {
+        self.checks.iter().filter(|c| c.status == status).collect()
+    }
+}
+
+pub(crate) fn format_violations(violations: &[BudgetCheck]) -> String {
+    violations
+        .iter()
+        .map(|v| v.to_string())
+        .collect::<Vec<_>>()
+        .join("; ")
+}
+
+/// Measures the outputs of a compilation against `budgets`.
+///
+/// `max_wasm_bytes` and `max_gzip_bytes` apply to every `.wasm` artifact
+/// individually, `max_total_bundle_bytes` to the sum of all output files.
+pub fn evaluate_budgets(
+    budgets: &SizeBudgets,
+    result: &CompileResult,
+) -> WasmRustResult<BudgetReport> {
+    let mut report = BudgetReport::default();
+    if budgets.is_empty() {
+        return Ok(report);
+    }
+
+    let files = output_files(result);
+    let mut total = 0u64;
+
+    for file in &files {
+        let size = fs::metadata(file)?.len();
+        total += size;
+
+        if file.extension().is_none_or(|ext| ext != "wasm") {
+            continue;
+        }
+
+        let artifact = artifact_name(file);
+        if let Some(allowed) = budgets.max_wasm_bytes {
+            report.checks.push(BudgetCheck::new(
+                "max_wasm_bytes",
+                artifact.clone(),
+                size,
+                allowed,
+            ));
+        }
+        if let Some(allowed) = budgets.max_gzip_bytes {
+            let gzip_size = gzip_size(file)?;
+            report.checks.push(BudgetCheck::new(
+                "max_gzip_bytes",
+                artifact,
+                gzip_size,
+                allowed,
+            ));
+        }
+    }
+
+    if let Some(allowed) = budgets.max_total_bundle_bytes {
+        report.checks.push(BudgetCheck::new(
+            "max_total_bundle_bytes",
+            "bundle".to_string(),
+            total,
+            allowed,
+        ));
+    }
+
+    Ok(report)
+}
+
+fn artifact_name(path: &Path) -> String {
+    path.file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_else(|| path.display().to_string())
+}
+
+fn gzip_size(path: &Path) -> WasmRustResult<u64> {
+    Ok(gzip(&fs::read(path)?, GZIP_BUDGET_LEVEL)?.len() as u64)
+}
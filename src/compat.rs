@@ -0,0 +1,388 @@
+use crate::manifest::{output_files, BuildManifest};
+use crate::wasm::{is_wasm, ExternalKind, WasmModule};
+use crate::{CompileResult, WasmRustError, WasmRustResult};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Compatibility checking options, set on `CompileConfig` or under
+/// `[rust.compat]` in `wasmrun.toml`.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct CompatConfig {
+    /// Snapshot file to compare against, relative to the project directory.
+    /// Written from the current build when it does not exist yet.
+    #[serde(default)]
+    pub snapshot: Option<String>,
+    /// Keep release builds passing even when exports were removed or changed
+    #[serde(default)]
+    pub allow_breaking: bool,
+    /// Overwrite the snapshot with the current surface after a successful check
+    #[serde(default)]
+    pub update_snapshot: bool,
+}
+
+impl CompatConfig {
+    pub fn or(&self, fallback: &CompatConfig) -> CompatConfig {
+        CompatConfig {
+            snapshot: self.snapshot.clone().or_else(|| fallback.snapshot.clone()),
+            allow_breaking: self.allow_breaking || fallback.allow_breaking,
+            update_snapshot: self.update_snapshot || fallback.update_snapshot,
+        }
+    }
+}
+
+/// The parts of a build that host code links against: wasm exports with
+/// their types, and the declarations of wasm-bindgen's generated `.d.ts`.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct ExportSurface {
+    /// Wasm exports keyed by `<kind> <name>`, e.g. `func greet`
+    pub exports: BTreeMap<String, String>,
+    /// TypeScript declarations keyed by name, e.g. `Calculator.add`
+    #[serde(default)]
+    pub typescript: BTreeMap<String, String>,
+}
+
+impl ExportSurface {
+    pub fn from_module(module: &WasmModule) -> Self {
+        let exports = module
+            .exports
+            .iter()
+            .filter(|e| !is_glue_export(&e.name))
+            .map(|e| (format!("{} {}", e.kind, e.name), e.signature.clone()))
+            .collect();
+
+        Self {
+            exports,
+            typescript: BTreeMap::new(),
+        }
+    }
+
+    /// Captures the surface of a finished build: its primary wasm module and,
+    /// when present, the `.d.ts` next to the JS glue.
+    pub fn from_result(result: &CompileResult) -> WasmRustResult<Self> {
+        let wasm_path = primary_wasm(result).ok_or_else(|| {
+            WasmRustError::InvalidProject("Build produced no .wasm file".to_string())
+        })?;
+        let mut surface = Self::from_module(&WasmModule::from_file(&wasm_path)?);
+
+        if let Some(dts) = result
+            .js_path
+            .as_ref()
+            .map(|js| Path::new(js).with_extension("d.ts"))
+            .filter(|dts| dts.exists())
+        {
+            surface.typescript = parse_typescript_declarations(&fs::read_to_string(dts)?);
+        }
+
+        Ok(surface)
+    }
+
+    /// Captures the surface of a `.wasm` file, a build manifest or an output
+    /// directory containing one.
+    pub fn from_path(path: &Path) -> WasmRustResult<Self> {
+        if path.is_file() && is_wasm(&fs::read(path)?) {
+            return Ok(Self::from_module(&WasmModule::from_file(path)?));
+        }
+
+        let manifest = BuildManifest::load(path)?;
+        let base = if path.is_dir() {
+            path.to_path_buf()
+        } else {
+            path.parent().map(Path::to_path_buf).unwrap_or_default()
+        };
+        let wasm_path = manifest.wasm_path.as_ref().ok_or_else(|| {
+            WasmRustError::InvalidProject("Manifest lists no wasm artifact".to_string())
+        })?;
+
+        Self::from_result(&CompileResult {
+            wasm_path: base.join(wasm_path).to_string_lossy().to_string(),
+            js_path: manifest
+                .js_path
+                .as_ref()
+                .map(|js| base.join(js).to_string_lossy().to_string()),
+            ..Default::default()
+        })
+    }
+
+    pub fn function_exports(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.exports_of(ExternalKind::Function)
+    }
+
+    pub fn memory_exports(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.exports_of(ExternalKind::Memory)
+    }
+
+    fn exports_of(&self, kind: ExternalKind) -> impl Iterator<Item = (&str, &str)> {
+        let prefix = format!("{kind} ");
+        self.exports.iter().filter_map(move |(key, signature)| {
+            key.strip_prefix(&prefix)
+                .map(|name| (name, signature.as_str()))
+        })
+    }
+
+    pub fn load(path: &Path) -> WasmRustResult<Self> {
+        Ok(serde_json::from_str(&fs::read_to_string(path)?)?)
+    }
+
+    pub fn write(&self, path: &Path) -> WasmRustResult<()> {
+        if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(path, serde_json::to_string_pretty(self)?)?;
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub enum CompatChangeKind {
+    Added,
+    Removed,
+    Changed,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct CompatChange {
+    /// `func greet`, `memory memory` or `ts Calculator.add`
+    pub item: String,
+    pub kind: CompatChangeKind,
+    pub old: Option<String>,
+    pub new: Option<String>,
+}
+
+impl fmt::Display for CompatChange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.kind {
+            CompatChangeKind::Added => write!(f, "added {}", self.item),
+            CompatChangeKind::Removed => write!(f, "removed {}", self.item),
+            CompatChangeKind::Changed => write!(
+                f,
+                "changed {}: {} -> {}",
+                self.item,
+                self.old.as_deref().unwrap_or_default(),
+                self.new.as_deref().unwrap_or_default()
+            ),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct CompatReport {
+    pub breaking: Vec<CompatChange>,
+    pub additive: Vec<CompatChange>,
+}
+
+impl CompatReport {
+    pub fn is_breaking(&self) -> bool {
+        !self.breaking.is_empty()
+    }
+
+    pub fn is_unchanged(&self) -> bool {
+        self.breaking.is_empty() && self.additive.is_empty()
+    }
+}
+
+pub(crate) fn format_changes(changes: &[CompatChange]) -> String {
+    changes
+        .iter()
+        .map(|c| c.to_string())
+        .collect::<Vec<_>>()
+        .join("; ")
+}
+
+/// Classifies the differences between two surfaces. Removals and signature
+/// changes are breaking for existing hosts, new items are additive.
+pub fn check_compatibility(baseline: &ExportSurface, current: &ExportSurface) -> CompatReport {
+    let mut report = CompatReport::default();
+    compare_maps(&baseline.exports, &current.exports, "", &mut report);
+    compare_maps(
+        &baseline.typescript,
+        &current.typescript,
+        "ts ",
+        &mut report,
+    );
+    report
+}
+
+fn compare_maps(
+    old: &BTreeMap<String, String>,
+    new: &BTreeMap<String, String>,
+    prefix: &str,
+    report: &mut CompatReport,
+) {
+    for (name, old_signature) in old {
+        match new.get(name) {
+            None => report.breaking.push(CompatChange {
+                item: format!("{prefix}{name}"),
+                kind: CompatChangeKind::Removed,
+                old: Some(old_signature.clone()),
+                new: None,
+            }),
+            Some(new_signature) if new_signature != old_signature => {
+                report.breaking.push(CompatChange {
+                    item: format!("{prefix}{name}"),
+                    kind: CompatChangeKind::Changed,
+                    old: Some(old_signature.clone()),
+                    new: Some(new_signature.clone()),
+                })
+            }
+            Some(_) => {}
+        }
+    }
+
+    for (name, new_signature) in new {
+        if !old.contains_key(name) {
+            report.additive.push(CompatChange {
+                item: format!("{prefix}{name}"),
+                kind: CompatChangeKind::Added,
+                old: None,
+                new: Some(new_signature.clone()),
+            });
+        }
+    }
+}
+
+/// Extracts exported declarations from a wasm-bindgen `.d.ts` file. Members
+/// of classes, interfaces and enums are keyed as `Class.member`; overloads
+/// share a name, so they are keyed by their full signature instead.
+pub fn parse_typescript_declarations(source: &str) -> BTreeMap<String, String> {
+    let mut declarations = BTreeMap::new();
+    let mut overloaded = BTreeSet::new();
+    let mut current_class: Option<String> = None;
+    let mut in_comment = false;
+
+    for line in source.lines() {
+        let line = line.trim();
+
+        if in_comment {
+            in_comment = !line.contains("*/");
+            continue;
+        }
+        if line.starts_with("/*") {
+            in_comment = !line.contains("*/");
+            continue;
+        }
+        if line.is_empty() || line.starts_with("//") {
+            continue;
+        }
+
+        if let Some(container) = &current_class {
+            if line.starts_with('}') {
+                current_class = None;
+            } else {
+                let member = match declaration_name(line) {
+                    name if name.is_empty() => normalize(line),
+                    name => name,
+                };
+                if !is_glue_export(container) && !is_glue_export(&member) {
+                    let prefix = format!("{container}.");
+                    insert_declaration(
+                        &mut declarations,
+                        &mut overloaded,
+                        &prefix,
+                        &member,
+                        normalize(line),
+                    );
+                }
+            }
+            continue;
+        }
+
+        let Some(rest) = line.strip_prefix("export ") else {
+            continue;
+        };
+        let rest = rest.strip_prefix("declare ").unwrap_or(rest);
+        let rest = rest.strip_prefix("default ").unwrap_or(rest);
+
+        for container in ["class ", "interface ", "enum "] {
+            if let Some(declaration) = rest.strip_prefix(container) {
+                if line.ends_with('{') {
+                    current_class = Some(declaration_name(declaration));
+                }
+            }
+        }
+
+        let name = [
+            "function ",
+            "class ",
+            "interface ",
+            "enum ",
+            "const ",
+            "let ",
+            "type ",
+        ]
+        .iter()
+        .find_map(|keyword| rest.strip_prefix(keyword))
+        .map(declaration_name);
+        if let Some(name) = name.filter(|n| !n.is_empty() && !is_glue_export(n)) {
+            insert_declaration(
+                &mut declarations,
+                &mut overloaded,
+                "",
+                &name,
+                normalize(rest),
+            );
+        }
+    }
+
+    declarations
+}
+
+// Declared by `--target web` for its loader; they describe the raw wasm
+// exports rather than the bindings.
+const INIT_DECLARATIONS: &[&str] = &["InitInput", "SyncInitInput", "InitOutput", "initSync"];
+
+// wasm-bindgen's own shims are regenerated with the JS glue and are not
+// part of the surface host code relies on.
+fn is_glue_export(name: &str) -> bool {
+    name.starts_with("__wbindgen") || name.starts_with("__wbg") || INIT_DECLARATIONS.contains(&name)
+}
+
+fn insert_declaration(
+    declarations: &mut BTreeMap<String, String>,
+    overloaded: &mut BTreeSet<String>,
+    prefix: &str,
+    name: &str,
+    signature: String,
+) {
+    let key = format!("{prefix}{name}");
+    if let Some(first) = declarations.remove(&key) {
+        declarations.insert(format!("{prefix}{first}"), first);
+        overloaded.insert(key.clone());
+    }
+    if overloaded.contains(&key) {
+        declarations.insert(format!("{prefix}{signature}"), signature);
+    } else {
+        declarations.insert(key, signature);
+    }
+}
+
+fn declaration_name(declaration: &str) -> String {
+    let declaration = declaration
+        .trim_start_matches("static ")
+        .trim_start_matches("readonly ");
+    declaration
+        .split(|c: char| !(c.is_alphanumeric() || c == '_' || c == '$'))
+        .next()
+        .unwrap_or_default()
+        .to_string()
+}
+
+fn normalize(declaration: &str) -> String {
+    declaration
+        .trim_end_matches(['{', ';'])
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+fn primary_wasm(result: &CompileResult) -> Option<PathBuf> {
+    let wasm_path = PathBuf::from(&result.wasm_path);
+    if wasm_path.is_file() {
+        return Some(wasm_path);
+    }
+    output_files(result)
+        .into_iter()
+        .find(|f| f.extension().is_some_and(|ext| ext == "wasm"))
+}
@@ -0,0 +1,139 @@
+use crate::manifest::output_files;
+use crate::{CompileResult, WasmRustError, WasmRustResult};
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io::Write;
+use std::path::Path;
+
+/// Extensions of output files that get precompressed variants.
+pub const PRECOMPRESS_EXTENSIONS: &[&str] = &["wasm", "js", "mjs", "html"];
+
+fn default_true() -> bool {
+    true
+}
+
+fn default_gzip_level() -> u32 {
+    9
+}
+
+fn default_brotli_level() -> u32 {
+    11
+}
+
+/// Opt-in `.gz`/`.br` generation, set on `CompileConfig` or under
+/// `[rust.precompress]` in `wasmrun.toml`.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct PrecompressConfig {
+    #[serde(default = "default_true")]
+    pub gzip: bool,
+    #[serde(default = "default_true")]
+    pub brotli: bool,
+    /// 0-9
+    #[serde(default = "default_gzip_level")]
+    pub gzip_level: u32,
+    /// 0-11
+    #[serde(default = "default_brotli_level")]
+    pub brotli_level: u32,
+}
+
+impl Default for PrecompressConfig {
+    fn default() -> Self {
+        Self {
+            gzip: true,
+            brotli: true,
+            gzip_level: default_gzip_level(),
+            brotli_level: default_brotli_level(),
+        }
+    }
+}
+
+impl PrecompressConfig {
+    fn validate(&self) -> WasmRustResult<()> {
+        if self.gzip_level > 9 {
+            return Err(WasmRustError::InvalidConfiguration(format!(
+                "gzip_level must be 0-9, got {}",
+                self.gzip_level
+            )));
+        }
+        if self.brotli_level > 11 {
+            return Err(WasmRustError::InvalidConfiguration(format!(
+                "brotli_level must be 0-11, got {}",
+                self.brotli_level
+            )));
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct CompressedArtifact {
+    pub path: String,
+    pub size: u64,
+    pub gzip_size: Option<u64>,
+    pub brotli_size: Option<u64>,
+}
+
+/// Writes `<file>.gz` and `<file>.br` next to every wasm, JS and HTML output.
+pub fn precompress_outputs(
+    config: &PrecompressConfig,
+    result: &CompileResult,
+) -> WasmRustResult<Vec<CompressedArtifact>> {
+    config.validate()?;
+
+    let mut artifacts = Vec::new();
+    for file in output_files(result) {
+        let compressible = file
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .is_some_and(|ext| PRECOMPRESS_EXTENSIONS.contains(&ext));
+        if !compressible {
+            continue;
+        }
+
+        let bytes = fs::read(&file)?;
+        let mut artifact = CompressedArtifact {
+            path: file.to_string_lossy().to_string(),
+            size: bytes.len() as u64,
+            ..Default::default()
+        };
+
+        if config.gzip {
+            let compressed = gzip(&bytes, config.gzip_level)?;
+            fs::write(with_suffix(&file, "gz"), &compressed)?;
+            artifact.gzip_size = Some(compressed.len() as u64);
+        }
+        if config.brotli {
+            let compressed = brotli(&bytes, config.brotli_level)?;
+            fs::write(with_suffix(&file, "br"), &compressed)?;
+            artifact.brotli_size = Some(compressed.len() as u64);
+        }
+
+        artifacts.push(artifact);
+    }
+
+    Ok(artifacts)
+}
+
+pub(crate) fn gzip(bytes: &[u8], level: u32) -> WasmRustResult<Vec<u8>> {
+    let mut encoder = GzEncoder::new(Vec::new(), Compression::new(level));
+    encoder.write_all(bytes)?;
+    Ok(encoder.finish()?)
+}
+
+fn brotli(bytes: &[u8], level: u32) -> WasmRustResult<Vec<u8>> {
+    let mut output = Vec::new();
+    {
+        let mut writer = brotli::CompressorWriter::new(&mut output, 4096, level, 22);
+        writer.write_all(bytes)?;
+    }
+    Ok(output)
+}
+
+fn with_suffix(path: &Path, suffix: &str) -> std::path::PathBuf {
+    let mut name = path.as_os_str().to_owned();
+    name.push(".");
+    name.push(suffix);
+    name.into()
+}
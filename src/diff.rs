@@ -0,0 +1,293 @@
+use crate::manifest::BuildManifest;
+use crate::wasm::{is_wasm, WasmExport, WasmImport, WasmModule};
+use crate::{WasmRustError, WasmRustResult};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct SizeDelta {
+    pub name: String,
+    pub old: u64,
+    pub new: u64,
+}
+
+impl SizeDelta {
+    pub fn delta(&self) -> i64 {
+        self.new as i64 - self.old as i64
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct SignatureChange {
+    pub name: String,
+    pub old: String,
+    pub new: String,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct ModuleDiff {
+    pub artifact: String,
+    pub old_size: u64,
+    pub new_size: u64,
+    /// Every section present in either module
+    pub sections: Vec<SizeDelta>,
+    /// Functions whose body size changed, including added and removed ones
+    pub functions: Vec<SizeDelta>,
+    pub exports_added: Vec<WasmExport>,
+    pub exports_removed: Vec<WasmExport>,
+    pub exports_changed: Vec<SignatureChange>,
+    pub imports_added: Vec<WasmImport>,
+    pub imports_removed: Vec<WasmImport>,
+    pub imports_changed: Vec<SignatureChange>,
+    pub features_added: Vec<String>,
+    pub features_removed: Vec<String>,
+}
+
+impl ModuleDiff {
+    pub fn size_delta(&self) -> i64 {
+        self.new_size as i64 - self.old_size as i64
+    }
+
+    pub fn has_interface_changes(&self) -> bool {
+        !(self.exports_added.is_empty()
+            && self.exports_removed.is_empty()
+            && self.exports_changed.is_empty()
+            && self.imports_added.is_empty()
+            && self.imports_removed.is_empty()
+            && self.imports_changed.is_empty()
+            && self.features_added.is_empty()
+            && self.features_removed.is_empty())
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct BuildDiff {
+    pub modules: Vec<ModuleDiff>,
+    /// Output files only present in the new build (manifest inputs only)
+    pub artifacts_added: Vec<String>,
+    /// Output files only present in the old build (manifest inputs only)
+    pub artifacts_removed: Vec<String>,
+}
+
+/// Compares two parsed modules.
+pub fn diff_modules(artifact: &str, old: &WasmModule, new: &WasmModule) -> ModuleDiff {
+    let mut diff = ModuleDiff {
+        artifact: artifact.to_string(),
+        old_size: old.size,
+        new_size: new.size,
+        ..Default::default()
+    };
+
+    let section_names: BTreeSet<&str> = old
+        .sections
+        .iter()
+        .chain(&new.sections)
+        .map(|s| s.name.as_str())
+        .collect();
+    diff.sections = section_names
+        .into_iter()
+        .map(|name| SizeDelta {
+            name: name.to_string(),
+            old: old.section_size(name),
+            new: new.section_size(name),
+        })
+        .collect();
+
+    let old_functions: BTreeMap<&str, u64> = old
+        .functions
+        .iter()
+        .map(|f| (f.name.as_str(), f.body_size))
+        .collect();
+    let new_functions: BTreeMap<&str, u64> = new
+        .functions
+        .iter()
+        .map(|f| (f.name.as_str(), f.body_size))
+        .collect();
+    let function_names: BTreeSet<&str> = old_functions
+        .keys()
+        .chain(new_functions.keys())
+        .copied()
+        .collect();
+    diff.functions = function_names
+        .into_iter()
+        .map(|name| SizeDelta {
+            name: name.to_string(),
+            old: old_functions.get(name).copied().unwrap_or(0),
+            new: new_functions.get(name).copied().unwrap_or(0),
+        })
+        .filter(|d| d.old != d.new)
+        .collect();
+    diff.functions
+        .sort_by_key(|d| std::cmp::Reverse(d.delta().unsigned_abs()));
+
+    let old_exports: BTreeMap<&str, &WasmExport> =
+        old.exports.iter().map(|e| (e.name.as_str(), e)).collect();
+    let new_exports: BTreeMap<&str, &WasmExport> =
+        new.exports.iter().map(|e| (e.name.as_str(), e)).collect();
+    for (name, export) in &new_exports {
+        match old_exports.get(name) {
+            None => diff.exports_added.push((*export).clone()),
+            Some(previous)
+                if previous.kind != export.kind || previous.signature != export.signature =>
+            {
+                diff.exports_changed.push(SignatureChange {
+                    name: name.to_string(),
+                    old: format!("{} {}", previous.kind, previous.signature),
+                    new: format!("{} {}", export.kind, export.signature),
+                })
+            }
+            Some(_) => {}
+        }
+    }
+    diff.exports_removed = old_exports
+        .iter()
+        .filter(|(name, _)| !new_exports.contains_key(*name))
+        .map(|(_, export)| (*export).clone())
+        .collect();
+
+    let old_imports: BTreeMap<String, &WasmImport> = old
+        .imports
+        .iter()
+        .map(|i| (i.qualified_name(), i))
+        .collect();
+    let new_imports: BTreeMap<String, &WasmImport> = new
+        .imports
+        .iter()
+        .map(|i| (i.qualified_name(), i))
+        .collect();
+    for (name, import) in &new_imports {
+        match old_imports.get(name) {
+            None => diff.imports_added.push((*import).clone()),
+            Some(previous)
+                if previous.kind != import.kind || previous.signature != import.signature =>
+            {
+                diff.imports_changed.push(SignatureChange {
+                    name: name.clone(),
+                    old: format!("{} {}", previous.kind, previous.signature),
+                    new: format!("{} {}", import.kind, import.signature),
+                })
+            }
+            Some(_) => {}
+        }
+    }
+    diff.imports_removed = old_imports
+        .iter()
+        .filter(|(name, _)| !new_imports.contains_key(*name))
+        .map(|(_, import)| (*import).clone())
+        .collect();
+
+    diff.features_added = new.features.difference(&old.features).cloned().collect();
+    diff.features_removed = old.features.difference(&new.features).cloned().collect();
+
+    diff
+}
+
+enum BuildInput {
+    Module(PathBuf),
+    Manifest(Box<BuildManifest>, PathBuf),
+}
+
+impl BuildInput {
+    fn open(path: &Path) -> WasmRustResult<Self> {
+        if path.is_file() && is_wasm(&fs::read(path)?) {
+            return Ok(BuildInput::Module(path.to_path_buf()));
+        }
+
+        let manifest = BuildManifest::load(path)?;
+        let base = if path.is_dir() {
+            path.to_path_buf()
+        } else {
+            path.parent().map(Path::to_path_buf).unwrap_or_default()
+        };
+        Ok(BuildInput::Manifest(Box::new(manifest), base))
+    }
+
+    fn primary_module(&self) -> WasmRustResult<(String, PathBuf)> {
+        match self {
+            BuildInput::Module(path) => Ok((file_name(path), path.clone())),
+            BuildInput::Manifest(manifest, base) => {
+                let wasm = manifest.wasm_path.as_ref().ok_or_else(|| {
+                    WasmRustError::InvalidProject("Manifest lists no wasm artifact".to_string())
+                })?;
+                Ok((wasm.clone(), base.join(wasm)))
+            }
+        }
+    }
+}
+
+/// Compares two builds, each given as a `.wasm` file, a build manifest, or
+/// an output directory containing a manifest.
+pub fn diff_builds(old: &Path, new: &Path) -> WasmRustResult<BuildDiff> {
+    let old_input = BuildInput::open(old)?;
+    let new_input = BuildInput::open(new)?;
+    let mut diff = BuildDiff::default();
+
+    if let (
+        BuildInput::Manifest(old_manifest, old_base),
+        BuildInput::Manifest(new_manifest, new_base),
+    ) = (&old_input, &new_input)
+    {
+        // Artifacts are matched by their stable names so content-hashed
+        // builds compare like for like.
+        for artifact in &new_manifest.artifacts {
+            let logical = new_manifest.logical_path(&artifact.path);
+            if old_manifest.artifact_by_logical_path(&logical).is_none() {
+                diff.artifacts_added.push(logical);
+            }
+        }
+        for artifact in &old_manifest.artifacts {
+            let logical = old_manifest.logical_path(&artifact.path);
+            if new_manifest.artifact_by_logical_path(&logical).is_none() {
+                diff.artifacts_removed.push(logical);
+            }
+        }
+
+        for artifact in &new_manifest.artifacts {
+            let logical = new_manifest.logical_path(&artifact.path);
+            let Some(old_artifact) = old_manifest.artifact_by_logical_path(&logical) else {
+                continue;
+            };
+            if !logical.ends_with(".wasm") {
+                continue;
+            }
+            let old_module = WasmModule::from_file(&old_base.join(&old_artifact.path))?;
+            let new_module = WasmModule::from_file(&new_base.join(&artifact.path))?;
+            diff.modules
+                .push(diff_modules(&logical, &old_module, &new_module));
+        }
+
+        // Renamed primary modules would otherwise go uncompared.
+        if diff.modules.is_empty()
+            && old_manifest.wasm_path.is_some()
+            && new_manifest.wasm_path.is_some()
+        {
+            let (_, old_path) = old_input.primary_module()?;
+            let (name, new_path) = new_input.primary_module()?;
+            diff.modules.push(diff_modules(
+                &name,
+                &WasmModule::from_file(&old_path)?,
+                &WasmModule::from_file(&new_path)?,
+            ));
+        }
+
+        return Ok(diff);
+    }
+
+    let (_, old_path) = old_input.primary_module()?;
+    let (name, new_path) = new_input.primary_module()?;
+    diff.modules.push(diff_modules(
+        &name,
+        &WasmModule::from_file(&old_path)?,
+        &WasmModule::from_file(&new_path)?,
+    ));
+
+    Ok(diff)
+}
+
+fn file_name(path: &Path) -> String {
+    path.file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .unwrap_or_else(|| path.display().to_string())
+}
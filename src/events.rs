@@ -0,0 +1,182 @@
+use crate::plan::PlannedCommand;
+use crate::runner::OutputStream;
+use serde::{Deserialize, Serialize};
+use std::cell::Cell;
+use std::fmt;
+
+/// The stages a build moves through, in order. Tools report some of them
+/// only implicitly, so a build may skip phases.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BuildPhase {
+    /// Loading the project and checking the toolchain and targets
+    Resolving,
+    Compiling,
+    /// Generating JavaScript bindings with wasm-bindgen
+    Bindgen,
+    Optimizing,
+    /// Copying, hashing and compressing the outputs
+    Bundling,
+}
+
+impl fmt::Display for BuildPhase {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let description = match self {
+            BuildPhase::Resolving => "resolving toolchain",
+            BuildPhase::Compiling => "compiling",
+            BuildPhase::Bindgen => "generating bindings",
+            BuildPhase::Optimizing => "optimizing",
+            BuildPhase::Bundling => "bundling",
+        };
+        f.write_str(description)
+    }
+}
+
+/// Something that happened during a build, reported as it happens.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum BuildEvent {
+    Phase {
+        phase: BuildPhase,
+    },
+    /// A tool is about to run
+    Command {
+        command: PlannedCommand,
+    },
+    /// One line printed by the running tool
+    Output {
+        stream: OutputStream,
+        line: String,
+    },
+    /// cargo started compiling a crate. `total` is the number of packages
+    /// in Cargo.lock, an upper bound since up-to-date crates are skipped.
+    Crate {
+        name: String,
+        current: usize,
+        total: Option<usize>,
+    },
+    /// A note for whoever watches the build: inputs were unchanged, a
+    /// target is being installed, a size budget is close
+    Message {
+        text: String,
+    },
+    /// Another build holds `lock`, and this one waits for it to finish
+    Waiting {
+        lock: String,
+        pid: Option<u32>,
+    },
+    Finished {
+        success: bool,
+    },
+}
+
+/// The crate name from cargo's `Compiling serde v1.0.203` line.
+pub fn compiling_crate(line: &str) -> Option<&str> {
+    let mut words = line.split_whitespace();
+    if words.next() != Some("Compiling") {
+        return None;
+    }
+    let name = words.next()?;
+    words
+        .next()
+        .is_some_and(|version| version.starts_with('v'))
+        .then_some(name)
+}
+
+/// What wasm-pack and trunk print when they start wasm-bindgen. Other lines
+/// naming it, such as cargo downloading `wasm-bindgen-shared`, are not the
+/// bindgen step.
+const BINDGEN_MARKERS: &[&str] = &[
+    "installing wasm-bindgen",
+    "running wasm-bindgen",
+    "calling wasm-bindgen",
+];
+
+/// The phase a line of cargo, wasm-pack or trunk output announces, if any.
+pub fn phase_for_line(line: &str) -> Option<BuildPhase> {
+    let lower = line.to_lowercase();
+    if compiling_crate(line).is_some() || lower.contains("compiling to wasm") {
+        Some(BuildPhase::Compiling)
+    } else if BINDGEN_MARKERS.iter().any(|marker| lower.contains(marker)) {
+        Some(BuildPhase::Bindgen)
+    } else if lower.contains("wasm-opt") || lower.contains("optimizing") {
+        Some(BuildPhase::Optimizing)
+    } else if lower.contains("applying new distribution") {
+        Some(BuildPhase::Bundling)
+    } else {
+        None
+    }
+}
+
+/// Turns tool output into [`BuildEvent`]s for one build.
+pub(crate) struct Progress<'a> {
+    on_event: &'a dyn Fn(&BuildEvent),
+    phase: Cell<Option<BuildPhase>>,
+    crates: Cell<usize>,
+    total: Option<usize>,
+}
+
+impl<'a> Progress<'a> {
+    pub fn new(on_event: &'a dyn Fn(&BuildEvent), total: Option<usize>) -> Self {
+        Self {
+            on_event,
+            phase: Cell::new(None),
+            crates: Cell::new(0),
+            total,
+        }
+    }
+
+    pub fn emit(&self, event: BuildEvent) {
+        (self.on_event)(&event);
+    }
+
+    /// Enters `phase`, unless the build is already in it.
+    pub fn phase(&self, phase: BuildPhase) {
+        if self.phase.replace(Some(phase)) != Some(phase) {
+            self.emit(BuildEvent::Phase { phase });
+        }
+    }
+
+    pub fn message(&self, text: impl Into<String>) {
+        self.emit(BuildEvent::Message { text: text.into() });
+    }
+
+    pub fn command(&self, command: &PlannedCommand) {
+        self.emit(BuildEvent::Command {
+            command: command.clone(),
+        });
+    }
+
+    pub fn line(&self, stream: OutputStream, line: &str) {
+        if let Some(phase) = phase_for_line(line) {
+            self.phase(phase);
+        }
+        self.emit(BuildEvent::Output {
+            stream,
+            line: line.to_string(),
+        });
+        if let Some(name) = compiling_crate(line) {
+            let current = self.crates.get() + 1;
+            self.crates.set(current);
+            self.emit(BuildEvent::Crate {
+                name: name.to_string(),
+                current,
+                total: self.total.filter(|total| current <= *total),
+            });
+        }
+    }
+}
+
+/// What `verbose` builds print without a caller-supplied handler: the
+/// commands, the plugin's messages and the tools' output, line by line.
+pub(crate) fn print_verbose_event(event: &BuildEvent) {
+    match event {
+        BuildEvent::Command { command } => println!("Running: {command}"),
+        BuildEvent::Message { text } => println!("{text}"),
+        BuildEvent::Output { stream, line } => match stream {
+            OutputStream::Stdout => println!("{line}"),
+            OutputStream::Stderr => eprintln!("{line}"),
+        },
+        _ => {}
+    }
+}
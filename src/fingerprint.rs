@@ -0,0 +1,177 @@
+use crate::toolchain::Toolchain;
+use crate::tools::ToolInfo;
+use crate::{CompileConfig, WasmRustResult};
+use sha2::{Digest, Sha256};
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+use toml::Value;
+
+/// Environment variables outside `CARGO_*` and `RUSTUP_*` that change what
+/// cargo and rustc produce.
+const BUILD_ENV_VARS: &[&str] = &[
+    "RUSTFLAGS",
+    "RUSTDOCFLAGS",
+    "RUSTC",
+    "RUSTC_WRAPPER",
+    "RUSTC_WORKSPACE_WRAPPER",
+];
+
+const DEPENDENCY_SECTIONS: &[&str] = &["dependencies", "dev-dependencies", "build-dependencies"];
+
+/// Digest of everything that decides what a build produces: the input files
+/// (directories are walked), the compile configuration, the toolchain, the
+/// versions of the tools that run and the build environment (see
+/// [`build_environment`]). Two builds with the same fingerprint produce the
+/// same outputs.
+pub fn input_fingerprint(
+    config: &CompileConfig,
+    inputs: &[PathBuf],
+    toolchain: &Toolchain,
+    tools: &[ToolInfo],
+    env: &[(String, String)],
+) -> WasmRustResult<String> {
+    let mut hasher = Sha256::new();
+
+    // Settings that change how a build runs but not what it produces.
+    let config = CompileConfig {
+        verbose: false,
+        force: false,
+        step_timeout: None,
+        cancel: None,
+        lock: None,
+        allow_unsafe_output_dir: false,
+        ..config.clone()
+    };
+    hash_field(
+        &mut hasher,
+        "config",
+        serde_json::to_string(&config)?.as_bytes(),
+    );
+    hash_field(
+        &mut hasher,
+        "toolchain",
+        serde_json::to_string(toolchain)?.as_bytes(),
+    );
+    for tool in tools {
+        let version = tool.version_output.as_deref().unwrap_or_default();
+        hash_field(&mut hasher, &tool.name, version.as_bytes());
+    }
+    for (name, value) in env {
+        hash_field(&mut hasher, name, value.as_bytes());
+    }
+
+    let mut files = Vec::new();
+    for input in inputs {
+        collect_files(input, &mut files);
+    }
+    files.sort();
+    files.dedup();
+    for file in files {
+        hash_field(&mut hasher, &file.to_string_lossy(), &fs::read(&file)?);
+    }
+
+    Ok(hasher
+        .finalize()
+        .iter()
+        .map(|b| format!("{b:02x}"))
+        .collect())
+}
+
+/// The variables of the current environment that cargo, rustc or rustup
+/// read: `CARGO_*`, `RUSTUP_*`, `RUSTFLAGS` and the like, sorted by name.
+pub fn build_environment() -> Vec<(String, String)> {
+    let mut env: Vec<(String, String)> = std::env::vars()
+        .filter(|(name, _)| {
+            name.starts_with("CARGO_")
+                || name.starts_with("RUSTUP_")
+                || BUILD_ENV_VARS.contains(&name.as_str())
+        })
+        .collect();
+    env.sort();
+    env
+}
+
+/// Files a build of `project_path` reads besides its own sources: build
+/// scripts, cargo configuration, the workspace manifest and every path
+/// dependency's manifest and sources, followed recursively. `None` when a
+/// manifest cannot be read or parsed, since the inputs are then unknown.
+pub(crate) fn dependency_inputs(project_path: &Path) -> Option<Vec<PathBuf>> {
+    let project_path = project_path.canonicalize().ok()?;
+    let mut inputs = Vec::new();
+    for dir in project_path.ancestors() {
+        inputs.push(dir.join(".cargo").join("config.toml"));
+        inputs.push(dir.join(".cargo").join("config"));
+    }
+
+    let mut pending = vec![project_path.clone()];
+    pending.extend(workspace_root(&project_path));
+    let mut seen = HashSet::new();
+    while let Some(dir) = pending.pop() {
+        let dir = dir.canonicalize().ok()?;
+        if !seen.insert(dir.clone()) {
+            continue;
+        }
+        let manifest_path = dir.join("Cargo.toml");
+        let manifest: Value = toml::from_str(&fs::read_to_string(&manifest_path).ok()?).ok()?;
+        let build_script = manifest
+            .get("package")
+            .and_then(|package| package.get("build"))
+            .and_then(Value::as_str)
+            .unwrap_or("build.rs");
+        inputs.extend([manifest_path, dir.join("src"), dir.join(build_script)]);
+        pending.extend(path_dependencies(&manifest).map(|path| dir.join(path)));
+    }
+    Some(inputs)
+}
+
+/// The nearest ancestor of `project_path` whose manifest has a
+/// `[workspace]` table.
+fn workspace_root(project_path: &Path) -> Option<PathBuf> {
+    project_path.ancestors().skip(1).find_map(|dir| {
+        let manifest = fs::read_to_string(dir.join("Cargo.toml")).ok()?;
+        let manifest: Value = toml::from_str(&manifest).ok()?;
+        manifest.get("workspace").map(|_| dir.to_path_buf())
+    })
+}
+
+/// `path` values of every dependency in `manifest`, including
+/// target-specific and `[workspace.dependencies]` ones.
+fn path_dependencies(manifest: &Value) -> impl Iterator<Item = &str> {
+    let targets = manifest
+        .get("target")
+        .and_then(Value::as_table)
+        .into_iter()
+        .flat_map(|targets| targets.values());
+    std::iter::once(manifest)
+        .chain(manifest.get("workspace"))
+        .chain(targets)
+        .flat_map(|owner| {
+            DEPENDENCY_SECTIONS
+                .iter()
+                .filter_map(|section| owner.get(section)?.as_table())
+        })
+        .flat_map(|dependencies| dependencies.values())
+        .filter_map(|dependency| dependency.get("path")?.as_str())
+}
+
+// Length-prefixed, so no two different inputs hash the same bytes.
+fn hash_field(hasher: &mut Sha256, name: &str, value: &[u8]) {
+    for part in [name.as_bytes(), value] {
+        hasher.update((part.len() as u64).to_le_bytes());
+        hasher.update(part);
+    }
+}
+
+fn collect_files(path: &Path, files: &mut Vec<PathBuf>) {
+    if path.is_file() {
+        files.push(path.to_path_buf());
+        return;
+    }
+    let Ok(entries) = fs::read_dir(path) else {
+        return;
+    };
+    for entry in entries.flatten() {
+        collect_files(&entry.path(), files);
+    }
+}
@@ -0,0 +1,187 @@
+use crate::{CompileResult, WasmRustError, WasmRustResult};
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Number of hex characters of the SHA-256 digest used in hashed filenames.
+pub const CONTENT_HASH_LENGTH: usize = 16;
+
+// Text outputs that may refer to the renamed files.
+const REFERENCING_EXTENSIONS: &[&str] = &["js", "mjs", "html", "json", "ts"];
+
+pub fn content_hash(bytes: &[u8]) -> String {
+    let digest = Sha256::digest(bytes);
+    digest
+        .iter()
+        .map(|b| format!("{b:02x}"))
+        .collect::<String>()[..CONTENT_HASH_LENGTH]
+        .to_string()
+}
+
+/// `app_bg.wasm` -> `app_bg-<hash>.wasm`; compound extensions such as
+/// `.wasm.d.ts` keep the hash in front of the first dot.
+pub fn hashed_file_name(file_name: &str, hash: &str) -> String {
+    match file_name.split_once('.') {
+        Some((stem, extension)) => format!("{stem}-{hash}.{extension}"),
+        None => format!("{file_name}-{hash}"),
+    }
+}
+
+/// Renames the wasm module and JS glue of a wasm-bindgen build to
+/// content-hashed names and rewrites every reference to them in the other
+/// text outputs. Returns the original -> hashed file name mapping.
+pub fn apply_content_hashes(
+    result: &mut CompileResult,
+    output_dir: &Path,
+) -> WasmRustResult<BTreeMap<String, String>> {
+    let mut mapping = BTreeMap::new();
+
+    let wasm_path = PathBuf::from(&result.wasm_path);
+    let wasm_hash = content_hash(&fs::read(&wasm_path)?);
+    let new_wasm = rename_hashed(&wasm_path, &wasm_hash, &mut mapping)?;
+    result.wasm_path = new_wasm.to_string_lossy().to_string();
+    rename_companion(&wasm_path, "d.ts", &wasm_hash, &mut mapping)?;
+
+    if let Some(js) = result.js_path.clone() {
+        let js_path = PathBuf::from(js);
+        rewrite_references(&js_path, &mapping)?;
+        let js_hash = content_hash(&fs::read(&js_path)?);
+        let new_js = rename_hashed(&js_path, &js_hash, &mut mapping)?;
+        result.js_path = Some(new_js.to_string_lossy().to_string());
+
+        let dts = js_path.with_extension("d.ts");
+        if dts.exists() {
+            let new_dts =
+                dts.with_file_name(format!("{}.d.ts", file_stem(&new_js).unwrap_or_default()));
+            fs::rename(&dts, &new_dts)?;
+            mapping.insert(file_name(&dts)?, file_name(&new_dts)?);
+        }
+    }
+
+    let js_path = result.js_path.as_ref().map(PathBuf::from);
+    for file in referencing_files(result, output_dir) {
+        // The glue was rewritten before hashing; touching it now would make
+        // its name lie about its content.
+        if Some(&file) == js_path.as_ref() {
+            continue;
+        }
+        rewrite_references(&file, &mapping)?;
+    }
+
+    for file in &mut result.additional_files {
+        if let Some(hashed) = Path::new(file)
+            .file_name()
+            .and_then(|name| mapping.get(name.to_string_lossy().as_ref()))
+        {
+            *file = Path::new(file)
+                .with_file_name(hashed)
+                .to_string_lossy()
+                .to_string();
+        }
+    }
+
+    Ok(mapping)
+}
+
+fn rename_hashed(
+    path: &Path,
+    hash: &str,
+    mapping: &mut BTreeMap<String, String>,
+) -> WasmRustResult<PathBuf> {
+    let name = file_name(path)?;
+    let hashed = hashed_file_name(&name, hash);
+    let new_path = path.with_file_name(&hashed);
+    fs::rename(path, &new_path)?;
+    mapping.insert(name, hashed);
+    Ok(new_path)
+}
+
+fn rename_companion(
+    path: &Path,
+    suffix: &str,
+    hash: &str,
+    mapping: &mut BTreeMap<String, String>,
+) -> WasmRustResult<()> {
+    let companion = PathBuf::from(format!("{}.{suffix}", path.display()));
+    if companion.exists() {
+        rename_hashed(&companion, hash, mapping)?;
+    }
+    Ok(())
+}
+
+fn referencing_files(result: &CompileResult, output_dir: &Path) -> Vec<PathBuf> {
+    let mut files: Vec<PathBuf> = fs::read_dir(output_dir)
+        .map(|entries| entries.flatten().map(|e| e.path()).collect())
+        .unwrap_or_default();
+    files.extend(result.additional_files.iter().map(PathBuf::from));
+    files.sort();
+    files.dedup();
+
+    files
+        .into_iter()
+        .filter(|f| f.is_file())
+        .filter(|f| {
+            f.extension()
+                .and_then(|ext| ext.to_str())
+                .is_some_and(|ext| REFERENCING_EXTENSIONS.contains(&ext))
+        })
+        .collect()
+}
+
+fn rewrite_references(path: &Path, mapping: &BTreeMap<String, String>) -> WasmRustResult<()> {
+    let Ok(content) = fs::read_to_string(path) else {
+        return Ok(());
+    };
+
+    let rewritten = replace_references(&content, mapping);
+    if rewritten != content {
+        fs::write(path, rewritten)?;
+    }
+    Ok(())
+}
+
+/// Replaces file names that appear as whole path segments, longest first so
+/// `app_bg.wasm.d.ts` is not mistaken for `app_bg.wasm`.
+pub(crate) fn replace_references(content: &str, mapping: &BTreeMap<String, String>) -> String {
+    let mut names: Vec<(&String, &String)> = mapping.iter().collect();
+    names.sort_by_key(|(old, _)| std::cmp::Reverse(old.len()));
+
+    let mut content = content.to_string();
+    for (old, new) in names {
+        let mut output = String::with_capacity(content.len());
+        let mut rest = content.as_str();
+
+        while let Some(index) = rest.find(old.as_str()) {
+            let before = rest[..index].chars().next_back();
+            let after = rest[index + old.len()..].chars().next();
+            let whole = !before.is_some_and(is_name_char)
+                && !after.is_some_and(|c| is_name_char(c) || c == '.');
+
+            output.push_str(&rest[..index]);
+            output.push_str(if whole { new } else { old });
+            rest = &rest[index + old.len()..];
+        }
+
+        output.push_str(rest);
+        content = output;
+    }
+    content
+}
+
+fn is_name_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_' || c == '-'
+}
+
+fn file_name(path: &Path) -> WasmRustResult<String> {
+    path.file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .ok_or_else(|| {
+            WasmRustError::CompilationFailed(format!("Not a file path: {}", path.display()))
+        })
+}
+
+fn file_stem(path: &Path) -> Option<String> {
+    path.file_stem()
+        .map(|stem| stem.to_string_lossy().to_string())
+}
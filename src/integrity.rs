@@ -0,0 +1,462 @@
+use crate::manifest::output_files;
+use crate::public_url::DEFAULT_PUBLIC_URL;
+use crate::{CompileResult, WasmRustError, WasmRustResult};
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha384};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+// Link relations that fetch a subresource the browser can verify.
+const VERIFIABLE_RELS: &[&str] = &["stylesheet", "modulepreload", "preload"];
+
+// Files an inline bootstrap script may load that need a preload hint.
+const INLINE_REFERENCE_EXTENSIONS: &[&str] = &["js", "mjs", "wasm"];
+
+/// `sha384-<base64>` digest as used by `integrity` attributes and CSP hash sources.
+pub fn sri_hash(bytes: &[u8]) -> String {
+    format!("sha384-{}", STANDARD.encode(Sha384::digest(bytes)))
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct IntegrityResource {
+    /// Reference exactly as written in the HTML
+    pub reference: String,
+    pub integrity: String,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct IntegrityReport {
+    pub html_path: String,
+    pub resources: Vec<IntegrityResource>,
+    /// Suggested `Content-Security-Policy` value for serving this page
+    pub csp: String,
+}
+
+impl IntegrityReport {
+    pub fn csp_header(&self) -> String {
+        format!("Content-Security-Policy: {}", self.csp)
+    }
+
+    pub fn csp_meta_tag(&self) -> String {
+        format!(
+            r#"<meta http-equiv="Content-Security-Policy" content="{}">"#,
+            self.csp
+        )
+    }
+}
+
+/// Adds `integrity` attributes to every script, stylesheet and preload in the
+/// HTML outputs of a build, and preload hints for the glue and wasm that an
+/// inline bootstrap script imports.
+pub fn apply_integrity(
+    result: &CompileResult,
+    output_dir: &Path,
+    public_url: &str,
+) -> WasmRustResult<Vec<IntegrityReport>> {
+    output_files(result)
+        .into_iter()
+        .filter(|f| f.extension().is_some_and(|ext| ext == "html"))
+        .map(|html| apply_integrity_to_html(&html, output_dir, public_url))
+        .collect()
+}
+
+/// Rewrites a single HTML file. References under `public_url` and absolute
+/// ones (`/app.js`) resolve against `root`, relative ones against the file's
+/// directory. External or
+/// missing references fail with `UnhashableReferences`, since the page
+/// could not be fully pinned.
+pub fn apply_integrity_to_html(
+    html_path: &Path,
+    root: &Path,
+    public_url: &str,
+) -> WasmRustResult<IntegrityReport> {
+    let html = fs::read_to_string(html_path)?;
+    let location = Location {
+        base: html_path.parent().unwrap_or(root),
+        root,
+        public_url,
+    };
+
+    let mut resources = Vec::new();
+    let mut unhashable = Vec::new();
+    let mut edits: Vec<(usize, usize, String)> = Vec::new();
+    let mut script_hashes = Vec::new();
+    let mut style_hashes = Vec::new();
+    let mut inline_references = Vec::new();
+
+    for tag in parse_tags(&html) {
+        let reference = match tag.name.as_str() {
+            "script" => tag.attribute("src"),
+            "link" => tag
+                .attribute("rel")
+                .filter(|rel| {
+                    rel.split_whitespace()
+                        .any(|r| VERIFIABLE_RELS.contains(&r.to_ascii_lowercase().as_str()))
+                })
+                .and(tag.attribute("href")),
+            _ => None,
+        };
+
+        if let Some(reference) = reference {
+            match hash_reference(reference, &location) {
+                Some(integrity) => {
+                    edits.extend(tag.integrity_edits(&integrity));
+                    resources.push(IntegrityResource {
+                        reference: reference.to_string(),
+                        integrity,
+                    });
+                }
+                None => unhashable.push(reference.to_string()),
+            }
+            continue;
+        }
+
+        let Some(content) = tag.content else {
+            continue;
+        };
+        if tag.name == "style" {
+            style_hashes.push(sri_hash(content.as_bytes()));
+        } else if tag.name == "script" && is_executable(tag.attribute("type")) {
+            script_hashes.push(sri_hash(content.as_bytes()));
+            inline_references.extend(string_references(content));
+        }
+    }
+
+    let mut preloads = String::new();
+    for reference in inline_references {
+        if resources.iter().any(|r| r.reference == reference) {
+            continue;
+        }
+        let Some(integrity) = hash_reference(&reference, &location) else {
+            unhashable.push(reference);
+            continue;
+        };
+        preloads.push_str(&preload_link(&reference, &integrity));
+        resources.push(IntegrityResource {
+            reference,
+            integrity,
+        });
+    }
+
+    if !unhashable.is_empty() {
+        unhashable.dedup();
+        return Err(WasmRustError::UnhashableReferences {
+            html_path: html_path.to_string_lossy().to_string(),
+            references: unhashable,
+        });
+    }
+
+    if !preloads.is_empty() {
+        edits.push((preload_position(&html), 0, preloads));
+    }
+
+    let rewritten = apply_edits(&html, edits);
+    if rewritten != html {
+        fs::write(html_path, rewritten)?;
+    }
+
+    Ok(IntegrityReport {
+        html_path: html_path.to_string_lossy().to_string(),
+        resources,
+        csp: content_security_policy(&script_hashes, &style_hashes),
+    })
+}
+
+/// `'wasm-unsafe-eval'` is what lets `WebAssembly.instantiate` run without
+/// allowing `eval` for scripts.
+fn content_security_policy(script_hashes: &[String], style_hashes: &[String]) -> String {
+    let sources = |hashes: &[String]| hashes.iter().map(|h| format!(" '{h}'")).collect::<String>();
+
+    format!(
+        "default-src 'self'; script-src 'self' 'wasm-unsafe-eval'{}; style-src 'self'{}; object-src 'none'; base-uri 'self'",
+        sources(script_hashes),
+        sources(style_hashes)
+    )
+}
+
+// Where the references of one HTML file resolve from.
+struct Location<'a> {
+    base: &'a Path,
+    root: &'a Path,
+    public_url: &'a str,
+}
+
+fn hash_reference(reference: &str, location: &Location) -> Option<String> {
+    let path = resolve_reference(reference, location)?;
+    fs::read(path).ok().map(|bytes| sri_hash(&bytes))
+}
+
+fn resolve_reference(reference: &str, location: &Location) -> Option<PathBuf> {
+    if let Some(path) = reference
+        .strip_prefix(location.public_url)
+        .filter(|_| location.public_url != DEFAULT_PUBLIC_URL)
+    {
+        return resolve_reference(&format!("/{path}"), location);
+    }
+    if reference.starts_with("//") || reference.contains(':') {
+        return None;
+    }
+
+    let path = reference
+        .split(['?', '#'])
+        .next()
+        .unwrap_or(reference)
+        .trim();
+    if path.is_empty() {
+        return None;
+    }
+
+    let resolved = match path.strip_prefix('/') {
+        Some(absolute) => location.root.join(absolute),
+        None => location.base.join(path),
+    };
+    resolved.is_file().then_some(resolved)
+}
+
+fn is_executable(script_type: Option<&str>) -> bool {
+    match script_type.map(|t| t.trim().to_ascii_lowercase()) {
+        None => true,
+        Some(t) => {
+            t.is_empty()
+                || t == "module"
+                || t == "importmap"
+                || t.ends_with("javascript")
+                || t.ends_with("ecmascript")
+        }
+    }
+}
+
+/// Quoted relative or root-relative paths to `.js`/`.mjs`/`.wasm` files,
+/// e.g. `import init from './app.js'`.
+fn string_references(script: &str) -> Vec<String> {
+    let mut references = Vec::new();
+    let mut rest = script;
+
+    while let Some(start) = rest.find(['\'', '"', '`']) {
+        let quote = rest[start..].chars().next().unwrap_or('"');
+        let body = &rest[start + 1..];
+        let Some(end) = body.find(quote) else {
+            break;
+        };
+
+        let literal = &body[..end];
+        let extension = literal
+            .split(['?', '#'])
+            .next()
+            .and_then(|path| path.rsplit_once('.'))
+            .map(|(_, ext)| ext);
+        let is_path = literal.starts_with("./")
+            || literal.starts_with("../")
+            || (literal.starts_with('/') && !literal.starts_with("//"));
+        if is_path
+            && extension.is_some_and(|ext| INLINE_REFERENCE_EXTENSIONS.contains(&ext))
+            && !references.iter().any(|r| r == literal)
+        {
+            references.push(literal.to_string());
+        }
+
+        rest = &body[end + 1..];
+    }
+
+    references
+}
+
+fn preload_link(reference: &str, integrity: &str) -> String {
+    if reference
+        .split(['?', '#'])
+        .next()
+        .unwrap_or_default()
+        .ends_with(".wasm")
+    {
+        format!(
+            r#"<link rel="preload" href="{reference}" as="fetch" type="application/wasm" crossorigin integrity="{integrity}">"#
+        ) + "\n"
+    } else {
+        format!(r#"<link rel="modulepreload" href="{reference}" integrity="{integrity}">"#) + "\n"
+    }
+}
+
+// Preload hints go at the end of <head>, or before the first script when
+// the page has no head.
+fn preload_position(html: &str) -> usize {
+    let lower = html.to_ascii_lowercase();
+    lower
+        .find("</head")
+        .or_else(|| lower.find("<script"))
+        .unwrap_or(0)
+}
+
+fn apply_edits(html: &str, mut edits: Vec<(usize, usize, String)>) -> String {
+    edits.sort_by_key(|(start, _, _)| *start);
+
+    let mut output = String::with_capacity(html.len());
+    let mut position = 0;
+    for (start, length, replacement) in edits {
+        output.push_str(&html[position..start]);
+        output.push_str(&replacement);
+        position = start + length;
+    }
+    output.push_str(&html[position..]);
+    output
+}
+
+struct Attribute<'a> {
+    name: String,
+    value: Option<&'a str>,
+    start: usize,
+    end: usize,
+}
+
+struct Tag<'a> {
+    name: String,
+    attributes: Vec<Attribute<'a>>,
+    /// Offset of the `>` or `/>` that closes the start tag
+    close: usize,
+    /// Body of `<script>` and `<style>` elements
+    content: Option<&'a str>,
+}
+
+impl<'a> Tag<'a> {
+    fn attribute(&self, name: &str) -> Option<&'a str> {
+        self.attributes
+            .iter()
+            .find(|a| a.name == name)
+            .and_then(|a| a.value)
+    }
+
+    fn integrity_edits(&self, integrity: &str) -> Vec<(usize, usize, String)> {
+        let mut edits: Vec<_> = self
+            .attributes
+            .iter()
+            .filter(|a| a.name == "integrity")
+            .map(|a| (a.start, a.end - a.start, String::new()))
+            .collect();
+        edits.push((self.close, 0, format!(r#" integrity="{integrity}""#)));
+        edits
+    }
+}
+
+/// Minimal tag scanner: enough to find start tags and their attributes,
+/// skipping comments and the bodies of raw-text elements.
+fn parse_tags(html: &str) -> Vec<Tag<'_>> {
+    let lower = html.to_ascii_lowercase();
+    let bytes = html.as_bytes();
+    let mut tags = Vec::new();
+    let mut position = 0;
+
+    while let Some(offset) = html[position..].find('<') {
+        let start = position + offset;
+        if lower[start..].starts_with("<!--") {
+            position = lower[start..]
+                .find("-->")
+                .map_or(html.len(), |end| start + end + 3);
+            continue;
+        }
+
+        let name_start = start + 1;
+        let name_end = html[name_start..]
+            .find(|c: char| !c.is_ascii_alphanumeric())
+            .map_or(html.len(), |end| name_start + end);
+        if name_end == name_start {
+            position = name_start;
+            continue;
+        }
+        let name = lower[name_start..name_end].to_string();
+
+        let mut attributes = Vec::new();
+        let mut i = name_end;
+        let close = loop {
+            while i < bytes.len() && bytes[i].is_ascii_whitespace() {
+                i += 1;
+            }
+            if i >= bytes.len() {
+                break bytes.len();
+            }
+            if bytes[i] == b'>' {
+                break i;
+            }
+            if bytes[i] == b'/' && bytes.get(i + 1) == Some(&b'>') {
+                break i;
+            }
+            if bytes[i] == b'/' {
+                i += 1;
+                continue;
+            }
+
+            let attribute_start = i;
+            while i < bytes.len() && !matches!(bytes[i], b' ' | b'\t' | b'\n' | b'\r' | b'=' | b'>')
+            {
+                if bytes[i] == b'/' && bytes.get(i + 1) == Some(&b'>') {
+                    break;
+                }
+                i += 1;
+            }
+            let attribute_name = lower[attribute_start..i].to_string();
+
+            let mut value = None;
+            let mut lookahead = i;
+            while lookahead < bytes.len() && bytes[lookahead].is_ascii_whitespace() {
+                lookahead += 1;
+            }
+            if bytes.get(lookahead) == Some(&b'=') {
+                i = lookahead + 1;
+                while i < bytes.len() && bytes[i].is_ascii_whitespace() {
+                    i += 1;
+                }
+                match bytes.get(i) {
+                    Some(&quote) if quote == b'"' || quote == b'\'' => {
+                        let value_start = i + 1;
+                        let value_end = html[value_start..]
+                            .find(quote as char)
+                            .map_or(html.len(), |end| value_start + end);
+                        value = Some(&html[value_start..value_end]);
+                        i = (value_end + 1).min(html.len());
+                    }
+                    _ => {
+                        let value_start = i;
+                        while i < bytes.len() && !bytes[i].is_ascii_whitespace() && bytes[i] != b'>'
+                        {
+                            i += 1;
+                        }
+                        value = Some(&html[value_start..i]);
+                    }
+                }
+            }
+
+            // Include the separating whitespace so removing an attribute
+            // leaves no double spaces.
+            let mut removal_start = attribute_start;
+            while removal_start > name_end && bytes[removal_start - 1].is_ascii_whitespace() {
+                removal_start -= 1;
+            }
+            attributes.push(Attribute {
+                name: attribute_name,
+                value,
+                start: removal_start,
+                end: i,
+            });
+        };
+
+        position = (close + 1).min(html.len());
+        let mut content = None;
+        if name == "script" || name == "style" {
+            let closing = format!("</{name}");
+            let end = lower[position..]
+                .find(&closing)
+                .map_or(html.len(), |end| position + end);
+            content = Some(&html[position..end]);
+            position = end;
+        }
+
+        tags.push(Tag {
+            name,
+            attributes,
+            close,
+            content,
+        });
+    }
+
+    tags
+}
@@ -1,9 +1,85 @@
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 use std::fs;
 use std::path::Path;
-use std::process::Command;
+use std::sync::Arc;
+use std::time::Duration;
 use thiserror::Error;
 
+use events::Progress;
+use lock::BuildLock;
+use staging::StagedOutput;
+
+mod budget;
+mod compat;
+mod compress;
+mod diff;
+mod events;
+mod fingerprint;
+mod hashing;
+mod integrity;
+mod lock;
+mod manifest;
+mod output_guard;
+mod plan;
+mod probe_cache;
+mod project;
+mod public_url;
+mod runner;
+mod staging;
+#[cfg(any(test, feature = "testing"))]
+mod testing;
+mod toolchain;
+mod tools;
+mod trunk;
+mod wasm;
+
+pub use budget::{
+    evaluate_budgets, BudgetCheck, BudgetReport, BudgetStatus, SizeBudgets, BUDGET_WARNING_PERCENT,
+};
+pub use compat::{
+    check_compatibility, parse_typescript_declarations, CompatChange, CompatChangeKind,
+    CompatConfig, CompatReport, ExportSurface,
+};
+pub use compress::{
+    precompress_outputs, CompressedArtifact, PrecompressConfig, PRECOMPRESS_EXTENSIONS,
+};
+pub use diff::{diff_builds, diff_modules, BuildDiff, ModuleDiff, SignatureChange, SizeDelta};
+pub use events::{compiling_crate, phase_for_line, BuildEvent, BuildPhase};
+pub use fingerprint::{build_environment, input_fingerprint};
+pub use hashing::{apply_content_hashes, content_hash, hashed_file_name, CONTENT_HASH_LENGTH};
+pub use integrity::{
+    apply_integrity, apply_integrity_to_html, sri_hash, IntegrityReport, IntegrityResource,
+};
+pub use lock::{BuildLockMode, LOCK_FILE_NAME};
+pub use manifest::{BuildManifest, ManifestArtifact, MANIFEST_FILE_NAME};
+pub use output_guard::check_output_dir;
+pub use plan::{BuildPlan, PlannedCommand};
+pub use probe_cache::{ProbeCache, DEFAULT_PROBE_TTL};
+pub use project::{
+    Decision, DetectionEvidence, EvidenceSource, ProjectModel, STATIC_DIRS, WASM_BINDGEN_CRATES,
+    WEB_FRAMEWORKS,
+};
+pub use public_url::{apply_public_url, normalize_public_url, DEFAULT_PUBLIC_URL};
+pub use runner::{
+    CancellationToken, CommandOutput, CommandRunner, OutputStream, ProcessRunner, StepLimits,
+};
+#[cfg(any(test, feature = "testing"))]
+pub use testing::RecordingRunner;
+pub use toolchain::{
+    parse_active_toolchain, target_in_sysroot, TargetDetection, TargetStatus, Toolchain,
+    ToolchainFile, ToolchainSource, TOOLCHAIN_FILES, WASM_TARGET,
+};
+pub use tools::{
+    find_cargo_lock, locked_version, minimum_version, parse_tool_version, tool_requirement,
+    wasm_bindgen_install_command, ToolInfo, ToolRequirement, PROBED_TOOLS, TOOL_REQUIREMENTS,
+};
+pub use trunk::{
+    TrunkBuildConfig, TrunkConfig, TrunkHook, DEFAULT_TRUNK_DIST, DEFAULT_TRUNK_TARGET,
+    TRUNK_CONFIG_FILES,
+};
+pub use wasm::{ExternalKind, WasmExport, WasmFunction, WasmImport, WasmModule, WasmSection};
+
 // Core plugin types - defined locally since wasmrun-core doesn't exist
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub enum PluginType {
@@ -41,14 +117,88 @@ pub struct PluginInfo {
     pub capabilities: PluginCapabilities,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
 pub enum OptimizationLevel {
     Debug,
+    #[default]
     Release,
     Size,
+    /// Release build that keeps debug info and symbol names
+    Profiling,
+    /// Named cargo profile, e.g. `[profile.wasm-release]`
+    Custom(String),
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+// Cargo keeps its built-in profiles' artifacts under these directories.
+const BUILTIN_PROFILE_DIRS: &[(&str, &str)] = &[
+    ("dev", "debug"),
+    ("test", "debug"),
+    ("release", "release"),
+    ("bench", "release"),
+];
+
+impl OptimizationLevel {
+    pub fn cargo_profile(&self) -> &str {
+        match self {
+            OptimizationLevel::Debug => "dev",
+            OptimizationLevel::Release | OptimizationLevel::Size | OptimizationLevel::Profiling => {
+                "release"
+            }
+            OptimizationLevel::Custom(profile) => profile,
+        }
+    }
+
+    /// Directory under `target/<triple>/` holding this level's artifacts.
+    pub fn profile_dir(&self) -> &str {
+        let profile = self.cargo_profile();
+        BUILTIN_PROFILE_DIRS
+            .iter()
+            .find(|(name, _)| *name == profile)
+            .map_or(profile, |(_, dir)| dir)
+    }
+
+    /// Cargo arguments selecting the profile.
+    pub fn cargo_args(&self) -> Vec<String> {
+        match self {
+            OptimizationLevel::Debug => Vec::new(),
+            OptimizationLevel::Release | OptimizationLevel::Size | OptimizationLevel::Profiling => {
+                vec!["--release".to_string()]
+            }
+            OptimizationLevel::Custom(profile) => vec!["--profile".to_string(), profile.clone()],
+        }
+    }
+
+    /// Environment that turns a plain release build into a profiling one.
+    pub fn cargo_env(&self) -> Vec<(String, String)> {
+        match self {
+            OptimizationLevel::Profiling => vec![
+                (
+                    "CARGO_PROFILE_RELEASE_DEBUG".to_string(),
+                    "true".to_string(),
+                ),
+                (
+                    "CARGO_PROFILE_RELEASE_STRIP".to_string(),
+                    "none".to_string(),
+                ),
+            ],
+            _ => Vec::new(),
+        }
+    }
+
+    /// Decodes the C interface's optimization byte; `profile` names the
+    /// cargo profile for `4`.
+    pub fn from_ffi(optimization: u8, profile: Option<String>) -> Self {
+        match (optimization, profile) {
+            (0, _) => OptimizationLevel::Debug,
+            (2, _) => OptimizationLevel::Size,
+            (3, _) => OptimizationLevel::Profiling,
+            (4, Some(profile)) if !profile.is_empty() => OptimizationLevel::Custom(profile),
+            _ => OptimizationLevel::Release,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct BuildConfig {
     pub input: String,
     pub output_dir: String,
@@ -56,6 +206,28 @@ pub struct BuildConfig {
     pub target_type: String,
     pub verbose: bool,
     pub watch: bool,
+    /// Cargo features to enable
+    #[serde(default)]
+    pub features: Vec<String>,
+    #[serde(default)]
+    pub all_features: bool,
+    #[serde(default)]
+    pub no_default_features: bool,
+    /// rustup toolchain to build with, e.g. `nightly`
+    #[serde(default)]
+    pub toolchain: Option<String>,
+    /// Longest a single tool invocation may run before it is killed
+    #[serde(default)]
+    pub step_timeout: Option<Duration>,
+    /// Stops the build, including the tool currently running
+    #[serde(skip)]
+    pub cancel: Option<CancellationToken>,
+    /// Rebuild even when nothing changed since the last build
+    #[serde(default)]
+    pub force: bool,
+    /// What to do when another build of the project is running
+    #[serde(default)]
+    pub lock: Option<BuildLockMode>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -65,6 +237,19 @@ pub struct BuildResult {
     pub optimization_level: OptimizationLevel,
     pub build_time: std::time::Duration,
     pub file_size: u64,
+    /// Size of the precompressed `.wasm.gz`, when precompression is enabled
+    #[serde(default)]
+    pub gzip_size: Option<u64>,
+    /// Size of the precompressed `.wasm.br`, when precompression is enabled
+    #[serde(default)]
+    pub brotli_size: Option<u64>,
+    /// Base path the build expects to be served from
+    #[serde(default = "default_public_url")]
+    pub public_url: String,
+}
+
+fn default_public_url() -> String {
+    DEFAULT_PUBLIC_URL.to_string()
 }
 
 #[derive(Error, Debug)]
@@ -77,19 +262,58 @@ pub enum CompilationError {
 
     #[error("Invalid configuration: {reason}")]
     InvalidConfiguration { reason: String },
+
+    #[error("Build cancelled for {language}")]
+    Cancelled { language: String },
+
+    #[error("Build step {step} timed out for {language} after {timeout:?}")]
+    TimedOut {
+        language: String,
+        step: String,
+        timeout: std::time::Duration,
+    },
 }
 
 pub type CompilationResult<T> = std::result::Result<T, CompilationError>;
 
-#[derive(Deserialize)]
-struct CargoTomlFull {
-    package: PackageFull,
+// Subset of wasmrun.toml that the Rust plugin reads
+#[derive(Debug, Default, Deserialize)]
+struct WasmrunToml {
+    #[serde(default)]
+    rust: RustSection,
 }
 
-#[derive(Deserialize)]
-struct PackageFull {
-    name: String,
-    version: String,
+#[derive(Debug, Default, Deserialize)]
+struct RustSection {
+    #[serde(default)]
+    budgets: SizeBudgets,
+    #[serde(default)]
+    compat: CompatConfig,
+    #[serde(default)]
+    precompress: Option<PrecompressConfig>,
+    #[serde(default)]
+    content_hash: bool,
+    #[serde(default)]
+    integrity: bool,
+    #[serde(default)]
+    public_url: Option<String>,
+    #[serde(default)]
+    features: Vec<String>,
+    #[serde(default)]
+    all_features: bool,
+    #[serde(default)]
+    no_default_features: bool,
+    #[serde(default)]
+    toolchain: Option<String>,
+    /// Seconds a single build step may run
+    #[serde(default)]
+    step_timeout: Option<u64>,
+    #[serde(default)]
+    prune: bool,
+    #[serde(default)]
+    lock: Option<BuildLockMode>,
+    #[serde(default)]
+    allow_unsafe_output_dir: bool,
 }
 
 #[derive(Error, Debug)]
@@ -108,31 +332,174 @@ pub enum WasmRustError {
 
     #[error("TOML parse error: {0}")]
     TomlParse(#[from] toml::de::Error),
+
+    #[error("JSON error: {0}")]
+    Json(#[from] serde_json::Error),
+
+    #[error("Invalid configuration: {0}")]
+    InvalidConfiguration(String),
+
+    #[error("Invalid WebAssembly module: {0}")]
+    InvalidWasm(String),
+
+    #[error("Breaking export changes: {}", compat::format_changes(.changes))]
+    IncompatibleExports { changes: Vec<CompatChange> },
+
+    #[error("Size budget exceeded: {}", budget::format_violations(.violations))]
+    BudgetExceeded { violations: Vec<BudgetCheck> },
+
+    #[error("Cannot compute integrity for {html_path}: {}", .references.join(", "))]
+    UnhashableReferences {
+        html_path: String,
+        references: Vec<String>,
+    },
+
+    #[error("Build cancelled")]
+    Cancelled,
+
+    #[error("{step} timed out after {timeout:?}")]
+    TimedOut { step: String, timeout: Duration },
+
+    #[error("Build locked: {} is using {path}", lock::format_holder(.pid))]
+    BuildLocked { path: String, pid: Option<u32> },
 }
 
 pub type WasmRustResult<T> = std::result::Result<T, WasmRustError>;
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct CompileConfig {
     pub project_path: String,
     pub output_dir: String,
     pub optimization: OptimizationLevel,
     pub target_type: TargetType,
     pub verbose: bool,
+    /// Size limits checked after the build, merged over `[rust.budgets]` in wasmrun.toml
+    #[serde(default)]
+    pub budgets: SizeBudgets,
+    /// Export-surface snapshot checking, merged over `[rust.compat]` in wasmrun.toml
+    #[serde(default)]
+    pub compat: CompatConfig,
+    /// Write `.gz`/`.br` variants of the outputs; falls back to `[rust.precompress]`
+    #[serde(default)]
+    pub precompress: Option<PrecompressConfig>,
+    /// Rename wasm-bindgen outputs to content-hashed names (trunk already does)
+    #[serde(default)]
+    pub content_hash: bool,
+    /// Add SRI attributes to produced HTML and suggest a CSP; falls back to `[rust] integrity`
+    #[serde(default)]
+    pub integrity: bool,
+    /// Base path the app is served from, e.g. `/apps/demo/`; falls back to `[rust] public_url`
+    #[serde(default)]
+    pub public_url: Option<String>,
+    /// Cargo features to enable; falls back to `[rust] features`
+    #[serde(default)]
+    pub features: Vec<String>,
+    #[serde(default)]
+    pub all_features: bool,
+    #[serde(default)]
+    pub no_default_features: bool,
+    /// rustup toolchain for every cargo invocation (`nightly` or `+nightly`);
+    /// falls back to `[rust] toolchain`, then to what rustup resolves
+    #[serde(default)]
+    pub toolchain: Option<String>,
+    /// Longest a single tool invocation may run before it is killed, and
+    /// the build fails with [`WasmRustError::TimedOut`]; falls back to
+    /// `[rust] step_timeout` (seconds)
+    #[serde(default)]
+    pub step_timeout: Option<Duration>,
+    /// Stops the build, killing the tool currently running; the build fails
+    /// with [`WasmRustError::Cancelled`]
+    #[serde(skip)]
+    pub cancel: Option<CancellationToken>,
+    /// Rebuild even when the inputs match the last build's fingerprint
+    #[serde(default)]
+    pub force: bool,
+    /// Remove files in `output_dir` that this build did not produce; falls
+    /// back to `[rust] prune`
+    #[serde(default)]
+    pub prune: bool,
+    /// What to do when another build of the project or into `output_dir`
+    /// is running; falls back to `[rust] lock`, then to waiting
+    #[serde(default)]
+    pub lock: Option<BuildLockMode>,
+    /// Skip [`check_output_dir`] and build into `output_dir` even when it
+    /// overlaps the project's sources; falls back to
+    /// `[rust] allow_unsafe_output_dir`
+    #[serde(default)]
+    pub allow_unsafe_output_dir: bool,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+impl CompileConfig {
+    pub fn step_limits(&self) -> StepLimits {
+        StepLimits {
+            cancel: self.cancel.clone(),
+            timeout: self.step_timeout,
+        }
+    }
+
+    /// Fails with [`WasmRustError::Cancelled`] once the build was cancelled.
+    pub fn check_cancelled(&self) -> WasmRustResult<()> {
+        if self.step_limits().is_cancelled() {
+            return Err(WasmRustError::Cancelled);
+        }
+        Ok(())
+    }
+
+    /// Feature flags in the form cargo, trunk and wasm-pack's cargo
+    /// passthrough all accept.
+    pub fn feature_args(&self) -> Vec<String> {
+        let mut args = Vec::new();
+        if !self.features.is_empty() {
+            args.push("--features".to_string());
+            args.push(self.features.join(","));
+        }
+        if self.all_features {
+            args.push("--all-features".to_string());
+        }
+        if self.no_default_features {
+            args.push("--no-default-features".to_string());
+        }
+        args
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
 pub enum TargetType {
+    #[default]
     Wasm,
     WebApp,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
 pub struct CompileResult {
     pub wasm_path: String,
     pub js_path: Option<String>,
     pub additional_files: Vec<String>,
     pub is_webapp: bool,
+    /// Budget checks for this build, present when any size budget is configured
+    #[serde(default)]
+    pub budget_report: Option<BudgetReport>,
+    /// Build manifest written into the output directory
+    #[serde(default)]
+    pub manifest_path: Option<String>,
+    /// Export changes against the configured snapshot
+    #[serde(default)]
+    pub compat_report: Option<CompatReport>,
+    /// Precompressed variants written next to the outputs
+    #[serde(default)]
+    pub compressed: Vec<CompressedArtifact>,
+    /// Original -> content-hashed file names, when content hashing is enabled
+    #[serde(default)]
+    pub hashed_files: BTreeMap<String, String>,
+    /// SRI attributes and suggested CSP for each HTML output
+    #[serde(default)]
+    pub integrity: Vec<IntegrityReport>,
+    /// Normalized base path the outputs were built for, when one is configured
+    #[serde(default)]
+    pub public_url: Option<String>,
+    /// Whether the inputs were unchanged and the previous build was reused
+    #[serde(default)]
+    pub cached: bool,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -143,16 +510,29 @@ pub struct ProjectInfo {
     pub build_strategy: BuildStrategy,
     pub frameworks: Vec<String>,
     pub dependencies: DependencyStatus,
+    /// `[[hooks]]` from Trunk.toml, which trunk runs as part of the build
+    #[serde(default)]
+    pub trunk_hooks: Vec<TrunkHook>,
+    /// `[features]` table of the crate: feature name -> what it enables
+    #[serde(default)]
+    pub features: BTreeMap<String, Vec<String>>,
+    /// Toolchain the project resolves to, and where that came from
+    #[serde(default)]
+    pub toolchain: Toolchain,
+    /// What led to `project_type` and `build_strategy`, including the
+    /// candidates that were rejected
+    #[serde(default)]
+    pub evidence: Vec<DetectionEvidence>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
 pub enum ProjectType {
     StandardWasm,
     WasmBindgen,
     WebApplication,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
 pub enum BuildStrategy {
     Cargo,
     WasmPack,
@@ -165,11 +545,19 @@ pub struct DependencyStatus {
     pub optional: Vec<DependencyCheck>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct DependencyCheck {
     pub name: String,
     pub available: bool,
     pub reason: String,
+    #[serde(default)]
+    pub version: Option<String>,
+    /// Set when the strategy needs at least this version of the tool
+    #[serde(default)]
+    pub minimum_version: Option<String>,
+    /// Command that resolves a failed check
+    #[serde(default)]
+    pub fix: Option<String>,
 }
 
 // Plugin trait definitions
@@ -182,6 +570,16 @@ pub trait Plugin {
 pub trait WasmBuilder {
     fn can_handle_project(&self, project_path: &str) -> bool;
     fn build(&self, config: &BuildConfig) -> CompilationResult<BuildResult>;
+    /// Builds while reporting progress to `on_event`. Builders that have no
+    /// events to report just build.
+    fn build_with_events(
+        &self,
+        config: &BuildConfig,
+        on_event: &dyn Fn(&BuildEvent),
+    ) -> CompilationResult<BuildResult> {
+        let _ = on_event;
+        self.build(config)
+    }
     fn check_dependencies(&self) -> Vec<String>;
     fn validate_project(&self, project_path: &str) -> CompilationResult<()>;
     fn clean(&self, project_path: &str) -> std::result::Result<(), Box<dyn std::error::Error>>;
@@ -214,12 +612,45 @@ fn copy_dir_recursive(from: &Path, to: &Path) -> WasmRustResult<()> {
     Ok(())
 }
 
+/// Clones share one [`ProbeCache`] and [`CommandRunner`].
 #[derive(Clone)]
-pub struct WasmRustPlugin;
+pub struct WasmRustPlugin {
+    probes: Arc<ProbeCache>,
+    runner: Arc<dyn CommandRunner>,
+}
 
 impl WasmRustPlugin {
     pub fn new() -> Self {
-        Self
+        Self::with_probe_ttl(DEFAULT_PROBE_TTL)
+    }
+
+    /// A plugin that reuses toolchain probe results for `ttl`. A zero TTL
+    /// probes on every call.
+    pub fn with_probe_ttl(ttl: Duration) -> Self {
+        Self::with_runner(Arc::new(ProcessRunner), ttl)
+    }
+
+    /// A plugin that runs every external process, builds and probes alike,
+    /// through `runner`.
+    pub fn with_runner(runner: Arc<dyn CommandRunner>, ttl: Duration) -> Self {
+        Self {
+            probes: Arc::new(ProbeCache::with_runner(ttl, runner.clone())),
+            runner,
+        }
+    }
+
+    pub fn runner(&self) -> &dyn CommandRunner {
+        self.runner.as_ref()
+    }
+
+    pub fn probe_cache(&self) -> &ProbeCache {
+        &self.probes
+    }
+
+    /// Forgets cached tool versions, toolchains and installed targets, e.g.
+    /// after installing a tool while the plugin is running.
+    pub fn refresh_probes(&self) {
+        self.probes.clear();
     }
 
     pub fn can_handle(&self, project_path: &str) -> bool {
@@ -230,195 +661,686 @@ impl WasmRustPlugin {
     pub fn check_dependencies(&self) -> Vec<String> {
         let mut missing = Vec::new();
 
-        if !self.is_tool_available("cargo") {
-            missing.push("cargo (Rust toolchain)".to_string());
-        }
-
-        if !self.is_tool_available("rustc") {
-            missing.push("rustc (Rust compiler)".to_string());
+        for (tool, description) in [("cargo", "Rust toolchain"), ("rustc", "Rust compiler")] {
+            let info = self.tool_info(tool);
+            let minimum = tool_requirement(tool).map(ToolRequirement::minimum_version);
+            if !info.is_available() {
+                missing.push(format!("{tool} ({description})"));
+            } else if let Some(minimum) = minimum.filter(|minimum| info.is_older_than(minimum)) {
+                missing.push(format!("{info} ({description}) is older than {minimum}"));
+            }
         }
 
-        if !self.is_wasm_target_installed() {
-            missing.push("wasm32-unknown-unknown target".to_string());
+        let target = self.wasm_target_status();
+        if !target.installed {
+            missing.push(format!(
+                "{WASM_TARGET} target (checked via {})",
+                target.method
+            ));
         }
 
         missing
     }
 
     pub fn inspect_project(&self, project_path: &str) -> WasmRustResult<ProjectInfo> {
-        let cargo_toml_path = Path::new(project_path).join("Cargo.toml");
-        if !cargo_toml_path.exists() {
-            return Err(WasmRustError::InvalidProject(
-                "No Cargo.toml found".to_string(),
-            ));
+        let project = ProjectModel::load(Path::new(project_path))?;
+        let toolchain = self.probes.toolchain(Path::new(project_path), None)?;
+        let mut dependencies = self.check_dependencies_comprehensive(
+            &project.project_type,
+            &project.build_strategy,
+            &toolchain,
+        );
+        if let Some(locked) = &project.locked_wasm_bindgen {
+            let (check, required) = self.wasm_bindgen_check(locked);
+            if required {
+                dependencies.required.push(check);
+            } else {
+                dependencies.optional.push(check);
+            }
         }
 
-        let content = fs::read_to_string(&cargo_toml_path)?;
-        let cargo_toml: CargoTomlFull = toml::from_str(&content)?;
-
-        let name = cargo_toml.package.name.clone();
-        let version = cargo_toml.package.version.clone();
-
-        let (project_type, frameworks) =
-            self.detect_project_type_and_frameworks(project_path, &content);
-        let build_strategy = self.determine_build_strategy(project_path, &project_type);
-        let dependencies = self.check_dependencies_comprehensive(&project_type, &build_strategy);
-
         Ok(ProjectInfo {
-            name,
-            version,
-            project_type,
-            build_strategy,
-            frameworks,
+            name: project.name,
+            version: project.version,
+            project_type: project.project_type,
+            build_strategy: project.build_strategy,
+            frameworks: project.frameworks,
             dependencies,
+            trunk_hooks: project.trunk.map(|trunk| trunk.hooks).unwrap_or_default(),
+            features: project.features,
+            toolchain,
+            evidence: project.evidence,
         })
     }
 
-    fn detect_project_type_and_frameworks(
+    fn check_dependencies_comprehensive(
         &self,
-        project_path: &str,
-        cargo_toml_content: &str,
-    ) -> (ProjectType, Vec<String>) {
-        let mut frameworks = Vec::new();
+        _project_type: &ProjectType,
+        build_strategy: &BuildStrategy,
+        toolchain: &Toolchain,
+    ) -> DependencyStatus {
+        let mut required = Vec::new();
+        let mut optional = Vec::new();
 
-        let web_frameworks = [
-            "yew", "leptos", "dioxus", "sycamore", "mogwai", "seed", "percy", "iced", "dodrio",
-            "smithy",
-        ];
+        required.push(self.tool_check("cargo", "Rust build tool", build_strategy, toolchain));
+
+        required.push(self.tool_check("rustc", "Rust compiler", build_strategy, toolchain));
+
+        let target = self.probes.target(toolchain, WASM_TARGET);
+        required.push(DependencyCheck {
+            name: WASM_TARGET.to_string(),
+            available: target.installed,
+            reason: format!(
+                "WebAssembly compilation target for {toolchain} (checked via {})",
+                target.method
+            ),
+            ..Default::default()
+        });
+
+        for component in &toolchain.components {
+            required.push(DependencyCheck {
+                name: component.clone(),
+                available: self.probes.component(toolchain, component),
+                reason: "Component listed in the toolchain file".to_string(),
+                ..Default::default()
+            });
+        }
 
-        for framework in web_frameworks {
-            if cargo_toml_content.contains(framework) {
-                frameworks.push(framework.to_string());
+        match build_strategy {
+            BuildStrategy::WasmPack => {
+                required.push(self.tool_check(
+                    "wasm-pack",
+                    "Required for wasm-bindgen projects",
+                    build_strategy,
+                    toolchain,
+                ));
+            }
+            BuildStrategy::Trunk => {
+                required.push(self.tool_check(
+                    "trunk",
+                    "Required for web application builds",
+                    build_strategy,
+                    toolchain,
+                ));
+
+                optional.push(self.tool_check(
+                    "wasm-pack",
+                    "Useful for optimized builds",
+                    build_strategy,
+                    toolchain,
+                ));
+            }
+            BuildStrategy::Cargo => {
+                optional.push(self.tool_check(
+                    "wasm-pack",
+                    "Useful for advanced WASM features",
+                    build_strategy,
+                    toolchain,
+                ));
             }
         }
 
-        let wasm_bindgen_deps = ["wasm-bindgen", "web-sys", "js-sys"];
-        let has_wasm_bindgen = wasm_bindgen_deps
-            .iter()
-            .any(|dep| cargo_toml_content.contains(dep));
+        optional.push(self.tool_check(
+            "rustup",
+            "Rust toolchain manager",
+            build_strategy,
+            toolchain,
+        ));
+
+        if !toolchain.components.iter().any(|c| c == "rust-src") {
+            optional.push(DependencyCheck {
+                name: "rust-src".to_string(),
+                available: self.probes.component(toolchain, "rust-src"),
+                reason: "Needed for -Zbuild-std builds (threads, atomics)".to_string(),
+                ..Default::default()
+            });
+        }
+
+        optional.push(self.tool_check(
+            "wasm-opt",
+            "WebAssembly optimizer",
+            build_strategy,
+            toolchain,
+        ));
+
+        DependencyStatus { required, optional }
+    }
 
-        if cargo_toml_content.contains("trunk") {
-            frameworks.push("trunk".to_string());
+    /// The `wasm-bindgen` CLI refuses modules linked against any other
+    /// version of the library, so an installed CLI must match the version
+    /// locked in Cargo.lock exactly. Without a CLI on PATH the check is
+    /// optional: wasm-pack and trunk download the matching release.
+    fn wasm_bindgen_check(&self, locked: &semver::Version) -> (DependencyCheck, bool) {
+        let cli = self.tool_info("wasm-bindgen");
+        let fix = Some(wasm_bindgen_install_command(locked));
+        let version = cli.version.as_ref().map(ToString::to_string);
+
+        if !cli.is_available() {
+            let check = DependencyCheck {
+                name: "wasm-bindgen".to_string(),
+                reason: format!("CLI for wasm-bindgen {locked} locked in Cargo.lock"),
+                fix,
+                ..Default::default()
+            };
+            return (check, false);
         }
 
-        let project_type = if !frameworks.is_empty() || self.is_rust_web_application(project_path) {
-            ProjectType::WebApplication
-        } else if has_wasm_bindgen {
-            ProjectType::WasmBindgen
-        } else {
-            ProjectType::StandardWasm
+        let matches = cli.version.as_ref().is_some_and(|v| {
+            v.major == locked.major
+                && v.minor == locked.minor
+                && v.patch == locked.patch
+                && v.pre == locked.pre
+        });
+        let check = DependencyCheck {
+            name: "wasm-bindgen".to_string(),
+            available: matches,
+            reason: if matches {
+                format!("CLI matches wasm-bindgen {locked} locked in Cargo.lock")
+            } else {
+                format!("CLI does not match wasm-bindgen {locked} locked in Cargo.lock")
+            },
+            version: version.or(cli.version_output),
+            fix: (!matches).then_some(fix).flatten(),
+            ..Default::default()
         };
+        (check, true)
+    }
 
-        (project_type, frameworks)
+    /// Builds the project. With `verbose` set, the tools' output is printed
+    /// as it arrives.
+    pub fn compile(&self, config: &CompileConfig) -> WasmRustResult<CompileResult> {
+        self.compile_with_events(config, &|event| {
+            if config.verbose {
+                events::print_verbose_event(event);
+            }
+        })
     }
 
-    fn determine_build_strategy(
+    /// Builds the project, reporting phases, crate progress and each line of
+    /// tool output to `on_event` as they happen. The last event is always
+    /// [`BuildEvent::Finished`].
+    pub fn compile_with_events(
         &self,
-        project_path: &str,
-        project_type: &ProjectType,
-    ) -> BuildStrategy {
-        match project_type {
-            ProjectType::StandardWasm => BuildStrategy::Cargo,
-            ProjectType::WasmBindgen => BuildStrategy::WasmPack,
-            ProjectType::WebApplication => {
-                let uses_trunk = Path::new(project_path).join("Trunk.toml").exists()
-                    || Path::new(project_path).join("trunk.toml").exists();
-
-                if uses_trunk {
-                    BuildStrategy::Trunk
-                } else {
-                    BuildStrategy::WasmPack
-                }
+        config: &CompileConfig,
+        on_event: &dyn Fn(&BuildEvent),
+    ) -> WasmRustResult<CompileResult> {
+        let total = tools::locked_package_count(Path::new(&config.project_path));
+        let progress = Progress::new(on_event, total);
+        let result = self.compile_inner(config, &progress);
+        progress.emit(BuildEvent::Finished {
+            success: result.is_ok(),
+        });
+        result
+    }
+
+    fn compile_inner(
+        &self,
+        config: &CompileConfig,
+        progress: &Progress,
+    ) -> WasmRustResult<CompileResult> {
+        progress.phase(BuildPhase::Resolving);
+        let project = ProjectModel::load(Path::new(&config.project_path))?;
+        let project_config = self.load_wasmrun_toml(&config.project_path)?;
+        let config = &self.resolve_config(config, &project_config);
+
+        if !config.allow_unsafe_output_dir {
+            check_output_dir(Path::new(&config.project_path), &config.output_dir)?;
+        }
+        if let Some(parent) = Path::new(&config.output_dir).parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::create_dir_all(&config.output_dir)?;
+
+        let lock_mode = config.lock.unwrap_or_default();
+        let lock = BuildLock::acquire(
+            Path::new(&config.project_path),
+            Path::new(&config.output_dir),
+            lock_mode,
+            &config.step_limits(),
+            &mut |path, pid| {
+                progress.emit(BuildEvent::Waiting {
+                    lock: path.display().to_string(),
+                    pid,
+                })
+            },
+        )?;
+
+        let toolchain = self
+            .probes
+            .toolchain(Path::new(&config.project_path), config.toolchain.as_deref())?;
+        toolchain.validate(self.runner())?;
+
+        let route = self.build_route(&project);
+        let fingerprint = self.build_fingerprint(config, &project, &toolchain, &route)?;
+        // A coalescing build that waited takes over what the build it waited
+        // for just produced, even when forced.
+        let coalesce = lock.waited && lock_mode == BuildLockMode::Coalesce;
+        if !config.force || coalesce {
+            if let Some(result) = fingerprint
+                .as_deref()
+                .and_then(|fingerprint| self.cached_build(config, fingerprint))
+            {
+                progress.message(format!(
+                    "Inputs unchanged, reusing the build in {}",
+                    config.output_dir
+                ));
+                // The snapshot is not a build input, so the stored report
+                // may predate edits to it.
+                let result = CompileResult {
+                    compat_report: None,
+                    ..result
+                };
+                return self.check_export_compatibility(config, &project_config, result, progress);
+            }
+        }
+
+        config.check_cancelled()?;
+        // Everything below writes into a staging copy of the output
+        // directory, which replaces it only once the build succeeded.
+        let staged = StagedOutput::prepare(
+            &config.output_dir,
+            Path::new(&config.project_path),
+            config.prune,
+        )?;
+        let config = &CompileConfig {
+            output_dir: staged.path().to_string_lossy().to_string(),
+            ..config.clone()
+        };
+
+        let mut result = match route {
+            BuildStrategy::Trunk => {
+                self.compile_with_trunk(config, &project, &toolchain, progress)?
             }
+            BuildStrategy::WasmPack if project.is_web_application() => {
+                self.compile_web_application(config, &project, &toolchain, progress)?
+            }
+            BuildStrategy::WasmPack => {
+                self.compile_wasm_bindgen(config, &project, &toolchain, progress)?
+            }
+            BuildStrategy::Cargo => {
+                self.compile_standard_wasm(config, &project, &toolchain, progress)?
+            }
+        };
+        config.check_cancelled()?;
+        progress.phase(BuildPhase::Bundling);
+        result.public_url = config.public_url.clone().or(result.public_url);
+
+        // Includes a Trunk.toml public_url when the config has none.
+        let public_url = result.public_url.clone();
+        let public_url = public_url.as_deref().unwrap_or(DEFAULT_PUBLIC_URL);
+        if !result.is_webapp {
+            apply_public_url(&result, Path::new(&config.output_dir), public_url)?;
         }
+
+        // Trunk hashes its own outputs; only the wasm-bindgen path needs it.
+        // Runs after every rewrite of the glue, so hashed names match the
+        // final contents.
+        let is_bindgen_output = result.js_path.is_some() && !result.is_webapp;
+        if is_bindgen_output && (config.content_hash || project_config.rust.content_hash) {
+            result.hashed_files = apply_content_hashes(&mut result, Path::new(&config.output_dir))?;
+        }
+
+        // Runs after hashing so the attributes cover the final file contents.
+        if config.integrity || project_config.rust.integrity {
+            result.integrity = apply_integrity(&result, Path::new(&config.output_dir), public_url)?;
+        }
+
+        let result = self.enforce_budgets(config, &project_config, result, progress)?;
+        let mut result =
+            self.check_export_compatibility(config, &project_config, result, progress)?;
+
+        if let Some(precompress) = config
+            .precompress
+            .as_ref()
+            .or(project_config.rust.precompress.as_ref())
+        {
+            result.compressed = precompress_outputs(precompress, &result)?;
+        }
+
+        let result =
+            self.write_manifest(config, &staged, &project, fingerprint.as_deref(), result)?;
+        staged.commit()?;
+        Ok(result)
     }
 
-    fn check_dependencies_comprehensive(
+    /// Fingerprint of what a build of `config` depends on: the watched
+    /// sources, the HTML entry page, build scripts, path and workspace
+    /// dependencies, Cargo.lock, wasmrun.toml, the configuration, the build
+    /// environment and the toolchain and tools `route` runs. `None` when
+    /// those inputs cannot all be found, which turns the cache off.
+    fn build_fingerprint(
         &self,
-        _project_type: &ProjectType,
-        build_strategy: &BuildStrategy,
-    ) -> DependencyStatus {
-        let mut required = Vec::new();
-        let mut optional = Vec::new();
+        config: &CompileConfig,
+        project: &ProjectModel,
+        toolchain: &Toolchain,
+        route: &BuildStrategy,
+    ) -> WasmRustResult<Option<String>> {
+        let project_path = Path::new(&config.project_path);
+        let Some(mut inputs) = fingerprint::dependency_inputs(project_path) else {
+            return Ok(None);
+        };
+        inputs.extend(self.get_watch_paths(&config.project_path));
+        inputs.extend(find_cargo_lock(project_path));
+        inputs.push(project_path.join("wasmrun.toml"));
+        inputs.push(project_path.join("index.html"));
+        if let Some(trunk) = &project.trunk {
+            inputs.push(project_path.join(trunk.target()));
+        }
 
-        required.push(DependencyCheck {
-            name: "cargo".to_string(),
-            available: self.is_tool_available("cargo"),
-            reason: "Rust build tool".to_string(),
-        });
+        let builder = match route {
+            BuildStrategy::Cargo => None,
+            BuildStrategy::WasmPack => Some("wasm-pack"),
+            BuildStrategy::Trunk => Some("trunk"),
+        };
+        let tools: Vec<ToolInfo> = ["cargo", "rustc"]
+            .into_iter()
+            .chain(builder)
+            .map(|tool| self.probes.tool(tool, &toolchain.env()))
+            .collect();
 
-        required.push(DependencyCheck {
-            name: "rustc".to_string(),
-            available: self.is_tool_available("rustc"),
-            reason: "Rust compiler".to_string(),
-        });
+        input_fingerprint(config, &inputs, toolchain, &tools, &build_environment()).map(Some)
+    }
 
-        required.push(DependencyCheck {
-            name: "wasm32-unknown-unknown".to_string(),
-            available: self.is_wasm_target_installed(),
-            reason: "WebAssembly compilation target".to_string(),
+    /// The result recorded in the output directory's manifest, if it was
+    /// built from `fingerprint` and its artifacts are still in place.
+    fn cached_build(&self, config: &CompileConfig, fingerprint: &str) -> Option<CompileResult> {
+        let output_dir = Path::new(&config.output_dir);
+        let manifest = BuildManifest::load(output_dir).ok()?;
+        if manifest.fingerprint.as_deref() != Some(fingerprint) {
+            return None;
+        }
+        let intact = manifest.artifacts.iter().all(|artifact| {
+            fs::metadata(output_dir.join(&artifact.path))
+                .is_ok_and(|metadata| metadata.len() == artifact.size)
         });
+        let result = manifest.result.filter(|_| intact)?;
+        Some(CompileResult {
+            cached: true,
+            ..result
+        })
+    }
 
-        match build_strategy {
+    /// The commands `compile` would run for `config` and the files it is
+    /// expected to produce. Nothing is executed or written.
+    pub fn plan(&self, config: &CompileConfig) -> WasmRustResult<BuildPlan> {
+        let project = ProjectModel::load(Path::new(&config.project_path))?;
+        let project_config = self.load_wasmrun_toml(&config.project_path)?;
+        let config = &self.resolve_config(config, &project_config);
+        if !config.allow_unsafe_output_dir {
+            check_output_dir(Path::new(&config.project_path), &config.output_dir)?;
+        }
+
+        let toolchain = self
+            .probes
+            .toolchain(Path::new(&config.project_path), config.toolchain.as_deref())?;
+        toolchain.validate(self.runner())?;
+
+        let build_strategy = self.build_route(&project);
+        let output_dir = Path::new(&config.output_dir);
+        let mut commands = Vec::new();
+        let mut notes = Vec::new();
+        if build_strategy != project.build_strategy {
+            notes.push("trunk is not installed; building with wasm-pack instead".to_string());
+        }
+        let fingerprint = self.build_fingerprint(config, &project, &toolchain, &build_strategy)?;
+        let cached = fingerprint
+            .as_deref()
+            .is_some_and(|fingerprint| self.cached_build(config, fingerprint).is_some());
+        if !config.force && cached {
+            notes.push(
+                "inputs are unchanged since the last build; compile will reuse it \
+                 unless forced"
+                    .to_string(),
+            );
+        }
+
+        let mut outputs = match build_strategy {
+            BuildStrategy::Cargo => {
+                commands.extend(self.target_setup_commands(&toolchain, &mut notes));
+                commands.push(plan::cargo_build_command(config, &toolchain));
+                vec![output_dir.join(format!("{}.wasm", project.package_name()))]
+            }
             BuildStrategy::WasmPack => {
-                required.push(DependencyCheck {
-                    name: "wasm-pack".to_string(),
-                    available: self.is_tool_available("wasm-pack"),
-                    reason: "Required for wasm-bindgen projects".to_string(),
-                });
+                if !self.is_tool_available("wasm-pack") {
+                    notes.push("wasm-pack is not installed".to_string());
+                }
+                commands.push(plan::wasm_pack_command(config, &toolchain)?);
+                let package_name = project.package_name();
+                let mut outputs = vec![
+                    output_dir.join(format!("{package_name}_bg.wasm")),
+                    output_dir.join(format!("{package_name}.js")),
+                ];
+                if project.is_web_application() && project.has_index_html {
+                    outputs.push(output_dir.join("index.html"));
+                }
+                outputs
             }
             BuildStrategy::Trunk => {
-                required.push(DependencyCheck {
-                    name: "trunk".to_string(),
-                    available: self.is_tool_available("trunk"),
-                    reason: "Required for web application builds".to_string(),
-                });
+                commands.extend(self.target_setup_commands(&toolchain, &mut notes));
+                let trunk_config = project.trunk.clone().unwrap_or_default();
+                let invocation = plan::trunk_command(config, &trunk_config, &toolchain)?;
+                commands.push(invocation.command);
+                if !invocation.direct_output {
+                    notes.push(format!(
+                        "trunk writes to {} and the result is copied to {}",
+                        invocation.dist.display(),
+                        output_dir.display()
+                    ));
+                }
+                vec![output_dir.join(trunk_config.html_file_name())]
+            }
+        };
 
-                optional.push(DependencyCheck {
-                    name: "wasm-pack".to_string(),
-                    available: self.is_tool_available("wasm-pack"),
-                    reason: "Useful for optimized builds".to_string(),
-                });
+        let is_bindgen_output =
+            build_strategy == BuildStrategy::WasmPack && !project.is_web_application();
+        if is_bindgen_output && (config.content_hash || project_config.rust.content_hash) {
+            notes.push("output names gain a content hash".to_string());
+        }
+        outputs.push(output_dir.join(MANIFEST_FILE_NAME));
+        if config.prune {
+            notes.push(format!(
+                "files in {} that this build does not produce are removed",
+                output_dir.display()
+            ));
+        }
+
+        Ok(BuildPlan {
+            project_type: project.project_type,
+            build_strategy,
+            toolchain,
+            commands,
+            outputs,
+            notes,
+        })
+    }
+
+    /// Merges the project's wasmrun.toml into `config` for the settings the
+    /// build tools themselves need.
+    fn resolve_config(
+        &self,
+        config: &CompileConfig,
+        project_config: &WasmrunToml,
+    ) -> CompileConfig {
+        CompileConfig {
+            public_url: config
+                .public_url
+                .as_deref()
+                .or(project_config.rust.public_url.as_deref())
+                .map(normalize_public_url),
+            features: if config.features.is_empty() {
+                project_config.rust.features.clone()
+            } else {
+                config.features.clone()
+            },
+            all_features: config.all_features || project_config.rust.all_features,
+            no_default_features: config.no_default_features
+                || project_config.rust.no_default_features,
+            toolchain: config
+                .toolchain
+                .clone()
+                .or_else(|| project_config.rust.toolchain.clone()),
+            step_timeout: config
+                .step_timeout
+                .or(project_config.rust.step_timeout.map(Duration::from_secs)),
+            prune: config.prune || project_config.rust.prune,
+            lock: config.lock.or(project_config.rust.lock),
+            allow_unsafe_output_dir: config.allow_unsafe_output_dir
+                || project_config.rust.allow_unsafe_output_dir,
+            ..config.clone()
+        }
+    }
+
+    /// The strategy that will actually run: trunk projects fall back to
+    /// wasm-pack when trunk is not installed.
+    fn build_route(&self, project: &ProjectModel) -> BuildStrategy {
+        if project.build_strategy == BuildStrategy::Trunk && !self.is_tool_available("trunk") {
+            BuildStrategy::WasmPack
+        } else {
+            project.build_strategy.clone()
+        }
+    }
+
+    /// `rustup` invocations `ensure_wasm32_target` would make.
+    fn target_setup_commands(
+        &self,
+        toolchain: &Toolchain,
+        notes: &mut Vec<String>,
+    ) -> Vec<PlannedCommand> {
+        let mut commands = Vec::new();
+        let target = self.probes.target(toolchain, WASM_TARGET);
+        if !target.installed {
+            if target.method == TargetDetection::Rustup {
+                commands.push(plan::rustup_command(
+                    toolchain,
+                    &["target", "add", WASM_TARGET],
+                ));
+            } else {
+                notes.push(format!(
+                    "{WASM_TARGET} is not installed and rustup is not available to add it"
+                ));
             }
-            BuildStrategy::Cargo => {
-                optional.push(DependencyCheck {
-                    name: "wasm-pack".to_string(),
-                    available: self.is_tool_available("wasm-pack"),
-                    reason: "Useful for advanced WASM features".to_string(),
-                });
+        }
+        for component in &toolchain.components {
+            if !self.probes.component(toolchain, component) {
+                commands.push(plan::rustup_command(
+                    toolchain,
+                    &["component", "add", component],
+                ));
             }
         }
+        commands
+    }
 
-        optional.push(DependencyCheck {
-            name: "rustup".to_string(),
-            available: self.is_tool_available("rustup"),
-            reason: "Rust toolchain manager".to_string(),
-        });
+    fn check_export_compatibility(
+        &self,
+        config: &CompileConfig,
+        project_config: &WasmrunToml,
+        mut result: CompileResult,
+        progress: &Progress,
+    ) -> WasmRustResult<CompileResult> {
+        let compat = config.compat.or(&project_config.rust.compat);
+        let Some(snapshot) = &compat.snapshot else {
+            return Ok(result);
+        };
 
-        optional.push(DependencyCheck {
-            name: "wasm-opt".to_string(),
-            available: self.is_tool_available("wasm-opt"),
-            reason: "WebAssembly optimizer".to_string(),
-        });
+        let snapshot_path = Path::new(&config.project_path).join(snapshot);
+        let surface = ExportSurface::from_result(&result)?;
 
-        DependencyStatus { required, optional }
+        if !snapshot_path.exists() {
+            progress.message(format!(
+                "Recording export snapshot: {}",
+                snapshot_path.display()
+            ));
+            surface.write(&snapshot_path)?;
+            return Ok(result);
+        }
+
+        let report = check_compatibility(&ExportSurface::load(&snapshot_path)?, &surface);
+
+        // Debug builds are iterated on constantly; only release-style builds
+        // are held to the recorded surface.
+        let enforce = config.optimization != OptimizationLevel::Debug && !compat.allow_breaking;
+        if report.is_breaking() && enforce {
+            return Err(WasmRustError::IncompatibleExports {
+                changes: report.breaking,
+            });
+        }
+
+        if compat.update_snapshot {
+            surface.write(&snapshot_path)?;
+        }
+
+        result.compat_report = Some(report);
+        Ok(result)
     }
 
-    pub fn compile(&self, config: &CompileConfig) -> WasmRustResult<CompileResult> {
-        if let Some(parent) = Path::new(&config.output_dir).parent() {
-            fs::create_dir_all(parent)?;
+    /// Writes the manifest into the staging directory and returns `result`
+    /// with its paths where they will be once `staged` is committed.
+    fn write_manifest(
+        &self,
+        config: &CompileConfig,
+        staged: &StagedOutput,
+        project: &ProjectModel,
+        fingerprint: Option<&str>,
+        mut result: CompileResult,
+    ) -> WasmRustResult<CompileResult> {
+        let output_dir = staged.path();
+        result.manifest_path = Some(
+            output_dir
+                .join(MANIFEST_FILE_NAME)
+                .to_string_lossy()
+                .to_string(),
+        );
+        let mut manifest = BuildManifest {
+            package: project.name.clone(),
+            version: project.version.clone(),
+            optimization: config.optimization.clone(),
+            target_type: config.target_type.clone(),
+            fingerprint: fingerprint.map(String::from),
+            ..BuildManifest::from_result(&result, output_dir)?
+        };
+        let result = staged.relocate(&result)?;
+        manifest.result = Some(result.clone());
+
+        manifest.write(output_dir)?;
+        Ok(result)
+    }
+
+    fn enforce_budgets(
+        &self,
+        config: &CompileConfig,
+        project_config: &WasmrunToml,
+        mut result: CompileResult,
+        progress: &Progress,
+    ) -> WasmRustResult<CompileResult> {
+        let budgets = config.budgets.or(&project_config.rust.budgets);
+        if budgets.is_empty() {
+            return Ok(result);
         }
-        fs::create_dir_all(&config.output_dir)?;
 
-        if self.uses_wasm_bindgen(&config.project_path) {
-            if self.is_rust_web_application(&config.project_path) {
-                self.compile_web_application(config)
-            } else {
-                self.compile_wasm_bindgen(config)
-            }
-        } else {
-            self.compile_standard_wasm(config)
+        let report = evaluate_budgets(&budgets, &result)?;
+        if report.is_exceeded() {
+            return Err(WasmRustError::BudgetExceeded {
+                violations: report.violations().into_iter().cloned().collect(),
+            });
         }
+
+        for warning in report.warnings() {
+            progress.message(format!("Size budget warning: {warning}"));
+        }
+
+        result.budget_report = Some(report);
+        Ok(result)
+    }
+
+    fn load_wasmrun_toml(&self, project_path: &str) -> WasmRustResult<WasmrunToml> {
+        let path = Path::new(project_path).join("wasmrun.toml");
+        if !path.exists() {
+            return Ok(WasmrunToml::default());
+        }
+
+        let content = fs::read_to_string(path)?;
+        Ok(toml::from_str(&content)?)
     }
 
     pub fn compile_for_aot(&self, project_path: &str, output_dir: &str) -> WasmRustResult<String> {
@@ -432,6 +1354,7 @@ impl WasmRustPlugin {
                 TargetType::Wasm
             },
             verbose: false,
+            ..Default::default()
         };
 
         let result = self.compile(&config)?;
@@ -454,6 +1377,7 @@ impl WasmRustPlugin {
                 TargetType::Wasm
             },
             verbose: false,
+            ..Default::default()
         };
 
         let result = self.compile(&config)?;
@@ -470,77 +1394,26 @@ impl WasmRustPlugin {
         }
     }
 
-    fn uses_wasm_bindgen(&self, project_path: &str) -> bool {
-        let cargo_toml_path = Path::new(project_path).join("Cargo.toml");
-
-        if let Ok(cargo_toml) = fs::read_to_string(cargo_toml_path) {
-            cargo_toml.contains("wasm-bindgen")
-                || cargo_toml.contains("web-sys")
-                || cargo_toml.contains("js-sys")
-        } else {
-            false
-        }
-    }
-
     fn is_rust_web_application(&self, project_path: &str) -> bool {
-        let cargo_toml_path = Path::new(project_path).join("Cargo.toml");
-
-        if let Ok(cargo_toml) = fs::read_to_string(cargo_toml_path) {
-            if !self.uses_wasm_bindgen(project_path) {
-                return false;
-            }
-
-            let web_frameworks = [
-                "yew", "leptos", "dioxus", "sycamore", "mogwai", "seed", "percy", "iced", "dodrio",
-                "smithy", "trunk",
-            ];
-
-            for framework in web_frameworks {
-                if cargo_toml.contains(framework) {
-                    return true;
-                }
-            }
-
-            if cargo_toml.contains("[lib]") && cargo_toml.contains("cdylib") {
-                if Path::new(project_path).join("index.html").exists() {
-                    return true;
-                }
-
-                let potential_static_dirs = ["public", "static", "assets", "dist", "www"];
-                for dir in potential_static_dirs {
-                    if Path::new(project_path).join(dir).exists() {
-                        return true;
-                    }
-                }
-            }
-        }
-
-        false
+        ProjectModel::load(Path::new(project_path))
+            .is_ok_and(|project| project.is_web_application())
     }
 
-    fn compile_standard_wasm(&self, config: &CompileConfig) -> WasmRustResult<CompileResult> {
-        self.ensure_wasm32_target(config.verbose)?;
-
-        let mut args = vec!["build", "--target", "wasm32-unknown-unknown"];
+    fn compile_standard_wasm(
+        &self,
+        config: &CompileConfig,
+        project: &ProjectModel,
+        toolchain: &Toolchain,
+        progress: &Progress,
+    ) -> WasmRustResult<CompileResult> {
+        self.ensure_wasm32_target(toolchain, progress)?;
 
-        match config.optimization {
-            OptimizationLevel::Debug => {}
-            OptimizationLevel::Release => args.push("--release"),
-            OptimizationLevel::Size => {
-                args.push("--release");
-            }
-        }
+        let command = plan::cargo_build_command(config, toolchain);
 
-        if config.verbose {
-            println!("Running: cargo {}", args.join(" "));
-        }
+        progress.phase(BuildPhase::Compiling);
+        let output = self.run_build_command(&command, &config.step_limits(), progress)?;
 
-        let output = Command::new("cargo")
-            .args(&args)
-            .current_dir(&config.project_path)
-            .output()?;
-
-        if !output.status.success() {
+        if !output.success() {
             let stderr = String::from_utf8_lossy(&output.stderr);
             let stdout = String::from_utf8_lossy(&output.stdout);
             return Err(WasmRustError::CompilationFailed(format!(
@@ -548,12 +1421,9 @@ impl WasmRustPlugin {
             )));
         }
 
-        let profile = match config.optimization {
-            OptimizationLevel::Debug => "debug",
-            _ => "release",
-        };
+        let profile = config.optimization.profile_dir();
 
-        let wasm_name = self.get_package_name(&config.project_path)?;
+        let wasm_name = project.package_name();
         let target_dir = Path::new(&config.project_path)
             .join("target/wasm32-unknown-unknown")
             .join(profile);
@@ -605,38 +1475,29 @@ impl WasmRustPlugin {
             js_path: None,
             additional_files: Vec::new(),
             is_webapp: false,
+            ..Default::default()
         })
     }
 
-    fn compile_wasm_bindgen(&self, config: &CompileConfig) -> WasmRustResult<CompileResult> {
+    fn compile_wasm_bindgen(
+        &self,
+        config: &CompileConfig,
+        project: &ProjectModel,
+        toolchain: &Toolchain,
+        progress: &Progress,
+    ) -> WasmRustResult<CompileResult> {
         if !self.is_tool_available("wasm-pack") {
             return Err(WasmRustError::ToolNotFound(
                 "wasm-pack is required for wasm-bindgen projects".to_string(),
             ));
         }
 
-        let mut args = vec!["build", "--target", "web"];
-
-        match config.optimization {
-            OptimizationLevel::Debug => args.push("--dev"),
-            OptimizationLevel::Release => args.push("--release"),
-            OptimizationLevel::Size => {
-                args.push("--release");
-            }
-        }
-
-        args.extend(["--out-dir", &config.output_dir]);
-
-        if config.verbose {
-            println!("Running: wasm-pack {}", args.join(" "));
-        }
+        let command = plan::wasm_pack_command(config, toolchain)?;
 
-        let output = Command::new("wasm-pack")
-            .args(&args)
-            .current_dir(&config.project_path)
-            .output()?;
+        progress.phase(BuildPhase::Compiling);
+        let output = self.run_build_command(&command, &config.step_limits(), progress)?;
 
-        if !output.status.success() {
+        if !output.success() {
             let stderr = String::from_utf8_lossy(&output.stderr);
             let stdout = String::from_utf8_lossy(&output.stdout);
             return Err(WasmRustError::CompilationFailed(format!(
@@ -644,7 +1505,7 @@ impl WasmRustPlugin {
             )));
         }
 
-        let package_name = self.get_package_name(&config.project_path)?;
+        let package_name = project.package_name();
         let wasm_path = Path::new(&config.output_dir).join(format!("{package_name}_bg.wasm"));
         let js_path = Path::new(&config.output_dir).join(format!("{package_name}.js"));
 
@@ -653,52 +1514,58 @@ impl WasmRustPlugin {
             js_path: Some(js_path.to_string_lossy().to_string()),
             additional_files: Vec::new(),
             is_webapp: false,
+            ..Default::default()
         })
     }
 
-    fn compile_web_application(&self, config: &CompileConfig) -> WasmRustResult<CompileResult> {
-        let uses_trunk = Path::new(&config.project_path).join("Trunk.toml").exists()
-            || Path::new(&config.project_path).join("trunk.toml").exists();
-
-        if uses_trunk && self.is_tool_available("trunk") {
-            self.compile_with_trunk(config)
-        } else {
-            self.compile_wasm_bindgen(config)
-        }
-    }
-
-    fn compile_with_trunk(&self, config: &CompileConfig) -> WasmRustResult<CompileResult> {
-        let mut args = vec!["build"];
-
-        match config.optimization {
-            OptimizationLevel::Debug => {}
-            OptimizationLevel::Release => args.push("--release"),
-            OptimizationLevel::Size => {
-                args.extend(["--release", "--minify"]);
-            }
+    /// A web application built by wasm-pack, with the project's page copied
+    /// next to the bindings.
+    fn compile_web_application(
+        &self,
+        config: &CompileConfig,
+        project: &ProjectModel,
+        toolchain: &Toolchain,
+        progress: &Progress,
+    ) -> WasmRustResult<CompileResult> {
+        let mut result = self.compile_wasm_bindgen(config, project, toolchain, progress)?;
+        progress.phase(BuildPhase::Bundling);
+
+        let project_index = Path::new(&config.project_path).join("index.html");
+        let output_index = Path::new(&config.output_dir).join("index.html");
+        if project_index.exists() && !output_index.exists() {
+            fs::copy(&project_index, &output_index)?;
         }
-
-        args.extend(["--dist", "dist"]);
-
-        if config.verbose {
-            println!(
-                "Running: trunk {} (from directory: {})",
-                args.join(" "),
-                config.project_path
-            );
+        if output_index.exists() {
+            result
+                .additional_files
+                .push(output_index.to_string_lossy().to_string());
         }
 
-        let output = Command::new("trunk")
-            .args(&args)
-            .current_dir(&config.project_path)
-            .output()?;
-
-        if config.verbose {
-            println!("Trunk stdout: {}", String::from_utf8_lossy(&output.stdout));
-            println!("Trunk stderr: {}", String::from_utf8_lossy(&output.stderr));
-        }
+        Ok(result)
+    }
 
-        if !output.status.success() {
+    fn compile_with_trunk(
+        &self,
+        config: &CompileConfig,
+        project: &ProjectModel,
+        toolchain: &Toolchain,
+        progress: &Progress,
+    ) -> WasmRustResult<CompileResult> {
+        self.ensure_wasm32_target(toolchain, progress)?;
+
+        let output_dir = Path::new(&config.output_dir);
+        let trunk_config = project.trunk.clone().unwrap_or_default();
+
+        let plan::TrunkInvocation {
+            command,
+            dist,
+            direct_output,
+        } = plan::trunk_command(config, &trunk_config, toolchain)?;
+
+        progress.phase(BuildPhase::Compiling);
+        let output = self.run_build_command(&command, &config.step_limits(), progress)?;
+
+        if !output.success() {
             let stderr = String::from_utf8_lossy(&output.stderr);
             let stdout = String::from_utf8_lossy(&output.stdout);
             return Err(WasmRustError::CompilationFailed(format!(
@@ -706,102 +1573,153 @@ impl WasmRustPlugin {
             )));
         }
 
-        let project_dist = Path::new(&config.project_path).join("dist");
-        let index_in_project_dist = project_dist.join("index.html");
-
-        if config.verbose {
-            println!(
-                "Checking for index.html at: {}",
-                index_in_project_dist.display()
-            );
-        }
-
-        if index_in_project_dist.exists() {
-            if project_dist != Path::new(&config.output_dir) {
-                fs::create_dir_all(&config.output_dir)?;
-                copy_dir_recursive(&project_dist, Path::new(&config.output_dir))?;
-            }
-
-            let final_index = Path::new(&config.output_dir).join("index.html");
-            return Ok(CompileResult {
-                wasm_path: config.output_dir.clone(),
-                js_path: Some(final_index.to_string_lossy().to_string()),
-                additional_files: Vec::new(),
-                is_webapp: true,
-            });
-        }
-
-        let index_path = Path::new(&config.output_dir).join("index.html");
-        if !index_path.exists() {
+        let Some(html) = trunk_config.find_html(&dist) else {
             return Err(WasmRustError::CompilationFailed(format!(
-                "No index.html generated by trunk. Checked: {} and {}",
-                index_in_project_dist.display(),
-                index_path.display()
+                "No {} generated by trunk in {}",
+                trunk_config.html_file_name(),
+                dist.display()
             )));
-        }
+        };
+
+        progress.message(format!("Trunk page: {}", html.display()));
+
+        let html = if direct_output {
+            html
+        } else {
+            fs::create_dir_all(output_dir)?;
+            copy_dir_recursive(&dist, output_dir)?;
+            output_dir.join(html.file_name().unwrap_or_default())
+        };
 
         Ok(CompileResult {
             wasm_path: config.output_dir.clone(),
-            js_path: Some(index_path.to_string_lossy().to_string()),
+            js_path: Some(html.to_string_lossy().to_string()),
             additional_files: Vec::new(),
             is_webapp: true,
+            public_url: config.public_url.clone().or_else(|| {
+                trunk_config
+                    .build
+                    .public_url
+                    .as_deref()
+                    .map(normalize_public_url)
+            }),
+            ..Default::default()
         })
     }
 
-    fn get_package_name(&self, project_path: &str) -> WasmRustResult<String> {
-        let cargo_toml_path = Path::new(project_path).join("Cargo.toml");
-        let content = fs::read_to_string(cargo_toml_path)?;
-        let cargo_toml: CargoTomlFull = toml::from_str(&content)?;
-        Ok(cargo_toml.package.name.replace("-", "_"))
+    /// Runs one build step, streaming its output to `progress`. A program
+    /// the runner cannot find is reported as a missing tool rather than an
+    /// I/O error.
+    fn run_build_command(
+        &self,
+        command: &PlannedCommand,
+        limits: &StepLimits,
+        progress: &Progress,
+    ) -> WasmRustResult<CommandOutput> {
+        progress.command(command);
+        let mut on_line = |stream, line: &str| progress.line(stream, line);
+        self.runner
+            .run_streaming(command, limits, &mut on_line)
+            .map_err(|err| match err.kind() {
+                std::io::ErrorKind::NotFound => {
+                    WasmRustError::ToolNotFound(format!("{} is not installed", command.program))
+                }
+                std::io::ErrorKind::Interrupted if limits.is_cancelled() => {
+                    WasmRustError::Cancelled
+                }
+                std::io::ErrorKind::TimedOut => WasmRustError::TimedOut {
+                    step: command.program.clone(),
+                    timeout: limits.timeout.unwrap_or_default(),
+                },
+                _ => WasmRustError::Io(err),
+            })
     }
 
-    fn ensure_wasm32_target(&self, verbose: bool) -> WasmRustResult<()> {
-        if !self.is_wasm_target_installed() {
-            if verbose {
-                println!("Installing wasm32-unknown-unknown target...");
-            }
-
-            let output = Command::new("rustup")
-                .args(["target", "add", "wasm32-unknown-unknown"])
-                .output()?;
-
-            if !output.status.success() {
-                let stderr = String::from_utf8_lossy(&output.stderr);
-                return Err(WasmRustError::CompilationFailed(format!(
-                    "Failed to install wasm32 target: {stderr}",
-                )));
+    /// Makes sure the project's toolchain has the wasm target and whatever
+    /// its toolchain file asks for.
+    fn ensure_wasm32_target(
+        &self,
+        toolchain: &Toolchain,
+        progress: &Progress,
+    ) -> WasmRustResult<()> {
+        let mut installed = false;
+        if !self.probes.target(toolchain, WASM_TARGET).installed {
+            progress.message(format!(
+                "Installing {WASM_TARGET} target for {toolchain}..."
+            ));
+            toolchain.ensure_target(self.runner(), WASM_TARGET)?;
+            installed = true;
+        }
+        for component in &toolchain.components {
+            if !self.probes.component(toolchain, component) {
+                progress.message(format!(
+                    "Installing {component} component for {toolchain}..."
+                ));
+                toolchain.ensure_component(self.runner(), component)?;
+                installed = true;
             }
         }
+        if installed {
+            self.probes.invalidate_toolchain(toolchain);
+        }
         Ok(())
     }
 
-    fn is_wasm_target_installed(&self) -> bool {
-        Command::new("rustup")
-            .args(["target", "list", "--installed"])
-            .output()
-            .map(|output| {
-                let stdout = String::from_utf8_lossy(&output.stdout);
-                stdout.contains("wasm32-unknown-unknown")
-            })
-            .unwrap_or(false)
+    /// Availability of the wasm target for the current directory's toolchain,
+    /// and how it was detected.
+    pub fn wasm_target_status(&self) -> TargetStatus {
+        let toolchain = self
+            .probes
+            .toolchain(Path::new("."), None)
+            .unwrap_or_default();
+        self.probes.target(&toolchain, WASM_TARGET)
     }
 
     pub fn is_tool_available(&self, tool: &str) -> bool {
-        if let Ok(output) = Command::new(tool).arg("--version").output() {
-            return output.status.success();
-        }
+        self.tool_info(tool).is_available()
+    }
 
-        let which_cmd = if cfg!(target_os = "windows") {
-            "where"
-        } else {
-            "which"
-        };
+    /// Location and version of an external tool.
+    pub fn tool_info(&self, tool: &str) -> ToolInfo {
+        self.probes.tool(tool, &[])
+    }
 
-        Command::new(which_cmd)
-            .arg(tool)
-            .output()
-            .map(|output| output.status.success())
-            .unwrap_or(false)
+    /// Probes every tool in [`PROBED_TOOLS`].
+    pub fn probe_tools(&self) -> Vec<ToolInfo> {
+        PROBED_TOOLS
+            .iter()
+            .map(|tool| self.tool_info(tool))
+            .collect()
+    }
+
+    /// A dependency entry for an external tool. It fails when the tool is
+    /// missing or older than `strategy` supports; cargo and rustc are probed
+    /// through the project's toolchain.
+    fn tool_check(
+        &self,
+        tool: &str,
+        reason: &str,
+        strategy: &BuildStrategy,
+        toolchain: &Toolchain,
+    ) -> DependencyCheck {
+        let info = self.probes.tool(tool, &toolchain.env());
+        let minimum = minimum_version(tool, strategy);
+        let too_old = minimum
+            .as_ref()
+            .is_some_and(|minimum| info.is_older_than(minimum));
+
+        DependencyCheck {
+            name: tool.to_string(),
+            available: info.is_available() && !too_old,
+            reason: reason.to_string(),
+            version: info
+                .version
+                .as_ref()
+                .map(ToString::to_string)
+                .or(info.version_output),
+            minimum_version: minimum.map(|minimum| minimum.to_string()),
+            ..Default::default()
+        }
     }
 
     pub fn get_watch_paths(&self, project_path: &str) -> Vec<std::path::PathBuf> {
@@ -814,8 +1732,12 @@ impl WasmRustPlugin {
             paths.push(project_path.join("src"));
         }
 
-        if project_path.join("Trunk.toml").exists() {
-            paths.push(project_path.join("Trunk.toml"));
+        if let Some(trunk_toml) = TrunkConfig::find(project_path) {
+            paths.push(trunk_toml);
+        }
+
+        if let Some(toolchain_file) = ToolchainFile::find(project_path) {
+            paths.push(toolchain_file);
         }
 
         for asset_dir in &["assets", "static", "public"] {
@@ -926,7 +1848,9 @@ impl Plugin for WasmrustPlugin {
     }
 
     fn get_builder(&self) -> Box<dyn WasmBuilder> {
-        Box::new(WasmrustBuilder::new())
+        Box::new(WasmrustBuilder {
+            inner: self.inner.clone(),
+        })
     }
 }
 
@@ -954,6 +1878,18 @@ impl WasmBuilder for WasmrustBuilder {
     }
 
     fn build(&self, config: &BuildConfig) -> CompilationResult<BuildResult> {
+        self.build_with_events(config, &|event| {
+            if config.verbose {
+                events::print_verbose_event(event);
+            }
+        })
+    }
+
+    fn build_with_events(
+        &self,
+        config: &BuildConfig,
+        on_event: &dyn Fn(&BuildEvent),
+    ) -> CompilationResult<BuildResult> {
         let start_time = std::time::Instant::now();
 
         let optimization = config.optimization.clone();
@@ -971,39 +1907,68 @@ impl WasmBuilder for WasmrustBuilder {
             optimization: optimization.clone(),
             target_type,
             verbose: config.verbose,
+            features: config.features.clone(),
+            all_features: config.all_features,
+            no_default_features: config.no_default_features,
+            toolchain: config.toolchain.clone(),
+            step_timeout: config.step_timeout,
+            cancel: config.cancel.clone(),
+            force: config.force,
+            lock: config.lock,
+            ..Default::default()
         };
 
-        match self.inner.compile(&compile_config) {
+        match self.inner.compile_with_events(&compile_config, on_event) {
             Ok(result) => {
                 let build_time = start_time.elapsed();
 
-                let file_size = if result.is_webapp {
+                let wasm_file = if result.is_webapp {
                     std::fs::read_dir(&result.wasm_path)
                         .ok()
                         .and_then(|entries| {
                             entries
                                 .filter_map(|entry| entry.ok())
-                                .find(|entry| {
-                                    entry.path().extension().is_some_and(|ext| ext == "wasm")
-                                })
-                                .and_then(|entry| std::fs::metadata(entry.path()).ok())
-                                .map(|metadata| metadata.len())
+                                .map(|entry| entry.path())
+                                .find(|path| path.extension().is_some_and(|ext| ext == "wasm"))
                         })
-                        .unwrap_or(0)
                 } else {
-                    std::fs::metadata(&result.wasm_path)
-                        .map(|m| m.len())
-                        .unwrap_or(0)
+                    Some(std::path::PathBuf::from(&result.wasm_path))
                 };
 
+                let file_size = wasm_file
+                    .as_ref()
+                    .and_then(|path| std::fs::metadata(path).ok())
+                    .map(|metadata| metadata.len())
+                    .unwrap_or(0);
+
+                let compressed = wasm_file.as_ref().and_then(|path| {
+                    result
+                        .compressed
+                        .iter()
+                        .find(|c| Path::new(&c.path) == path)
+                });
+                let gzip_size = compressed.and_then(|c| c.gzip_size);
+                let brotli_size = compressed.and_then(|c| c.brotli_size);
+
                 Ok(BuildResult {
                     output_path: result.js_path.unwrap_or(result.wasm_path),
                     language: "rust".to_string(),
                     optimization_level: optimization,
                     build_time,
                     file_size,
+                    gzip_size,
+                    brotli_size,
+                    public_url: result.public_url.unwrap_or_else(default_public_url),
                 })
             }
+            Err(WasmRustError::Cancelled) => Err(CompilationError::Cancelled {
+                language: "rust".to_string(),
+            }),
+            Err(WasmRustError::TimedOut { step, timeout }) => Err(CompilationError::TimedOut {
+                language: "rust".to_string(),
+                step,
+                timeout,
+            }),
             Err(e) => Err(CompilationError::BuildFailed {
                 language: "rust".to_string(),
                 reason: format!("{e}"),
@@ -1026,12 +1991,19 @@ impl WasmBuilder for WasmrustBuilder {
     }
 
     fn clean(&self, project_path: &str) -> std::result::Result<(), Box<dyn std::error::Error>> {
-        let output = Command::new("cargo")
-            .args(["clean"])
-            .current_dir(project_path)
-            .output()?;
+        let command = PlannedCommand {
+            program: "cargo".to_string(),
+            args: vec!["clean".to_string()],
+            cwd: Some(project_path.into()),
+            ..Default::default()
+        };
+        let output = self.inner.run_build_command(
+            &command,
+            &StepLimits::default(),
+            &Progress::new(&|_| {}, None),
+        )?;
 
-        if !output.status.success() {
+        if !output.success() {
             let stderr = String::from_utf8_lossy(&output.stderr);
             return Err(format!("Clean failed: {stderr}").into());
         }
@@ -1040,7 +2012,9 @@ impl WasmBuilder for WasmrustBuilder {
     }
 
     fn clone_box(&self) -> Box<dyn WasmBuilder> {
-        Box::new(WasmrustBuilder::new())
+        Box::new(WasmrustBuilder {
+            inner: self.inner.clone(),
+        })
     }
 
     fn language_name(&self) -> &str {
@@ -1069,10 +2043,13 @@ use std::ptr;
 pub struct BuildConfigC {
     pub input: *const c_char,
     pub output_dir: *const c_char,
-    pub optimization: u8, // 0=Debug, 1=Release, 2=Size
+    pub optimization: u8, // 0=Debug, 1=Release, 2=Size, 3=Profiling, 4=Custom
     pub target_type: *const c_char,
     pub verbose: bool,
     pub watch: bool,
+    /// Cargo profile name; only read when `optimization` is 4, so callers
+    /// built against the older layout keep working.
+    pub profile: *const c_char,
 }
 
 #[repr(C)]
@@ -1082,8 +2059,16 @@ pub struct BuildResultC {
     pub file_size: u64,
     pub success: bool,
     pub error_message: *mut c_char,
+    /// One of the `WASMRUST_BUILD_*` codes; appended so callers built
+    /// against the older layout keep working.
+    pub status: u8,
 }
 
+pub const WASMRUST_BUILD_OK: u8 = 0;
+pub const WASMRUST_BUILD_FAILED: u8 = 1;
+pub const WASMRUST_BUILD_CANCELLED: u8 = 2;
+pub const WASMRUST_BUILD_TIMED_OUT: u8 = 3;
+
 #[no_mangle]
 pub extern "C" fn wasmrun_plugin_create() -> *mut c_void {
     let plugin = Box::new(WasmrustPlugin::new());
@@ -1126,6 +2111,8 @@ pub unsafe extern "C" fn wasmrust_can_handle_project(
 ///
 /// - `builder_ptr` must be a valid pointer to a WasmrustBuilder
 /// - `config` must be a valid pointer to a BuildConfigC
+/// - `config.profile` must be null or a valid null-terminated C string when
+///   `config.optimization` is 4
 /// - Caller must call `wasmrust_free_build_result` on the returned pointer
 #[no_mangle]
 pub unsafe extern "C" fn wasmrust_build(
@@ -1137,40 +2124,159 @@ pub unsafe extern "C" fn wasmrust_build(
     }
 
     let builder = &*(builder_ptr as *const WasmrustBuilder);
-    let config_c = &*config;
+    let Some(build_config) = build_config_from_c(&*config) else {
+        return ptr::null_mut();
+    };
 
-    let input = match CStr::from_ptr(config_c.input).to_str() {
-        Ok(s) => s.to_string(),
-        Err(_) => return ptr::null_mut(),
+    build_result_to_c(builder.build(&build_config))
+}
+
+/// Receives one build event as a JSON object (see [`BuildEvent`]). The
+/// string is only valid for the duration of the call.
+pub type BuildEventCallback = extern "C" fn(event_json: *const c_char, user_data: *mut c_void);
+
+/// Builds the project like `wasmrust_build`, calling `callback` with each
+/// build event as it happens.
+///
+/// # Safety
+///
+/// - `builder_ptr` must be a valid pointer to a WasmrustBuilder
+/// - `config` must be a valid pointer to a BuildConfigC
+/// - `config.profile` must be null or a valid null-terminated C string when
+///   `config.optimization` is 4
+/// - `callback` is called on the building thread and is passed `user_data`
+///   unchanged
+/// - Caller must call `wasmrust_free_build_result` on the returned pointer
+#[no_mangle]
+pub unsafe extern "C" fn wasmrust_build_with_events(
+    builder_ptr: *const c_void,
+    config: *const BuildConfigC,
+    callback: Option<BuildEventCallback>,
+    user_data: *mut c_void,
+) -> *mut BuildResultC {
+    wasmrust_build_cancellable(builder_ptr, config, ptr::null(), 0, callback, user_data)
+}
+
+/// Builds the project like `wasmrust_build_with_events`, stopping when
+/// `cancel_token` is cancelled or a single step runs longer than
+/// `step_timeout_ms` (0 for no limit). The result's `status` tells a
+/// cancelled or timed out build from a failed one.
+///
+/// # Safety
+///
+/// - `builder_ptr` must be a valid pointer to a WasmrustBuilder
+/// - `config` must be a valid pointer to a BuildConfigC
+/// - `config.profile` must be null or a valid null-terminated C string when
+///   `config.optimization` is 4
+/// - `cancel_token` must be null or a pointer from
+///   `wasmrust_cancel_token_new` that is not freed before this returns
+/// - `callback` is called on the building thread and is passed `user_data`
+///   unchanged
+/// - Caller must call `wasmrust_free_build_result` on the returned pointer
+#[no_mangle]
+pub unsafe extern "C" fn wasmrust_build_cancellable(
+    builder_ptr: *const c_void,
+    config: *const BuildConfigC,
+    cancel_token: *const c_void,
+    step_timeout_ms: u64,
+    callback: Option<BuildEventCallback>,
+    user_data: *mut c_void,
+) -> *mut BuildResultC {
+    if builder_ptr.is_null() || config.is_null() {
+        return ptr::null_mut();
+    }
+
+    let builder = &*(builder_ptr as *const WasmrustBuilder);
+    let Some(mut build_config) = build_config_from_c(&*config) else {
+        return ptr::null_mut();
     };
+    if !cancel_token.is_null() {
+        build_config.cancel = Some((*(cancel_token as *const CancellationToken)).clone());
+    }
+    if step_timeout_ms > 0 {
+        build_config.step_timeout = Some(Duration::from_millis(step_timeout_ms));
+    }
 
-    let output_dir = match CStr::from_ptr(config_c.output_dir).to_str() {
-        Ok(s) => s.to_string(),
-        Err(_) => return ptr::null_mut(),
+    let on_event = |event: &BuildEvent| {
+        let Some(callback) = callback else {
+            return;
+        };
+        let json = serde_json::to_string(event).ok();
+        if let Some(json) = json.and_then(|json| CString::new(json).ok()) {
+            callback(json.as_ptr(), user_data);
+        }
     };
+    build_result_to_c(builder.build_with_events(&build_config, &on_event))
+}
+
+/// Creates a token for `wasmrust_build_cancellable`. Free it with
+/// `wasmrust_cancel_token_free`.
+#[no_mangle]
+pub extern "C" fn wasmrust_cancel_token_new() -> *mut c_void {
+    Box::into_raw(Box::new(CancellationToken::new())) as *mut c_void
+}
+
+/// Cancels every build using the token; safe to call from another thread
+/// while the build runs.
+///
+/// # Safety
+///
+/// - `token` must be null or a pointer from `wasmrust_cancel_token_new`
+#[no_mangle]
+pub unsafe extern "C" fn wasmrust_cancel_token_cancel(token: *const c_void) {
+    if !token.is_null() {
+        (*(token as *const CancellationToken)).cancel();
+    }
+}
+
+/// Frees a token.
+///
+/// # Safety
+///
+/// - `token` must be null or a pointer from `wasmrust_cancel_token_new`,
+///   no longer used by a running build
+#[no_mangle]
+pub unsafe extern "C" fn wasmrust_cancel_token_free(token: *mut c_void) {
+    if !token.is_null() {
+        drop(Box::from_raw(token as *mut CancellationToken));
+    }
+}
+
+unsafe fn build_config_from_c(config_c: &BuildConfigC) -> Option<BuildConfig> {
+    let input = CStr::from_ptr(config_c.input).to_str().ok()?.to_string();
+    let output_dir = CStr::from_ptr(config_c.output_dir)
+        .to_str()
+        .ok()?
+        .to_string();
 
     let target_type = match CStr::from_ptr(config_c.target_type).to_str() {
         Ok(s) => s.to_string(),
         Err(_) => "wasm".to_string(),
     };
 
-    let optimization = match config_c.optimization {
-        0 => OptimizationLevel::Debug,
-        1 => OptimizationLevel::Release,
-        2 => OptimizationLevel::Size,
-        _ => OptimizationLevel::Release,
+    let profile = if config_c.optimization == 4 && !config_c.profile.is_null() {
+        CStr::from_ptr(config_c.profile)
+            .to_str()
+            .ok()
+            .map(str::to_string)
+    } else {
+        None
     };
+    let optimization = OptimizationLevel::from_ffi(config_c.optimization, profile);
 
-    let build_config = BuildConfig {
+    Some(BuildConfig {
         input,
         output_dir,
         optimization,
         target_type,
         verbose: config_c.verbose,
         watch: config_c.watch,
-    };
+        ..Default::default()
+    })
+}
 
-    match builder.build(&build_config) {
+fn build_result_to_c(result: CompilationResult<BuildResult>) -> *mut BuildResultC {
+    match result {
         Ok(result) => {
             let output_path = CString::new(result.output_path).unwrap();
             let language = CString::new(result.language).unwrap();
@@ -1181,11 +2287,17 @@ pub unsafe extern "C" fn wasmrust_build(
                 file_size: result.file_size,
                 success: true,
                 error_message: ptr::null_mut(),
+                status: WASMRUST_BUILD_OK,
             });
 
             Box::into_raw(result_c)
         }
         Err(e) => {
+            let status = match e {
+                CompilationError::Cancelled { .. } => WASMRUST_BUILD_CANCELLED,
+                CompilationError::TimedOut { .. } => WASMRUST_BUILD_TIMED_OUT,
+                _ => WASMRUST_BUILD_FAILED,
+            };
             let error_msg = CString::new(format!("{e}")).unwrap();
             let result_c = Box::new(BuildResultC {
                 output_path: ptr::null_mut(),
@@ -1193,6 +2305,7 @@ pub unsafe extern "C" fn wasmrust_build(
                 file_size: 0,
                 success: false,
                 error_message: error_msg.into_raw(),
+                status,
             });
 
             Box::into_raw(result_c)
@@ -0,0 +1,149 @@
+use crate::runner::{StepLimits, POLL_INTERVAL};
+use crate::{WasmRustError, WasmRustResult};
+use serde::{Deserialize, Serialize};
+use std::fs::{self, File, OpenOptions, TryLockError};
+use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::Instant;
+
+/// Lock file a build holds in the project directory. It is not in `target`,
+/// which may be shared by a workspace or moved by `CARGO_TARGET_DIR`. The
+/// PID of the build that has it is in `.wasmrust.lock.pid` next to it, which
+/// stays readable while the lock is held, including on Windows.
+pub const LOCK_FILE_NAME: &str = ".wasmrust.lock";
+
+/// What a build does when another build of the same project or into the
+/// same output directory is running, in this or another process.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BuildLockMode {
+    /// Wait for the other build to finish, then build
+    #[default]
+    Wait,
+    /// Wait for the other build to finish and reuse its outputs when it
+    /// built the same inputs, even for a forced build
+    Coalesce,
+    /// Fail right away with [`WasmRustError::BuildLocked`]
+    FailFast,
+}
+
+/// Advisory locks on a project and its output directory, held for the
+/// length of a build. The OS releases them when the process dies, so a
+/// crashed build never leaves a stale lock behind.
+pub(crate) struct BuildLock {
+    files: Vec<File>,
+    /// Whether another build had to finish first
+    pub waited: bool,
+}
+
+impl BuildLock {
+    /// Takes the project's lock, then the output directory's, always in
+    /// that order so two builds cannot each hold the lock the other needs.
+    /// `on_wait` is called once if the build has to wait, with the held
+    /// lock file and the holder's PID when it can be read. Waiting stops
+    /// when `limits` are cancelled or, with a timeout, once it has waited
+    /// that long in total.
+    pub fn acquire(
+        project_path: &Path,
+        output_dir: &Path,
+        mode: BuildLockMode,
+        limits: &StepLimits,
+        on_wait: &mut dyn FnMut(&Path, Option<u32>),
+    ) -> WasmRustResult<Self> {
+        let mut lock = Self {
+            files: Vec::new(),
+            waited: false,
+        };
+        let started = Instant::now();
+        for path in lock_paths(project_path, output_dir)? {
+            let file = OpenOptions::new()
+                .read(true)
+                .write(true)
+                .create(true)
+                .truncate(false)
+                .open(&path)?;
+            loop {
+                match file.try_lock() {
+                    Ok(()) => break,
+                    Err(TryLockError::WouldBlock) => {}
+                    Err(TryLockError::Error(err)) => return Err(err.into()),
+                }
+                if mode == BuildLockMode::FailFast {
+                    return Err(WasmRustError::BuildLocked {
+                        path: path.display().to_string(),
+                        pid: holder_pid(&path),
+                    });
+                }
+                if !lock.waited {
+                    on_wait(&path, holder_pid(&path));
+                    lock.waited = true;
+                }
+                if limits.is_cancelled() {
+                    return Err(WasmRustError::Cancelled);
+                }
+                if let Some(timeout) = limits.timeout.filter(|t| started.elapsed() >= *t) {
+                    return Err(WasmRustError::TimedOut {
+                        step: format!("waiting for {}", format_holder(&holder_pid(&path))),
+                        timeout,
+                    });
+                }
+                thread::sleep(POLL_INTERVAL);
+            }
+            write_pid(&path)?;
+            lock.files.push(file);
+        }
+        Ok(lock)
+    }
+}
+
+impl Drop for BuildLock {
+    fn drop(&mut self) {
+        // Reverse order, the output directory's first.
+        for file in self.files.iter().rev() {
+            let _ = file.unlock();
+        }
+    }
+}
+
+/// The project's lock file and the output directory's, which sits next to
+/// the directory since staged builds replace the directory itself.
+fn lock_paths(project_path: &Path, output_dir: &Path) -> WasmRustResult<Vec<PathBuf>> {
+    let mut paths = vec![project_path.join(LOCK_FILE_NAME)];
+
+    let output_dir = output_dir.canonicalize()?;
+    if let (Some(parent), Some(name)) = (output_dir.parent(), output_dir.file_name()) {
+        paths.push(parent.join(format!(".{}.wasmrust-lock", name.to_string_lossy())));
+    }
+    Ok(paths)
+}
+
+fn pid_path(lock_path: &Path) -> PathBuf {
+    let mut name = lock_path.as_os_str().to_owned();
+    name.push(".pid");
+    name.into()
+}
+
+/// Replaces the PID file whole, so a build that finds the lock taken never
+/// reads it half-written.
+fn write_pid(lock_path: &Path) -> WasmRustResult<()> {
+    let pid_path = pid_path(lock_path);
+    let partial = pid_path.with_extension(format!("pid.{}", std::process::id()));
+    fs::write(&partial, std::process::id().to_string())?;
+    fs::rename(&partial, &pid_path)?;
+    Ok(())
+}
+
+fn holder_pid(lock_path: &Path) -> Option<u32> {
+    fs::read_to_string(pid_path(lock_path))
+        .ok()?
+        .trim()
+        .parse()
+        .ok()
+}
+
+pub(crate) fn format_holder(pid: &Option<u32>) -> String {
+    match pid {
+        Some(pid) => format!("another build (pid {pid})"),
+        None => "another build".to_string(),
+    }
+}
@@ -1,7 +1,7 @@
 #[cfg(feature = "cli")]
 use clap::{Parser, Subcommand};
 #[cfg(feature = "cli")]
-use wasmrust::{CompileConfig, OptimizationLevel, TargetType, WasmRustPlugin};
+use wasmrust::{BuildLockMode, CompileConfig, OptimizationLevel, TargetType, WasmRustPlugin};
 
 #[cfg(feature = "cli")]
 #[derive(Parser)]
@@ -56,9 +56,73 @@ enum Commands {
         #[arg(long, value_enum, default_value = "wasm")]
         target: CliTarget,
 
+        /// Named cargo profile to build with; overrides --optimization
+        #[arg(long, value_name = "NAME")]
+        profile: Option<String>,
+
+        /// rustup toolchain to build with, e.g. nightly or +nightly
+        #[arg(long, value_name = "TOOLCHAIN")]
+        toolchain: Option<String>,
+
+        /// Export snapshot to check the build against (recorded if missing)
+        #[arg(long, value_name = "FILE")]
+        api_snapshot: Option<String>,
+
+        /// Write gzip and brotli variants of the wasm, JS and HTML outputs
+        #[arg(long)]
+        precompress: bool,
+
+        /// Rename wasm-bindgen outputs with a content hash for cache busting
+        #[arg(long)]
+        content_hash: bool,
+
+        /// Add subresource integrity attributes to HTML outputs and print a suggested CSP
+        #[arg(long)]
+        integrity: bool,
+
+        /// Base path the app is served from, e.g. /apps/demo/
+        #[arg(long, value_name = "URL")]
+        public_url: Option<String>,
+
+        /// Cargo features to enable (comma or space separated)
+        #[arg(short = 'F', long, value_delimiter = ',', value_name = "FEATURES")]
+        features: Vec<String>,
+
+        /// Enable all cargo features
+        #[arg(long)]
+        all_features: bool,
+
+        /// Do not enable the crate's default features
+        #[arg(long)]
+        no_default_features: bool,
+
+        /// Kill a build step (cargo, wasm-pack, trunk) that runs longer than this
+        #[arg(long, value_name = "SECONDS")]
+        timeout: Option<u64>,
+
         /// Enable verbose compilation output
         #[arg(short, long)]
         verbose: bool,
+
+        /// Print the commands and expected outputs without building
+        #[arg(long)]
+        dry_run: bool,
+
+        /// Rebuild even when nothing changed since the last build
+        #[arg(long)]
+        force: bool,
+
+        /// Remove files in the output directory that this build does not produce
+        #[arg(long)]
+        prune: bool,
+
+        /// What to do when another build of the project is running
+        #[arg(long, value_enum, value_name = "MODE")]
+        lock: Option<CliLockMode>,
+
+        /// Build into the output directory even if it overlaps the project
+        #[arg(long)]
+        allow_unsafe_output: bool,
     },
 
     /// Inspect project structure, dependencies, and frameworks
@@ -67,6 +131,40 @@ enum Commands {
         /// Project path to inspect
         #[arg(short, long, default_value = ".", value_name = "PATH")]
         project: String,
+
+        /// Show what the project type and build strategy were detected from
+        #[arg(long)]
+        explain: bool,
+    },
+
+    /// Compare two builds (a .wasm file, a build manifest or an output directory)
+    Diff {
+        /// Baseline build
+        #[arg(value_name = "OLD")]
+        old: String,
+
+        /// Build to compare against the baseline
+        #[arg(value_name = "NEW")]
+        new: String,
+
+        /// Number of changed functions to list per module
+        #[arg(long, default_value_t = 20, value_name = "N")]
+        top: usize,
+    },
+
+    /// Check a build's export surface against a recorded snapshot
+    Compat {
+        /// Build to check (a .wasm file, a build manifest or an output directory)
+        #[arg(value_name = "BUILD")]
+        build: String,
+
+        /// Snapshot file to compare against
+        #[arg(long, value_name = "FILE")]
+        snapshot: String,
+
+        /// Record the build's surface as the new snapshot
+        #[arg(long)]
+        update: bool,
     },
 
     /// Check if wasmrust can handle the project
@@ -102,6 +200,8 @@ enum CliOptimization {
     Release,
     /// Smallest possible output size
     Size,
+    /// Release build that keeps debug info for profiling
+    Profiling,
 }
 
 #[cfg(feature = "cli")]
@@ -113,6 +213,17 @@ enum CliTarget {
     WebApp,
 }
 
+#[cfg(feature = "cli")]
+#[derive(clap::ValueEnum, Clone, Debug)]
+enum CliLockMode {
+    /// Wait for the other build, then build
+    Wait,
+    /// Wait for the other build and reuse its outputs if nothing changed
+    Coalesce,
+    /// Exit with an error right away
+    FailFast,
+}
+
 #[cfg(feature = "cli")]
 impl From<CliOptimization> for OptimizationLevel {
     fn from(opt: CliOptimization) -> Self {
@@ -120,6 +231,7 @@ impl From<CliOptimization> for OptimizationLevel {
             CliOptimization::Debug => OptimizationLevel::Debug,
             CliOptimization::Release => OptimizationLevel::Release,
             CliOptimization::Size => OptimizationLevel::Size,
+            CliOptimization::Profiling => OptimizationLevel::Profiling,
         }
     }
 }
@@ -134,6 +246,17 @@ impl From<CliTarget> for TargetType {
     }
 }
 
+#[cfg(feature = "cli")]
+impl From<CliLockMode> for BuildLockMode {
+    fn from(mode: CliLockMode) -> Self {
+        match mode {
+            CliLockMode::Wait => BuildLockMode::Wait,
+            CliLockMode::Coalesce => BuildLockMode::Coalesce,
+            CliLockMode::FailFast => BuildLockMode::FailFast,
+        }
+    }
+}
+
 #[cfg(feature = "cli")]
 fn print_header() {
     println!(
@@ -172,6 +295,9 @@ fn check_dependencies(plugin: &WasmRustPlugin) -> bool {
         {
             eprintln!("   • Install Rust: https://rustup.rs/");
         }
+        if missing_deps.iter().any(|d| d.contains("is older than")) {
+            eprintln!("   • Update Rust: rustup update");
+        }
         if missing_deps
             .iter()
             .any(|d| d.contains("wasm32-unknown-unknown"))
@@ -186,6 +312,248 @@ fn check_dependencies(plugin: &WasmRustPlugin) -> bool {
     true
 }
 
+#[cfg(feature = "cli")]
+fn describe_dependency(dep: &wasmrust::DependencyCheck) -> String {
+    let mut label = dep.name.clone();
+    if let Some(version) = &dep.version {
+        label.push_str(&format!(" {version}"));
+    }
+    if let Some(minimum) = dep.minimum_version.as_ref().filter(|_| !dep.available) {
+        if dep.version.is_some() {
+            label.push_str(&format!(" (requires ≥ {minimum})"));
+        }
+    }
+    label
+}
+
+#[cfg(feature = "cli")]
+fn print_build_plan(plan: &wasmrust::BuildPlan) {
+    println!(
+        "📝 Build plan ({:?}, {})",
+        plan.build_strategy, plan.toolchain
+    );
+    for (index, command) in plan.commands.iter().enumerate() {
+        println!("   {}. {command}", index + 1);
+        if let Some(cwd) = &command.cwd {
+            println!("      in {}", cwd.display());
+        }
+    }
+
+    println!("📦 Expected outputs:");
+    for output in &plan.outputs {
+        println!("   • {}", output.display());
+    }
+
+    for note in &plan.notes {
+        println!("💡 {note}");
+    }
+}
+
+/// Renders build events: one line per phase, a crate counter that redraws
+/// in place on a terminal, and the tools' output in verbose mode.
+#[cfg(feature = "cli")]
+struct ProgressDisplay {
+    verbose: bool,
+    interactive: bool,
+    /// Whether the crate counter is on screen and has to be cleared
+    counter_shown: std::cell::Cell<bool>,
+}
+
+#[cfg(feature = "cli")]
+impl ProgressDisplay {
+    fn new(verbose: bool) -> Self {
+        use std::io::IsTerminal;
+        Self {
+            verbose,
+            interactive: std::io::stdout().is_terminal(),
+            counter_shown: std::cell::Cell::new(false),
+        }
+    }
+
+    fn handle(&self, event: &wasmrust::BuildEvent) {
+        use std::io::Write;
+        use wasmrust::{BuildEvent, BuildPhase, OutputStream};
+
+        match event {
+            BuildEvent::Phase { phase } => {
+                self.clear_counter();
+                let icon = match phase {
+                    BuildPhase::Resolving => "🔍",
+                    BuildPhase::Compiling => "🔨",
+                    BuildPhase::Bindgen => "🔗",
+                    BuildPhase::Optimizing => "⚡",
+                    BuildPhase::Bundling => "📦",
+                };
+                println!("{icon} {phase}...");
+            }
+            BuildEvent::Crate {
+                name,
+                current,
+                total,
+            } if self.interactive && !self.verbose => {
+                let count = match total {
+                    Some(total) => format!("{current}/{total}"),
+                    None => current.to_string(),
+                };
+                print!("\r   [{count}] {name}\x1b[K");
+                let _ = std::io::stdout().flush();
+                self.counter_shown.set(true);
+            }
+            BuildEvent::Command { command } if self.verbose => {
+                self.clear_counter();
+                println!("   Running: {command}");
+            }
+            BuildEvent::Message { text } if self.verbose => {
+                self.clear_counter();
+                println!("   {text}");
+            }
+            BuildEvent::Output { stream, line } if self.verbose => match stream {
+                OutputStream::Stdout => println!("   {line}"),
+                OutputStream::Stderr => eprintln!("   {line}"),
+            },
+            BuildEvent::Waiting { pid, .. } => {
+                let holder = match pid {
+                    Some(pid) => format!(" (pid {pid})"),
+                    None => String::new(),
+                };
+                println!("⏳ Waiting for another build{holder} to finish...");
+            }
+            BuildEvent::Finished { .. } => self.clear_counter(),
+            _ => {}
+        }
+    }
+
+    fn clear_counter(&self) {
+        if self.counter_shown.replace(false) {
+            print!("\r\x1b[K");
+        }
+    }
+}
+
+#[cfg(feature = "cli")]
+fn format_delta(delta: i64) -> String {
+    if delta > 0 {
+        format!("+{delta}")
+    } else {
+        delta.to_string()
+    }
+}
+
+#[cfg(feature = "cli")]
+fn print_build_diff(diff: &wasmrust::BuildDiff, top: usize) {
+    for artifact in &diff.artifacts_added {
+        println!("➕ Artifact added: {artifact}");
+    }
+    for artifact in &diff.artifacts_removed {
+        println!("➖ Artifact removed: {artifact}");
+    }
+    if !diff.artifacts_added.is_empty() || !diff.artifacts_removed.is_empty() {
+        println!();
+    }
+
+    for module in &diff.modules {
+        println!("📦 {}", module.artifact);
+        println!("═══════════════════");
+        println!(
+            "Size: {} → {} bytes ({})",
+            module.old_size,
+            module.new_size,
+            format_delta(module.size_delta())
+        );
+
+        println!();
+        println!("Sections:");
+        for section in &module.sections {
+            println!(
+                "   {:<24} {:>10} → {:>10} ({})",
+                section.name,
+                section.old,
+                section.new,
+                format_delta(section.delta())
+            );
+        }
+
+        if !module.functions.is_empty() {
+            println!();
+            println!(
+                "Functions ({} changed, largest first):",
+                module.functions.len()
+            );
+            for function in module.functions.iter().take(top) {
+                println!(
+                    "   {:>8}  {}",
+                    format_delta(function.delta()),
+                    function.name
+                );
+            }
+        }
+
+        if module.has_interface_changes() {
+            println!();
+            println!("Interface:");
+            for export in &module.exports_added {
+                println!(
+                    "   ➕ export {} {} {}",
+                    export.kind, export.name, export.signature
+                );
+            }
+            for export in &module.exports_removed {
+                println!(
+                    "   ➖ export {} {} {}",
+                    export.kind, export.name, export.signature
+                );
+            }
+            for change in &module.exports_changed {
+                println!(
+                    "   ✏️  export {}: {} → {}",
+                    change.name, change.old, change.new
+                );
+            }
+            for import in &module.imports_added {
+                println!(
+                    "   ➕ import {} {}",
+                    import.qualified_name(),
+                    import.signature
+                );
+            }
+            for import in &module.imports_removed {
+                println!(
+                    "   ➖ import {} {}",
+                    import.qualified_name(),
+                    import.signature
+                );
+            }
+            for change in &module.imports_changed {
+                println!(
+                    "   ✏️  import {}: {} → {}",
+                    change.name, change.old, change.new
+                );
+            }
+            for feature in &module.features_added {
+                println!("   ➕ requires feature {feature}");
+            }
+            for feature in &module.features_removed {
+                println!("   ➖ no longer requires feature {feature}");
+            }
+        }
+        println!();
+    }
+}
+
+#[cfg(feature = "cli")]
+fn print_compat_report(report: &wasmrust::CompatReport) {
+    if report.is_unchanged() {
+        println!("🔒 Export surface unchanged");
+        return;
+    }
+    for change in &report.breaking {
+        println!("💥 Breaking: {change}");
+    }
+    for change in &report.additive {
+        println!("➕ Additive: {change}");
+    }
+}
+
 #[cfg(feature = "cli")]
 fn main() -> Result<(), Box<dyn std::error::Error>> {
     let cli = Cli::parse();
@@ -245,8 +613,29 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
             output,
             optimization,
             target,
+            profile,
+            toolchain,
+            api_snapshot,
+            precompress,
+            content_hash,
+            integrity,
+            public_url,
+            features,
+            all_features,
+            no_default_features,
+            timeout,
             verbose,
+            dry_run,
+            force,
+            prune,
+            lock,
+            allow_unsafe_output,
         } => {
+            let optimization = match profile {
+                Some(profile) => OptimizationLevel::Custom(profile),
+                None => optimization.into(),
+            };
+
             if verbose {
                 print_header();
                 println!("🔨 Compiling Rust project to WebAssembly...");
@@ -261,21 +650,59 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
                 std::process::exit(1);
             }
 
-            if !check_dependencies(&plugin) {
+            if !dry_run && !check_dependencies(&plugin) {
                 std::process::exit(1);
             }
 
             let config = CompileConfig {
                 project_path: project.clone(),
                 output_dir: output,
-                optimization: optimization.into(),
+                optimization,
                 target_type: target.into(),
                 verbose,
+                compat: wasmrust::CompatConfig {
+                    snapshot: api_snapshot,
+                    ..Default::default()
+                },
+                precompress: precompress.then(wasmrust::PrecompressConfig::default),
+                content_hash,
+                integrity,
+                public_url,
+                features: features
+                    .iter()
+                    .flat_map(|f| f.split_whitespace())
+                    .map(String::from)
+                    .collect(),
+                all_features,
+                no_default_features,
+                toolchain,
+                step_timeout: timeout.map(std::time::Duration::from_secs),
+                force,
+                prune,
+                lock: lock.map(Into::into),
+                allow_unsafe_output_dir: allow_unsafe_output,
+                ..Default::default()
             };
 
-            match plugin.compile(&config) {
+            if dry_run {
+                match plugin.plan(&config) {
+                    Ok(plan) => print_build_plan(&plan),
+                    Err(e) => {
+                        eprintln!("❌ Cannot plan build: {e}");
+                        std::process::exit(1);
+                    }
+                }
+                return Ok(());
+            }
+
+            let display = ProgressDisplay::new(verbose);
+            match plugin.compile_with_events(&config, &|event| display.handle(event)) {
                 Ok(result) => {
-                    println!("✅ Compilation completed successfully!");
+                    if result.cached {
+                        println!("✅ Up to date, nothing changed since the last build");
+                    } else {
+                        println!("✅ Compilation completed successfully!");
+                    }
                     println!("🎯 WASM file: {}", result.wasm_path);
 
                     if let Some(js_path) = result.js_path {
@@ -286,6 +713,10 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
                         println!("🌐 Web application bundle created");
                     }
 
+                    if let Some(public_url) = &result.public_url {
+                        println!("🌍 Public URL: {public_url}");
+                    }
+
                     if !result.additional_files.is_empty() {
                         println!("📂 Additional files: {}", result.additional_files.len());
                         if verbose {
@@ -294,6 +725,81 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
                             }
                         }
                     }
+
+                    if verbose && !result.hashed_files.is_empty() {
+                        println!("🔖 Content-hashed files:");
+                        for (original, hashed) in &result.hashed_files {
+                            println!("   • {original} → {hashed}");
+                        }
+                    }
+
+                    for report in &result.integrity {
+                        println!(
+                            "🔒 Integrity: {} resources pinned in {}",
+                            report.resources.len(),
+                            report.html_path
+                        );
+                        if verbose {
+                            for resource in &report.resources {
+                                println!("   • {} {}", resource.reference, resource.integrity);
+                            }
+                        }
+                        println!("   Suggested header: {}", report.csp_header());
+                        println!("   Or meta tag: {}", report.csp_meta_tag());
+                    }
+
+                    if !result.compressed.is_empty() {
+                        println!("🗜️  Precompressed outputs:");
+                        for artifact in &result.compressed {
+                            let name = std::path::Path::new(&artifact.path)
+                                .file_name()
+                                .map(|n| n.to_string_lossy().to_string())
+                                .unwrap_or_else(|| artifact.path.clone());
+                            let mut sizes = vec![format!("{} bytes", artifact.size)];
+                            if let Some(gzip) = artifact.gzip_size {
+                                sizes.push(format!("gzip {gzip}"));
+                            }
+                            if let Some(brotli) = artifact.brotli_size {
+                                sizes.push(format!("br {brotli}"));
+                            }
+                            println!("   • {name}: {}", sizes.join(", "));
+                        }
+                    }
+
+                    if let Some(report) = &result.budget_report {
+                        for warning in report.warnings() {
+                            println!("⚠️  Near size budget: {warning}");
+                        }
+                    }
+
+                    if let Some(report) = &result.compat_report {
+                        print_compat_report(report);
+                    }
+                }
+                Err(wasmrust::WasmRustError::IncompatibleExports { changes }) => {
+                    eprintln!("❌ Breaking export changes:");
+                    for change in &changes {
+                        eprintln!("   • {change}");
+                    }
+                    std::process::exit(1);
+                }
+                Err(wasmrust::WasmRustError::UnhashableReferences {
+                    html_path,
+                    references,
+                }) => {
+                    eprintln!("❌ Cannot compute integrity for {html_path}:");
+                    for reference in &references {
+                        eprintln!("   • {reference}");
+                    }
+                    eprintln!("💡 Vendor external resources or fix references to missing files");
+                    std::process::exit(1);
+                }
+                Err(wasmrust::WasmRustError::BudgetExceeded { violations }) => {
+                    eprintln!("❌ Size budget exceeded:");
+                    for violation in &violations {
+                        eprintln!("   • {violation}");
+                    }
+                    std::process::exit(1);
                 }
                 Err(e) => {
                     eprintln!("❌ Compilation failed: {e}");
@@ -302,7 +808,7 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
             }
         }
 
-        Commands::Inspect { project } => {
+        Commands::Inspect { project, explain } => {
             print_header();
             println!("🔍 Inspecting Rust project...");
             println!();
@@ -327,11 +833,43 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
                         wasmrust::BuildStrategy::Trunk => "trunk + wasm-pack",
                     };
                     println!("🔧 Build Strategy: {strategy_desc}");
+                    println!("🦀 Toolchain: {}", info.toolchain);
+                    if let Some(file) = &info.toolchain.file {
+                        println!("   Pinned by: {file}");
+                    }
 
                     if !info.frameworks.is_empty() {
                         println!("🌐 Frameworks: {}", info.frameworks.join(", "));
                     }
 
+                    if !info.features.is_empty() {
+                        println!("🧩 Features:");
+                        for (feature, enables) in &info.features {
+                            if enables.is_empty() {
+                                println!("   • {feature}");
+                            } else {
+                                println!("   • {feature} → {}", enables.join(", "));
+                            }
+                        }
+                    }
+
+                    if !info.trunk_hooks.is_empty() {
+                        println!("🪝 Trunk hooks:");
+                        for hook in &info.trunk_hooks {
+                            println!("   • {hook}");
+                        }
+                    }
+
+                    if explain {
+                        println!();
+                        println!("🧭 Detection");
+                        println!("════════════");
+                        for evidence in &info.evidence {
+                            let mark = if evidence.accepted { "✅" } else { "➖" };
+                            println!("   {mark} {evidence}");
+                        }
+                    }
+
                     println!();
                     println!("📋 Dependencies");
                     println!("═══════════════");
@@ -341,8 +879,16 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
                     println!("Required:");
                     for dep in &info.dependencies.required {
                         let status = if dep.available { "✅" } else { "❌" };
-                        println!("   {} {} - {}", status, dep.name, dep.reason);
+                        println!(
+                            "   {} {} - {}",
+                            status,
+                            describe_dependency(dep),
+                            dep.reason
+                        );
                         if !dep.available {
+                            if let Some(fix) = &dep.fix {
+                                println!("      💡 {fix}");
+                            }
                             all_good = false;
                         }
                     }
@@ -352,7 +898,15 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
                         println!("Optional:");
                         for dep in &info.dependencies.optional {
                             let status = if dep.available { "✅" } else { "⚠️ " };
-                            println!("   {} {} - {}", status, dep.name, dep.reason);
+                            println!(
+                                "   {} {} - {}",
+                                status,
+                                describe_dependency(dep),
+                                dep.reason
+                            );
+                            if let Some(fix) = dep.fix.as_ref().filter(|_| !dep.available) {
+                                println!("      💡 {fix}");
+                            }
                         }
                     }
 
@@ -384,6 +938,44 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
             }
         }
 
+        Commands::Diff { old, new, top } => {
+            match wasmrust::diff_builds(std::path::Path::new(&old), std::path::Path::new(&new)) {
+                Ok(diff) => print_build_diff(&diff, top),
+                Err(e) => {
+                    eprintln!("❌ Failed to compare builds: {e}");
+                    std::process::exit(1);
+                }
+            }
+        }
+
+        Commands::Compat {
+            build,
+            snapshot,
+            update,
+        } => {
+            let snapshot_path = std::path::Path::new(&snapshot);
+            let surface = match wasmrust::ExportSurface::from_path(std::path::Path::new(&build)) {
+                Ok(surface) => surface,
+                Err(e) => {
+                    eprintln!("❌ Failed to read build: {e}");
+                    std::process::exit(1);
+                }
+            };
+
+            if !snapshot_path.exists() || update {
+                surface.write(snapshot_path)?;
+                println!("📸 Export snapshot written to {snapshot}");
+                return Ok(());
+            }
+
+            let baseline = wasmrust::ExportSurface::load(snapshot_path)?;
+            let report = wasmrust::check_compatibility(&baseline, &surface);
+            print_compat_report(&report);
+            if report.is_breaking() {
+                std::process::exit(1);
+            }
+        }
+
         Commands::CanHandle { project } => {
             if plugin.can_handle(&project) {
                 println!("✅ Yes, wasmrust can handle this project");
@@ -409,28 +1001,35 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
             println!();
 
             let missing = plugin.check_dependencies();
+            let target = plugin.wasm_target_status();
 
             if missing.is_empty() {
                 println!("✅ All required dependencies are available!");
+            }
 
-                // Show what we found
-                println!();
-                println!("📋 Available tools:");
-                println!("   ✅ cargo - Rust build tool");
-                println!("   ✅ rustc - Rust compiler");
-                println!("   ✅ wasm32-unknown-unknown - WebAssembly target");
-
-                // Check optional tools
-                if plugin.is_tool_available("wasm-pack") {
-                    println!("   ✅ wasm-pack - WebAssembly package tool");
-                }
-                if plugin.is_tool_available("trunk") {
-                    println!("   ✅ trunk - Web application bundler");
-                }
-                if plugin.is_tool_available("wasm-opt") {
-                    println!("   ✅ wasm-opt - WebAssembly optimizer");
+            // Show what we found
+            println!();
+            println!("📋 Tools:");
+            for tool in plugin.probe_tools() {
+                let minimum = wasmrust::tool_requirement(&tool.name)
+                    .map(wasmrust::ToolRequirement::minimum_version);
+                match (tool.is_available(), minimum) {
+                    (false, _) => println!("   ➖ {} - not installed", tool.name),
+                    (true, Some(minimum)) if tool.is_older_than(&minimum) => {
+                        println!("   ⚠️  {tool} - older than the supported {minimum}")
+                    }
+                    (true, _) => println!("   ✅ {tool}"),
                 }
-            } else {
+            }
+            if target.installed {
+                println!(
+                    "   ✅ wasm32-unknown-unknown - WebAssembly target (detected via {})",
+                    target.method
+                );
+            }
+
+            if !missing.is_empty() {
+                println!();
                 println!("❌ Missing required dependencies:");
                 for dep in &missing {
                     println!("   • {dep}");
@@ -439,7 +1038,16 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
                 println!();
                 println!("💡 Installation suggestions:");
                 println!("   • Install Rust: https://rustup.rs/");
-                println!("   • Add WASM target: rustup target add wasm32-unknown-unknown");
+                if missing.iter().any(|d| d.contains("is older than")) {
+                    println!("   • Update Rust: rustup update");
+                }
+                if target.method == wasmrust::TargetDetection::Rustup {
+                    println!("   • Add WASM target: rustup target add wasm32-unknown-unknown");
+                } else {
+                    println!(
+                        "   • Add WASM target: install your distribution's wasm32 Rust standard library"
+                    );
+                }
                 println!("   • Install wasm-pack: cargo install wasm-pack");
                 println!("   • Install trunk (for web apps): cargo install trunk");
                 println!("   • Install wasm-opt: cargo install wasm-opt");
@@ -0,0 +1,182 @@
+use crate::{CompileResult, OptimizationLevel, TargetType, WasmRustResult};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// File name of the manifest written into every output directory.
+pub const MANIFEST_FILE_NAME: &str = "wasmrust-manifest.json";
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct ManifestArtifact {
+    /// Path relative to the output directory, always using `/` separators
+    pub path: String,
+    pub size: u64,
+    #[serde(default)]
+    pub gzip_size: Option<u64>,
+    #[serde(default)]
+    pub brotli_size: Option<u64>,
+}
+
+/// Record of what a build produced, stored next to its outputs so later
+/// tooling (diffs, budgets, caching) can reason about a build without
+/// re-running it.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct BuildManifest {
+    pub package: String,
+    pub version: String,
+    pub optimization: OptimizationLevel,
+    pub target_type: TargetType,
+    pub is_webapp: bool,
+    /// Primary `.wasm` artifact, relative to the output directory
+    pub wasm_path: Option<String>,
+    pub js_path: Option<String>,
+    pub artifacts: Vec<ManifestArtifact>,
+    /// Original -> content-hashed file names
+    #[serde(default)]
+    pub hashed_files: BTreeMap<String, String>,
+    /// Fingerprint of the inputs the outputs were built from
+    #[serde(default)]
+    pub fingerprint: Option<String>,
+    /// What `compile` returned, handed out again while the fingerprint
+    /// still matches
+    #[serde(default)]
+    pub result: Option<CompileResult>,
+}
+
+impl BuildManifest {
+    pub fn from_result(result: &CompileResult, output_dir: &Path) -> WasmRustResult<Self> {
+        let mut artifacts = Vec::new();
+        for file in output_files(result) {
+            let compressed = result
+                .compressed
+                .iter()
+                .find(|c| Path::new(&c.path) == file);
+            artifacts.push(ManifestArtifact {
+                path: relative_path(&file, output_dir),
+                size: fs::metadata(&file)?.len(),
+                gzip_size: compressed.and_then(|c| c.gzip_size),
+                brotli_size: compressed.and_then(|c| c.brotli_size),
+            });
+        }
+
+        let wasm_path = if Path::new(&result.wasm_path).is_file() {
+            Some(relative_path(Path::new(&result.wasm_path), output_dir))
+        } else {
+            artifacts
+                .iter()
+                .find(|a| a.path.ends_with(".wasm"))
+                .map(|a| a.path.clone())
+        };
+
+        Ok(Self {
+            is_webapp: result.is_webapp,
+            wasm_path,
+            js_path: result
+                .js_path
+                .as_ref()
+                .map(|js| relative_path(Path::new(js), output_dir)),
+            artifacts,
+            hashed_files: result.hashed_files.clone(),
+            ..Default::default()
+        })
+    }
+
+    /// Reads a manifest from a file, or from `MANIFEST_FILE_NAME` inside a directory.
+    pub fn load(path: &Path) -> WasmRustResult<Self> {
+        let path = if path.is_dir() {
+            path.join(MANIFEST_FILE_NAME)
+        } else {
+            path.to_path_buf()
+        };
+        let content = fs::read_to_string(path)?;
+        Ok(serde_json::from_str(&content)?)
+    }
+
+    pub fn write(&self, output_dir: &Path) -> WasmRustResult<PathBuf> {
+        let path = output_dir.join(MANIFEST_FILE_NAME);
+        fs::write(&path, serde_json::to_string_pretty(self)?)?;
+        Ok(path)
+    }
+
+    pub fn artifact(&self, path: &str) -> Option<&ManifestArtifact> {
+        self.artifacts.iter().find(|a| a.path == path)
+    }
+
+    /// Maps a content-hashed artifact path back to its stable name, so builds
+    /// can be matched up even though their file names differ.
+    pub fn logical_path(&self, path: &str) -> String {
+        let (dir, name) = match path.rsplit_once('/') {
+            Some((dir, name)) => (Some(dir), name),
+            None => (None, path),
+        };
+
+        match self.hashed_files.iter().find(|(_, hashed)| *hashed == name) {
+            Some((original, _)) => match dir {
+                Some(dir) => format!("{dir}/{original}"),
+                None => original.clone(),
+            },
+            None => path.to_string(),
+        }
+    }
+
+    pub fn artifact_by_logical_path(&self, logical: &str) -> Option<&ManifestArtifact> {
+        self.artifacts
+            .iter()
+            .find(|a| self.logical_path(&a.path) == logical)
+    }
+}
+
+/// Lists every file belonging to a compilation's output set.
+pub(crate) fn output_files(result: &CompileResult) -> Vec<PathBuf> {
+    let mut files = Vec::new();
+
+    let wasm_path = Path::new(&result.wasm_path);
+    if wasm_path.is_dir() {
+        collect_files(wasm_path, &mut files);
+    } else {
+        let candidates = std::iter::once(&result.wasm_path)
+            .chain(result.js_path.iter())
+            .chain(result.additional_files.iter());
+        for candidate in candidates {
+            let path = PathBuf::from(candidate);
+            if path.is_file() && !files.contains(&path) {
+                files.push(path);
+            }
+        }
+    }
+
+    // The manifest and precompressed variants describe the output set rather
+    // than belong to it.
+    files.retain(|f| {
+        f.file_name().is_none_or(|name| name != MANIFEST_FILE_NAME)
+            && f.extension().is_none_or(|ext| ext != "gz" && ext != "br")
+    });
+    files
+}
+
+fn collect_files(dir: &Path, files: &mut Vec<PathBuf>) {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return;
+    };
+
+    let mut entries: Vec<_> = entries.flatten().map(|e| e.path()).collect();
+    entries.sort();
+
+    for path in entries {
+        if path.is_dir() {
+            collect_files(&path, files);
+        } else {
+            files.push(path);
+        }
+    }
+}
+
+fn relative_path(path: &Path, base: &Path) -> String {
+    let relative = path.strip_prefix(base).unwrap_or(path);
+    relative
+        .components()
+        .map(|c| c.as_os_str().to_string_lossy())
+        .collect::<Vec<_>>()
+        .join("/")
+}
@@ -0,0 +1,81 @@
+use crate::{WasmRustError, WasmRustResult};
+use std::path::{Component, Path, PathBuf};
+
+/// Fails when building into `output_dir` could overwrite the project: when
+/// it is or contains the project directory, its `src` or its `target`, or
+/// when a relative `output_dir` climbs out of the project with `..`.
+/// Relative paths are taken from the current directory, as the build takes
+/// them, and directories that don't exist yet are checked by where they
+/// would be.
+pub fn check_output_dir(project_path: &Path, output_dir: &str) -> WasmRustResult<()> {
+    let project = project_path.canonicalize()?;
+    let output = resolve(Path::new(output_dir))?;
+
+    let protected = [
+        ("the project directory", project.clone()),
+        ("the project's src directory", project.join("src")),
+        ("the project's target directory", project.join("target")),
+    ];
+    for (description, path) in protected {
+        let problem = if output == path {
+            "is"
+        } else if path.starts_with(&output) {
+            "contains"
+        } else {
+            continue;
+        };
+        return Err(unsafe_output_dir(format!(
+            "{output_dir} {problem} {description}"
+        )));
+    }
+
+    let relative = Path::new(output_dir).is_relative();
+    let climbs = Path::new(output_dir)
+        .components()
+        .any(|component| component == Component::ParentDir);
+    if relative && climbs && !output.starts_with(&project) {
+        return Err(unsafe_output_dir(format!(
+            "{output_dir} leads out of the project directory"
+        )));
+    }
+    Ok(())
+}
+
+fn unsafe_output_dir(problem: String) -> WasmRustError {
+    WasmRustError::InvalidConfiguration(format!(
+        "output_dir {problem}; set allow_unsafe_output_dir to build there anyway"
+    ))
+}
+
+/// Absolute, `..`-free form of `path` that compares with the canonical
+/// project path. The longest prefix that exists is canonicalized, so `..`
+/// after a symlink leads where the filesystem says; the rest cannot hold
+/// symlinks and is normalized as written.
+fn resolve(path: &Path) -> WasmRustResult<PathBuf> {
+    let path = std::path::absolute(path)?;
+    let mut existing = path.as_path();
+    let mut missing = Vec::new();
+    let mut resolved = loop {
+        if let Ok(canonical) = existing.canonicalize() {
+            break canonical;
+        }
+        match (existing.parent(), existing.components().next_back()) {
+            (Some(parent), Some(component)) => {
+                missing.push(component);
+                existing = parent;
+            }
+            _ => break existing.to_path_buf(),
+        }
+    };
+
+    for component in missing.into_iter().rev() {
+        match component {
+            Component::ParentDir => {
+                resolved.pop();
+            }
+            Component::CurDir => {}
+            other => resolved.push(other),
+        }
+    }
+    Ok(resolved)
+}
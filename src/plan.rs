@@ -0,0 +1,199 @@
+use crate::toolchain::{Toolchain, WASM_TARGET};
+use crate::trunk::{self, TrunkConfig};
+use crate::{
+    BuildStrategy, CompileConfig, OptimizationLevel, ProjectType, WasmRustError, WasmRustResult,
+};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// One process a build runs.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct PlannedCommand {
+    pub program: String,
+    #[serde(default)]
+    pub args: Vec<String>,
+    /// Working directory; `None` runs in the caller's directory
+    #[serde(default)]
+    pub cwd: Option<PathBuf>,
+    /// Variables set on top of the inherited environment
+    #[serde(default)]
+    pub env: Vec<(String, String)>,
+}
+
+impl PlannedCommand {
+    pub fn command(&self) -> Command {
+        let mut command = Command::new(&self.program);
+        command.args(&self.args).envs(self.env.iter().cloned());
+        if let Some(cwd) = &self.cwd {
+            command.current_dir(cwd);
+        }
+        command
+    }
+}
+
+impl fmt::Display for PlannedCommand {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (key, value) in &self.env {
+            write!(f, "{key}={} ", shell_quote(value))?;
+        }
+        write!(f, "{}", self.program)?;
+        for arg in &self.args {
+            write!(f, " {}", shell_quote(arg))?;
+        }
+        Ok(())
+    }
+}
+
+fn shell_quote(value: &str) -> String {
+    if !value.is_empty() && !value.contains(|c: char| c.is_whitespace() || c == '\'') {
+        value.to_string()
+    } else {
+        format!("'{}'", value.replace('\'', r"'\''"))
+    }
+}
+
+/// What `compile` would do for a configuration, without running anything.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BuildPlan {
+    pub project_type: ProjectType,
+    /// The strategy that will run, which differs from the detected one
+    /// when trunk is configured but not installed
+    pub build_strategy: BuildStrategy,
+    pub toolchain: Toolchain,
+    /// In execution order
+    pub commands: Vec<PlannedCommand>,
+    /// Files the build is expected to leave in the output directory, before
+    /// content hashing renames any of them
+    pub outputs: Vec<PathBuf>,
+    /// Caveats: fallbacks, missing tools, renamed outputs
+    #[serde(default)]
+    pub notes: Vec<String>,
+}
+
+pub(crate) fn cargo_build_command(config: &CompileConfig, toolchain: &Toolchain) -> PlannedCommand {
+    let mut args: Vec<String> = toolchain.cargo_arg().into_iter().collect();
+    args.extend(["build", "--target", WASM_TARGET].map(String::from));
+    args.extend(config.optimization.cargo_args());
+    args.extend(config.feature_args());
+
+    PlannedCommand {
+        program: "cargo".to_string(),
+        args,
+        cwd: Some(PathBuf::from(&config.project_path)),
+        env: config.optimization.cargo_env(),
+    }
+}
+
+pub(crate) fn wasm_pack_command(
+    config: &CompileConfig,
+    toolchain: &Toolchain,
+) -> WasmRustResult<PlannedCommand> {
+    let mut args = vec!["build", "--target", "web"];
+
+    match config.optimization {
+        OptimizationLevel::Debug => args.push("--dev"),
+        OptimizationLevel::Release => args.push("--release"),
+        OptimizationLevel::Size => {
+            args.push("--release");
+        }
+        OptimizationLevel::Profiling => args.push("--profiling"),
+        OptimizationLevel::Custom(ref profile) => {
+            return Err(WasmRustError::InvalidConfiguration(format!(
+                "wasm-pack only builds the dev, release and profiling profiles; \
+                 cargo profile '{profile}' needs the cargo or trunk strategy"
+            )));
+        }
+    }
+
+    args.extend(["--out-dir", &config.output_dir]);
+    let mut args: Vec<String> = args.into_iter().map(String::from).collect();
+
+    // wasm-pack forwards everything after `--` to cargo.
+    let feature_args = config.feature_args();
+    if !feature_args.is_empty() {
+        args.push("--".to_string());
+        args.extend(feature_args);
+    }
+
+    Ok(PlannedCommand {
+        program: "wasm-pack".to_string(),
+        args,
+        cwd: Some(PathBuf::from(&config.project_path)),
+        env: toolchain.env(),
+    })
+}
+
+/// A trunk build and where its output ends up.
+pub(crate) struct TrunkInvocation {
+    pub command: PlannedCommand,
+    /// Directory trunk writes to
+    pub dist: PathBuf,
+    /// Whether `dist` is the output directory itself; otherwise it is
+    /// copied there afterwards
+    pub direct_output: bool,
+}
+
+pub(crate) fn trunk_command(
+    config: &CompileConfig,
+    trunk_config: &TrunkConfig,
+    toolchain: &Toolchain,
+) -> WasmRustResult<TrunkInvocation> {
+    let project_path = Path::new(&config.project_path);
+    let output_dir = Path::new(&config.output_dir);
+
+    let mut args = vec!["build".to_string(), trunk_config.target().to_string()];
+
+    match &config.optimization {
+        OptimizationLevel::Debug => {}
+        OptimizationLevel::Release | OptimizationLevel::Profiling => {
+            args.push("--release".to_string())
+        }
+        OptimizationLevel::Size => {
+            args.extend(["--release".to_string(), "--minify".to_string()]);
+        }
+        OptimizationLevel::Custom(profile) => {
+            args.extend(["--cargo-profile".to_string(), profile.clone()]);
+        }
+    }
+
+    // Without a direct target trunk writes to its configured dist and the
+    // result is copied over.
+    let direct_output = trunk::can_write_directly(project_path, output_dir);
+    let dist = if direct_output {
+        let dist = trunk::resolve_output_dir(output_dir)?;
+        args.extend(["--dist".to_string(), dist.to_string_lossy().to_string()]);
+        dist
+    } else {
+        project_path.join(trunk_config.dist())
+    };
+
+    if let Some(public_url) = &config.public_url {
+        args.extend(["--public-url".to_string(), public_url.clone()]);
+    }
+
+    args.extend(config.feature_args());
+
+    let mut env = config.optimization.cargo_env();
+    env.extend(toolchain.env());
+
+    Ok(TrunkInvocation {
+        command: PlannedCommand {
+            program: "trunk".to_string(),
+            args,
+            cwd: Some(project_path.to_path_buf()),
+            env,
+        },
+        dist,
+        direct_output,
+    })
+}
+
+pub(crate) fn rustup_command(toolchain: &Toolchain, args: &[&str]) -> PlannedCommand {
+    PlannedCommand {
+        program: "rustup".to_string(),
+        args: toolchain.rustup_args(args),
+        ..Default::default()
+    }
+}
@@ -0,0 +1,228 @@
+use crate::runner::{CommandRunner, ProcessRunner};
+use crate::toolchain::{TargetStatus, Toolchain, ToolchainFile};
+use crate::tools::ToolInfo;
+use crate::WasmRustResult;
+use std::collections::HashMap;
+use std::env;
+use std::ffi::OsString;
+use std::fmt;
+use std::fs;
+use std::hash::Hash;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::{Duration, Instant, SystemTime};
+
+/// How long a probe result is reused before the tool is asked again.
+pub const DEFAULT_PROBE_TTL: Duration = Duration::from_secs(30);
+
+type ToolKey = (String, Vec<(String, String)>);
+type ToolchainKey = (
+    PathBuf,
+    Option<String>,
+    Option<OsString>,
+    Option<(PathBuf, Option<SystemTime>)>,
+);
+type InstallKey = (Option<String>, String);
+
+/// Remembers the results of spawning toolchain processes (`--version`,
+/// `rustup target list`, ...) so watch-mode rebuilds don't repeat them.
+/// Everything is dropped when `PATH` changes, since a different `PATH` can
+/// mean different tools, and each entry expires after the TTL.
+pub struct ProbeCache {
+    ttl: Duration,
+    runner: Arc<dyn CommandRunner>,
+    entries: Mutex<Entries>,
+}
+
+impl fmt::Debug for ProbeCache {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ProbeCache")
+            .field("ttl", &self.ttl)
+            .field("entries", &self.entries)
+            .finish_non_exhaustive()
+    }
+}
+
+#[derive(Debug, Default)]
+struct Entries {
+    path: Option<OsString>,
+    tools: HashMap<ToolKey, Cached<ToolInfo>>,
+    toolchains: HashMap<ToolchainKey, Cached<Toolchain>>,
+    targets: HashMap<InstallKey, Cached<TargetStatus>>,
+    components: HashMap<InstallKey, Cached<bool>>,
+}
+
+#[derive(Debug)]
+struct Cached<T> {
+    value: T,
+    probed_at: Instant,
+}
+
+impl Default for ProbeCache {
+    fn default() -> Self {
+        Self::new(DEFAULT_PROBE_TTL)
+    }
+}
+
+impl ProbeCache {
+    pub fn new(ttl: Duration) -> Self {
+        Self::with_runner(ttl, Arc::new(ProcessRunner))
+    }
+
+    /// A cache whose misses run their probes through `runner`.
+    pub fn with_runner(ttl: Duration, runner: Arc<dyn CommandRunner>) -> Self {
+        Self {
+            ttl,
+            runner,
+            entries: Mutex::new(Entries {
+                path: env::var_os("PATH"),
+                ..Default::default()
+            }),
+        }
+    }
+
+    pub fn ttl(&self) -> Duration {
+        self.ttl
+    }
+
+    /// Forgets every probe, so the next lookups spawn the tools again.
+    pub fn clear(&self) {
+        *self.entries() = Entries {
+            path: env::var_os("PATH"),
+            ..Default::default()
+        };
+    }
+
+    /// Number of probe results that are still fresh.
+    pub fn len(&self) -> usize {
+        let entries = self.entries();
+        self.fresh(&entries.tools)
+            + self.fresh(&entries.toolchains)
+            + self.fresh(&entries.targets)
+            + self.fresh(&entries.components)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn tool(&self, name: &str, envs: &[(String, String)]) -> ToolInfo {
+        let key = (name.to_string(), envs.to_vec());
+        if let Some(info) = self.lookup(|e| &mut e.tools, &key) {
+            return info;
+        }
+        let info = ToolInfo::probe(self.runner.as_ref(), name, envs);
+        self.store(|e| &mut e.tools, key, info.clone());
+        info
+    }
+
+    /// [`Toolchain::resolve`], reused until the TTL passes or the project's
+    /// toolchain file or `RUSTUP_TOOLCHAIN` changes.
+    pub fn toolchain(
+        &self,
+        project_path: &Path,
+        requested: Option<&str>,
+    ) -> WasmRustResult<Toolchain> {
+        let file = ToolchainFile::find(project_path).map(|file| {
+            let modified = fs::metadata(&file).and_then(|m| m.modified()).ok();
+            (file, modified)
+        });
+        let key = (
+            project_path
+                .canonicalize()
+                .unwrap_or_else(|_| project_path.to_path_buf()),
+            requested.map(str::to_string),
+            env::var_os("RUSTUP_TOOLCHAIN"),
+            file,
+        );
+        if let Some(toolchain) = self.lookup(|e| &mut e.toolchains, &key) {
+            return Ok(toolchain);
+        }
+        let toolchain = Toolchain::resolve(self.runner.as_ref(), project_path, requested)?;
+        self.store(|e| &mut e.toolchains, key, toolchain.clone());
+        Ok(toolchain)
+    }
+
+    pub fn target(&self, toolchain: &Toolchain, target: &str) -> TargetStatus {
+        let key = (toolchain.name.clone(), target.to_string());
+        if let Some(status) = self.lookup(|e| &mut e.targets, &key) {
+            return status;
+        }
+        let status = toolchain.detect_target(self.runner.as_ref(), target);
+        self.store(|e| &mut e.targets, key, status.clone());
+        status
+    }
+
+    pub fn component(&self, toolchain: &Toolchain, component: &str) -> bool {
+        let key = (toolchain.name.clone(), component.to_string());
+        if let Some(installed) = self.lookup(|e| &mut e.components, &key) {
+            return installed;
+        }
+        let installed = toolchain.is_component_installed(self.runner.as_ref(), component);
+        self.store(|e| &mut e.components, key, installed);
+        installed
+    }
+
+    /// Drops what is known about a toolchain's targets and components,
+    /// after something was installed into it.
+    pub fn invalidate_toolchain(&self, toolchain: &Toolchain) {
+        let mut entries = self.entries();
+        entries
+            .targets
+            .retain(|(name, _), _| *name != toolchain.name);
+        entries
+            .components
+            .retain(|(name, _), _| *name != toolchain.name);
+    }
+
+    fn lookup<K: Eq + Hash, V: Clone>(
+        &self,
+        select: impl FnOnce(&mut Entries) -> &mut HashMap<K, Cached<V>>,
+        key: &K,
+    ) -> Option<V> {
+        let mut entries = self.entries();
+        select(&mut entries)
+            .get(key)
+            .filter(|cached| cached.probed_at.elapsed() < self.ttl)
+            .map(|cached| cached.value.clone())
+    }
+
+    // Probes run outside the lock; concurrent misses may both probe, and the
+    // later result wins.
+    fn store<K: Eq + Hash, V>(
+        &self,
+        select: impl FnOnce(&mut Entries) -> &mut HashMap<K, Cached<V>>,
+        key: K,
+        value: V,
+    ) {
+        let mut entries = self.entries();
+        select(&mut entries).insert(
+            key,
+            Cached {
+                value,
+                probed_at: Instant::now(),
+            },
+        );
+    }
+
+    fn fresh<K, V>(&self, map: &HashMap<K, Cached<V>>) -> usize {
+        map.values()
+            .filter(|cached| cached.probed_at.elapsed() < self.ttl)
+            .count()
+    }
+
+    fn entries(&self) -> MutexGuard<'_, Entries> {
+        let mut entries = self
+            .entries
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        let path = env::var_os("PATH");
+        if entries.path != path {
+            *entries = Entries {
+                path,
+                ..Default::default()
+            };
+        }
+        entries
+    }
+}
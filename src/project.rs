@@ -0,0 +1,378 @@
+use crate::tools::locked_version;
+use crate::trunk::TrunkConfig;
+use crate::{BuildStrategy, ProjectType, WasmRustError, WasmRustResult};
+use semver::Version;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Rust web frameworks that make a crate a web application.
+pub const WEB_FRAMEWORKS: &[&str] = &[
+    "yew", "leptos", "dioxus", "sycamore", "mogwai", "seed", "percy", "iced", "dodrio", "smithy",
+];
+
+/// Dependencies that mean the crate talks to JavaScript through wasm-bindgen.
+pub const WASM_BINDGEN_CRATES: &[&str] = &["wasm-bindgen", "web-sys", "js-sys"];
+
+/// Directories whose presence marks a `cdylib` bindgen crate as a web app.
+pub const STATIC_DIRS: &[&str] = &["public", "static", "assets", "dist", "www"];
+
+/// What wasmrust knows about a project, read once from its Cargo.toml,
+/// Cargo.lock, Trunk.toml and directory layout. Inspection and compilation
+/// both route on this, so they always agree on the project type and build
+/// strategy.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProjectModel {
+    pub path: PathBuf,
+    pub name: String,
+    pub version: String,
+    /// `[features]` table: feature name -> what it enables
+    #[serde(default)]
+    pub features: BTreeMap<String, Vec<String>>,
+    /// Package names of normal and target-specific dependencies
+    #[serde(default)]
+    pub dependencies: BTreeSet<String>,
+    #[serde(default)]
+    pub crate_types: Vec<String>,
+    #[serde(default)]
+    pub trunk_config_path: Option<PathBuf>,
+    #[serde(default)]
+    pub trunk: Option<TrunkConfig>,
+    #[serde(default)]
+    pub locked_wasm_bindgen: Option<Version>,
+    #[serde(default)]
+    pub has_index_html: bool,
+    /// Entries of [`STATIC_DIRS`] present in the project
+    #[serde(default)]
+    pub static_dirs: Vec<String>,
+    /// Detected web frameworks, plus `trunk` when the project has a Trunk.toml
+    #[serde(default)]
+    pub frameworks: Vec<String>,
+    pub project_type: ProjectType,
+    pub build_strategy: BuildStrategy,
+    /// How `project_type` and `build_strategy` were arrived at
+    #[serde(default)]
+    pub evidence: Vec<DetectionEvidence>,
+}
+
+/// The two choices detection makes.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub enum Decision {
+    ProjectType,
+    BuildStrategy,
+}
+
+impl fmt::Display for Decision {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Decision::ProjectType => write!(f, "project type"),
+            Decision::BuildStrategy => write!(f, "build strategy"),
+        }
+    }
+}
+
+/// Something in the project that a decision rested on.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub enum EvidenceSource {
+    Dependency(String),
+    File(String),
+    Directory(String),
+    /// A Cargo.toml key and its value, e.g. `lib.crate-type = cdylib`
+    ConfigKey(String),
+}
+
+impl fmt::Display for EvidenceSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            EvidenceSource::Dependency(name) => write!(f, "dependency `{name}`"),
+            EvidenceSource::File(path) => write!(f, "file {path}"),
+            EvidenceSource::Directory(path) => write!(f, "directory {path}/"),
+            EvidenceSource::ConfigKey(key) => write!(f, "Cargo.toml `{key}`"),
+        }
+    }
+}
+
+/// Why a candidate project type or build strategy was chosen or rejected.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct DetectionEvidence {
+    pub decision: Decision,
+    pub candidate: String,
+    pub accepted: bool,
+    pub reason: String,
+    #[serde(default)]
+    pub sources: Vec<EvidenceSource>,
+}
+
+impl fmt::Display for DetectionEvidence {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let verdict = if self.accepted { "chosen" } else { "rejected" };
+        write!(
+            f,
+            "{} {} {verdict}: {}",
+            self.decision, self.candidate, self.reason
+        )?;
+        if !self.sources.is_empty() {
+            let sources: Vec<String> = self.sources.iter().map(ToString::to_string).collect();
+            write!(f, " ({})", sources.join(", "))?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Deserialize)]
+struct CargoManifest {
+    package: CargoPackage,
+    #[serde(default)]
+    features: BTreeMap<String, Vec<String>>,
+    #[serde(default)]
+    dependencies: BTreeMap<String, toml::Value>,
+    #[serde(default)]
+    target: BTreeMap<String, CargoTarget>,
+    #[serde(default)]
+    lib: Option<CargoLib>,
+}
+
+#[derive(Deserialize)]
+struct CargoPackage {
+    name: String,
+    version: String,
+}
+
+#[derive(Deserialize)]
+struct CargoTarget {
+    #[serde(default)]
+    dependencies: BTreeMap<String, toml::Value>,
+}
+
+#[derive(Deserialize)]
+struct CargoLib {
+    #[serde(default, rename = "crate-type")]
+    crate_type: Vec<String>,
+}
+
+impl ProjectModel {
+    pub fn load(project_path: &Path) -> WasmRustResult<Self> {
+        let cargo_toml_path = project_path.join("Cargo.toml");
+        if !cargo_toml_path.exists() {
+            return Err(WasmRustError::InvalidProject(
+                "No Cargo.toml found".to_string(),
+            ));
+        }
+        let manifest: CargoManifest = toml::from_str(&fs::read_to_string(&cargo_toml_path)?)?;
+
+        let dependencies = manifest
+            .dependencies
+            .iter()
+            .chain(manifest.target.values().flat_map(|t| &t.dependencies))
+            .map(|(key, spec)| dependency_package(key, spec))
+            .collect::<BTreeSet<_>>();
+
+        let trunk_config_path = TrunkConfig::find(project_path);
+        let trunk = TrunkConfig::load(project_path)?;
+
+        let mut frameworks: Vec<String> = WEB_FRAMEWORKS
+            .iter()
+            .filter(|framework| dependencies.contains(**framework))
+            .map(|framework| framework.to_string())
+            .collect();
+        if trunk_config_path.is_some() {
+            frameworks.push("trunk".to_string());
+        }
+
+        let mut model = Self {
+            path: project_path.to_path_buf(),
+            name: manifest.package.name,
+            version: manifest.package.version,
+            features: manifest.features,
+            dependencies,
+            crate_types: manifest.lib.map(|lib| lib.crate_type).unwrap_or_default(),
+            trunk_config_path,
+            trunk,
+            locked_wasm_bindgen: locked_version(project_path, "wasm-bindgen")?,
+            has_index_html: project_path.join("index.html").is_file(),
+            static_dirs: STATIC_DIRS
+                .iter()
+                .filter(|dir| project_path.join(dir).is_dir())
+                .map(|dir| dir.to_string())
+                .collect(),
+            frameworks,
+            project_type: ProjectType::StandardWasm,
+            build_strategy: BuildStrategy::Cargo,
+            evidence: Vec::new(),
+        };
+        let (project_type, mut evidence) = model.detect_project_type();
+        let (build_strategy, strategy_evidence) = model.select_build_strategy(&project_type);
+        evidence.extend(strategy_evidence);
+        model.project_type = project_type;
+        model.build_strategy = build_strategy;
+        model.evidence = evidence;
+        Ok(model)
+    }
+
+    /// The crate name as it appears in output file names.
+    pub fn package_name(&self) -> String {
+        self.name.replace('-', "_")
+    }
+
+    pub fn uses_wasm_bindgen(&self) -> bool {
+        WASM_BINDGEN_CRATES
+            .iter()
+            .any(|dep| self.dependencies.contains(*dep))
+    }
+
+    pub fn is_web_application(&self) -> bool {
+        self.project_type == ProjectType::WebApplication
+    }
+
+    pub fn is_cdylib(&self) -> bool {
+        self.crate_types.iter().any(|t| t == "cdylib")
+    }
+
+    fn dependency_sources(&self, names: &[&str]) -> Vec<EvidenceSource> {
+        names
+            .iter()
+            .filter(|name| self.dependencies.contains(**name))
+            .map(|name| EvidenceSource::Dependency(name.to_string()))
+            .collect()
+    }
+
+    fn trunk_source(&self) -> Option<EvidenceSource> {
+        self.trunk_config_path.as_ref().map(|path| {
+            let name = path.file_name().unwrap_or(path.as_os_str());
+            EvidenceSource::File(name.to_string_lossy().to_string())
+        })
+    }
+
+    // Frameworks bring wasm-bindgen in themselves. A plain bindgen crate is
+    // a web app when trunk builds it, or when it is a cdylib with a page or
+    // static assets next to it.
+    fn detect_project_type(&self) -> (ProjectType, Vec<DetectionEvidence>) {
+        let frameworks = self.dependency_sources(WEB_FRAMEWORKS);
+        let bindgen = self.dependency_sources(WASM_BINDGEN_CRATES);
+        let trunk = self.trunk_source();
+        let cdylib = self
+            .is_cdylib()
+            .then(|| EvidenceSource::ConfigKey("lib.crate-type = cdylib".to_string()));
+        let mut page: Vec<EvidenceSource> = self
+            .static_dirs
+            .iter()
+            .map(|dir| EvidenceSource::Directory(dir.clone()))
+            .collect();
+        if self.has_index_html {
+            page.insert(0, EvidenceSource::File("index.html".to_string()));
+        }
+
+        let (project_type, web_reason, web_sources) = if !frameworks.is_empty() {
+            (
+                ProjectType::WebApplication,
+                "depends on a web framework",
+                frameworks,
+            )
+        } else if let Some(trunk) = trunk.filter(|_| !bindgen.is_empty()) {
+            (
+                ProjectType::WebApplication,
+                "wasm-bindgen crate built by trunk",
+                bindgen.iter().cloned().chain([trunk]).collect(),
+            )
+        } else if !bindgen.is_empty() && cdylib.is_some() && !page.is_empty() {
+            (
+                ProjectType::WebApplication,
+                "wasm-bindgen cdylib served with a page",
+                bindgen.iter().cloned().chain(cdylib).chain(page).collect(),
+            )
+        } else if !bindgen.is_empty() {
+            (
+                ProjectType::WasmBindgen,
+                "no web framework, Trunk.toml, or cdylib with index.html or a static directory",
+                Vec::new(),
+            )
+        } else {
+            (
+                ProjectType::StandardWasm,
+                "no web framework and no wasm-bindgen dependency",
+                Vec::new(),
+            )
+        };
+
+        let other_reason = match (&project_type, bindgen.is_empty()) {
+            (ProjectType::WebApplication, _) => "the project is a web application",
+            (_, true) => "no wasm-bindgen, web-sys or js-sys dependency",
+            (_, false) => "uses wasm-bindgen",
+        };
+        let evidence = |candidate: ProjectType, reason: &str, sources| DetectionEvidence {
+            decision: Decision::ProjectType,
+            candidate: format!("{candidate:?}"),
+            accepted: candidate == project_type,
+            reason: reason.to_string(),
+            sources,
+        };
+
+        let evidence = vec![
+            evidence(ProjectType::WebApplication, web_reason, web_sources),
+            evidence(ProjectType::WasmBindgen, other_reason, bindgen),
+            evidence(ProjectType::StandardWasm, other_reason, Vec::new()),
+        ];
+        (project_type, evidence)
+    }
+
+    fn select_build_strategy(
+        &self,
+        project_type: &ProjectType,
+    ) -> (BuildStrategy, Vec<DetectionEvidence>) {
+        let trunk = self.trunk_source();
+        let strategy = match project_type {
+            ProjectType::StandardWasm => BuildStrategy::Cargo,
+            ProjectType::WasmBindgen => BuildStrategy::WasmPack,
+            ProjectType::WebApplication if trunk.is_some() => BuildStrategy::Trunk,
+            ProjectType::WebApplication => BuildStrategy::WasmPack,
+        };
+
+        let reason = |candidate: &BuildStrategy| match (candidate, project_type) {
+            (BuildStrategy::Cargo, ProjectType::StandardWasm) => "plain wasm32 crate",
+            (BuildStrategy::Cargo, _) => "wasm-bindgen output needs wasm-pack or trunk",
+            (BuildStrategy::Trunk, ProjectType::WebApplication) if trunk.is_some() => {
+                "web application with a trunk config"
+            }
+            (BuildStrategy::Trunk, ProjectType::WebApplication) => "no Trunk.toml",
+            (BuildStrategy::Trunk, _) => "only web applications build with trunk",
+            (BuildStrategy::WasmPack, ProjectType::StandardWasm) => "no wasm-bindgen dependency",
+            (BuildStrategy::WasmPack, ProjectType::WebApplication) if trunk.is_some() => {
+                "trunk is configured"
+            }
+            (BuildStrategy::WasmPack, ProjectType::WebApplication) => {
+                "web application without a Trunk.toml"
+            }
+            (BuildStrategy::WasmPack, ProjectType::WasmBindgen) => "wasm-bindgen library",
+        };
+
+        let evidence = [
+            BuildStrategy::Trunk,
+            BuildStrategy::WasmPack,
+            BuildStrategy::Cargo,
+        ]
+        .into_iter()
+        .map(|candidate| DetectionEvidence {
+            decision: Decision::BuildStrategy,
+            candidate: format!("{candidate:?}"),
+            accepted: candidate == strategy,
+            reason: reason(&candidate).to_string(),
+            sources: match candidate {
+                BuildStrategy::Trunk => trunk.iter().cloned().collect(),
+                _ => Vec::new(),
+            },
+        })
+        .collect();
+
+        (strategy, evidence)
+    }
+}
+
+// `web = { package = "web-sys", ... }` depends on web-sys under another name.
+fn dependency_package(key: &str, spec: &toml::Value) -> String {
+    spec.get("package")
+        .and_then(toml::Value::as_str)
+        .unwrap_or(key)
+        .to_string()
+}
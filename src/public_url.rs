@@ -0,0 +1,107 @@
+use crate::manifest::output_files;
+use crate::{CompileResult, WasmRustResult};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Base path used when no `public_url` is configured.
+pub const DEFAULT_PUBLIC_URL: &str = "/";
+
+// Outputs that may hold root-relative references to other outputs.
+const REWRITE_EXTENSIONS: &[&str] = &["html", "js", "mjs"];
+
+/// Normalizes a configured base path to the form trunk uses: `apps/demo`
+/// becomes `/apps/demo/`. Full URLs and `./`-relative paths keep their
+/// prefix and only gain a trailing slash.
+pub fn normalize_public_url(url: &str) -> String {
+    let url = url.trim();
+    if url.is_empty() || url == "/" {
+        return DEFAULT_PUBLIC_URL.to_string();
+    }
+
+    let mut normalized = if url.contains("://") || url.starts_with('/') || url.starts_with('.') {
+        url.to_string()
+    } else {
+        format!("/{url}")
+    };
+    if !normalized.ends_with('/') {
+        normalized.push('/');
+    }
+    normalized
+}
+
+/// Rewrites root-relative references (`"/app_bg.wasm"`) in the HTML and JS
+/// outputs of a non-trunk build so they resolve under `public_url`. Only
+/// references to files that exist in `output_dir` are touched. Returns the
+/// rewritten files.
+pub fn apply_public_url(
+    result: &CompileResult,
+    output_dir: &Path,
+    public_url: &str,
+) -> WasmRustResult<Vec<PathBuf>> {
+    let mut rewritten = Vec::new();
+    if public_url == DEFAULT_PUBLIC_URL {
+        return Ok(rewritten);
+    }
+
+    for file in output_files(result) {
+        let rewritable = file
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .is_some_and(|ext| REWRITE_EXTENSIONS.contains(&ext));
+        if !rewritable {
+            continue;
+        }
+
+        let Ok(content) = fs::read_to_string(&file) else {
+            continue;
+        };
+        let updated = rewrite_root_references(&content, public_url, output_dir);
+        if updated != content {
+            fs::write(&file, updated)?;
+            rewritten.push(file);
+        }
+    }
+
+    Ok(rewritten)
+}
+
+pub(crate) fn rewrite_root_references(
+    content: &str,
+    public_url: &str,
+    output_dir: &Path,
+) -> String {
+    let mut output = String::with_capacity(content.len());
+    let mut rest = content;
+
+    while let Some(start) = rest.find(['\'', '"', '`']) {
+        let quote = rest[start..].chars().next().unwrap_or('"');
+        let body = &rest[start + 1..];
+        let Some(end) = body.find(quote) else {
+            break;
+        };
+
+        let literal = &body[..end];
+        output.push_str(&rest[..=start]);
+        match literal
+            .strip_prefix('/')
+            .filter(|path| !path.starts_with('/') && !literal.starts_with(public_url))
+            .filter(|path| is_output_file(path, output_dir))
+        {
+            Some(path) => {
+                output.push_str(public_url);
+                output.push_str(path);
+            }
+            None => output.push_str(literal),
+        }
+        output.push(quote);
+        rest = &body[end + 1..];
+    }
+
+    output.push_str(rest);
+    output
+}
+
+fn is_output_file(path: &str, output_dir: &Path) -> bool {
+    let path = path.split(['?', '#']).next().unwrap_or(path);
+    !path.is_empty() && output_dir.join(path).is_file()
+}
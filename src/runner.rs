@@ -0,0 +1,297 @@
+use crate::plan::PlannedCommand;
+use serde::{Deserialize, Serialize};
+use std::io::{self, BufRead, BufReader, Read};
+use std::process::{Child, Command, Stdio};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// How often a running step checks for cancellation and its deadline, and a
+/// waiting build retries the build lock.
+pub(crate) const POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+/// How long after a tool exited its pipes may stay open with nothing left to
+/// read before they are taken to be held by something it left running.
+const EXIT_GRACE: Duration = Duration::from_millis(500);
+
+/// Which pipe a line of tool output came from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OutputStream {
+    Stdout,
+    Stderr,
+}
+
+/// What a finished process left behind.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct CommandOutput {
+    /// Exit code; `None` when the process was killed by a signal
+    pub status: Option<i32>,
+    #[serde(default)]
+    pub stdout: Vec<u8>,
+    #[serde(default)]
+    pub stderr: Vec<u8>,
+}
+
+impl CommandOutput {
+    /// A successful run that printed `stdout`.
+    pub fn ok(stdout: &str) -> Self {
+        Self {
+            status: Some(0),
+            stdout: stdout.as_bytes().to_vec(),
+            stderr: Vec::new(),
+        }
+    }
+
+    /// A run that exited with `code` after printing `stderr`.
+    pub fn failed(code: i32, stderr: &str) -> Self {
+        Self {
+            status: Some(code),
+            stdout: Vec::new(),
+            stderr: stderr.as_bytes().to_vec(),
+        }
+    }
+
+    pub fn success(&self) -> bool {
+        self.status == Some(0)
+    }
+}
+
+impl From<std::process::Output> for CommandOutput {
+    fn from(output: std::process::Output) -> Self {
+        Self {
+            status: output.status.code(),
+            stdout: output.stdout,
+            stderr: output.stderr,
+        }
+    }
+}
+
+/// Stops a build from another thread, e.g. when a newer file change makes it
+/// stale. Clones share one flag.
+#[derive(Debug, Clone, Default)]
+pub struct CancellationToken(Arc<AtomicBool>);
+
+impl CancellationToken {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::SeqCst);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::SeqCst)
+    }
+}
+
+/// When a running step has to be stopped. A stopped step fails with
+/// [`io::ErrorKind::Interrupted`] when cancelled and
+/// [`io::ErrorKind::TimedOut`] when it ran past `timeout`.
+#[derive(Debug, Clone, Default)]
+pub struct StepLimits {
+    pub cancel: Option<CancellationToken>,
+    pub timeout: Option<Duration>,
+}
+
+impl StepLimits {
+    pub fn is_cancelled(&self) -> bool {
+        self.cancel
+            .as_ref()
+            .is_some_and(CancellationToken::is_cancelled)
+    }
+
+    fn is_unlimited(&self) -> bool {
+        self.cancel.is_none() && self.timeout.is_none()
+    }
+}
+
+fn cancelled(command: &PlannedCommand) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::Interrupted,
+        format!("{} was cancelled", command.program),
+    )
+}
+
+/// Runs the external processes a build needs: cargo, wasm-pack, trunk,
+/// rustup and the `--version` probes. A runner that cannot start a program
+/// because it is not installed returns [`io::ErrorKind::NotFound`].
+pub trait CommandRunner: Send + Sync {
+    fn run(&self, command: &PlannedCommand) -> io::Result<CommandOutput>;
+
+    /// Like [`run`](Self::run), but hands each line to `on_line` as the
+    /// tool prints it and stops the tool when `limits` say so. The default
+    /// only checks for cancellation up front and replays the finished
+    /// output, which suits runners that don't spawn anything.
+    fn run_streaming(
+        &self,
+        command: &PlannedCommand,
+        limits: &StepLimits,
+        on_line: &mut dyn FnMut(OutputStream, &str),
+    ) -> io::Result<CommandOutput> {
+        if limits.is_cancelled() {
+            return Err(cancelled(command));
+        }
+        let output = self.run(command)?;
+        for line in String::from_utf8_lossy(&output.stdout).lines() {
+            on_line(OutputStream::Stdout, line);
+        }
+        for line in String::from_utf8_lossy(&output.stderr).lines() {
+            on_line(OutputStream::Stderr, line);
+        }
+        Ok(output)
+    }
+}
+
+/// Spawns real processes and waits for them.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ProcessRunner;
+
+impl CommandRunner for ProcessRunner {
+    fn run(&self, command: &PlannedCommand) -> io::Result<CommandOutput> {
+        command.command().output().map(CommandOutput::from)
+    }
+
+    fn run_streaming(
+        &self,
+        command: &PlannedCommand,
+        limits: &StepLimits,
+        on_line: &mut dyn FnMut(OutputStream, &str),
+    ) -> io::Result<CommandOutput> {
+        if limits.is_cancelled() {
+            return Err(cancelled(command));
+        }
+        let mut process = command.command();
+        process
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped());
+        // A step that may be stopped gets its own process group, so the
+        // compilers and linkers it spawns can be killed with it. Unlimited
+        // steps stay in ours and receive the terminal's Ctrl+C.
+        #[cfg(unix)]
+        if !limits.is_unlimited() {
+            std::os::unix::process::CommandExt::process_group(&mut process, 0);
+        }
+        let mut child = process.spawn()?;
+        let deadline = limits.timeout.map(|timeout| Instant::now() + timeout);
+
+        // One reader per pipe, so neither blocks the tool while the other
+        // is drained.
+        let (sender, lines) = mpsc::channel();
+        let pipes: [(OutputStream, Option<Box<dyn Read + Send>>); 2] = [
+            (
+                OutputStream::Stdout,
+                child.stdout.take().map(|pipe| Box::new(pipe) as _),
+            ),
+            (
+                OutputStream::Stderr,
+                child.stderr.take().map(|pipe| Box::new(pipe) as _),
+            ),
+        ];
+        let readers: Vec<_> = pipes
+            .into_iter()
+            .filter_map(|(stream, pipe)| Some((stream, pipe?)))
+            .map(|(stream, pipe)| {
+                let sender = sender.clone();
+                thread::spawn(move || {
+                    let mut reader = BufReader::new(pipe);
+                    let mut line = Vec::new();
+                    while reader.read_until(b'\n', &mut line).unwrap_or(0) > 0 {
+                        if sender.send((stream, std::mem::take(&mut line))).is_err() {
+                            break;
+                        }
+                    }
+                })
+            })
+            .collect();
+        drop(sender);
+
+        let mut output = CommandOutput::default();
+        let mut pipes_open = true;
+        let mut exited_at: Option<Instant> = None;
+        loop {
+            if exited_at.is_none() {
+                if limits.is_cancelled() {
+                    kill_tree(&mut child);
+                    return Err(cancelled(command));
+                }
+                if deadline.is_some_and(|deadline| Instant::now() >= deadline) {
+                    kill_tree(&mut child);
+                    return Err(io::Error::new(
+                        io::ErrorKind::TimedOut,
+                        format!("{} timed out", command.program),
+                    ));
+                }
+            }
+
+            if pipes_open {
+                match lines.recv_timeout(POLL_INTERVAL) {
+                    Ok((stream, line)) => {
+                        let text = String::from_utf8_lossy(&line);
+                        on_line(stream, text.trim_end_matches(['\n', '\r']));
+                        match stream {
+                            OutputStream::Stdout => output.stdout.extend_from_slice(&line),
+                            OutputStream::Stderr => output.stderr.extend_from_slice(&line),
+                        }
+                    }
+                    // Everything read is handled and the pipes are still
+                    // open well after the tool exited: whatever holds them
+                    // is a process it left behind.
+                    Err(RecvTimeoutError::Timeout)
+                        if exited_at.is_some_and(|at| at.elapsed() >= EXIT_GRACE) =>
+                    {
+                        break
+                    }
+                    Err(RecvTimeoutError::Timeout) => {}
+                    Err(RecvTimeoutError::Disconnected) => pipes_open = false,
+                }
+            } else if exited_at.is_none() {
+                thread::sleep(POLL_INTERVAL);
+            }
+
+            if exited_at.is_none() {
+                if let Some(status) = child.try_wait()? {
+                    output.status = status.code();
+                    exited_at = Some(Instant::now());
+                }
+            }
+            if exited_at.is_some() && !pipes_open {
+                break;
+            }
+        }
+        // Readers of pipes a leftover process still holds are left to finish
+        // on their own, once it closes them or the next line finds nobody
+        // listening.
+        if !pipes_open {
+            for reader in readers {
+                let _ = reader.join();
+            }
+        }
+        Ok(output)
+    }
+}
+
+/// Kills `child` and everything it started. Readers of its pipes are left
+/// to finish on their own, since a surviving grandchild could keep the
+/// pipes open.
+fn kill_tree(child: &mut Child) {
+    let pid = child.id().to_string();
+    #[cfg(unix)]
+    let _ = Command::new("kill")
+        .args(["-KILL", "--", &format!("-{pid}")])
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status();
+    #[cfg(windows)]
+    let _ = Command::new("taskkill")
+        .args(["/T", "/F", "/PID", &pid])
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status();
+    let _ = child.kill();
+    let _ = child.wait();
+}
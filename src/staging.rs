@@ -0,0 +1,264 @@
+use crate::WasmRustResult;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::BTreeSet;
+use std::ffi::{OsStr, OsString};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+const STAGING_MARKER: &str = ".wasmrust-staging-";
+const PREVIOUS_MARKER: &str = ".wasmrust-previous-";
+
+/// A build's private copy of `output_dir`. Tools write into it, and it only
+/// replaces `output_dir` once the whole build succeeded, so a failed or
+/// cancelled build leaves the previous outputs untouched. Dropping it
+/// without [`commit`](Self::commit) throws it away.
+pub(crate) struct StagedOutput {
+    /// `output_dir` as configured, which result paths are reported under
+    output_dir: String,
+    target: PathBuf,
+    staging: PathBuf,
+    prune: bool,
+    committed: bool,
+}
+
+impl StagedOutput {
+    /// Creates an empty staging directory next to `output_dir`, so the swap
+    /// is a rename within one filesystem, after clearing up what a killed
+    /// build left there. The caller must hold the output directory's build
+    /// lock.
+    ///
+    /// An `output_dir` holding the project itself cannot be swapped out, so
+    /// such builds write in place.
+    pub fn prepare(output_dir: &str, project_path: &Path, prune: bool) -> WasmRustResult<Self> {
+        let target = Path::new(output_dir).canonicalize()?;
+        let holds_project = project_path
+            .canonicalize()
+            .is_ok_and(|project| project.starts_with(&target));
+        let (Some(parent), Some(name), false) =
+            (target.parent(), target.file_name(), holds_project)
+        else {
+            return Ok(Self {
+                output_dir: output_dir.to_string(),
+                staging: target.clone(),
+                target,
+                prune,
+                committed: false,
+            });
+        };
+
+        let name = name.to_string_lossy();
+        recover(&target, parent, &name)?;
+        let staging = parent.join(format!(".{name}{STAGING_MARKER}{}", std::process::id()));
+        fs::create_dir(&staging)?;
+        Ok(Self {
+            output_dir: output_dir.to_string(),
+            target,
+            staging,
+            prune,
+            committed: false,
+        })
+    }
+
+    /// Where the build writes its outputs.
+    pub fn path(&self) -> &Path {
+        &self.staging
+    }
+
+    /// `value` with every path under the staging directory moved to
+    /// `output_dir`, i.e. where it will be after the swap.
+    pub fn relocate<T: Serialize + DeserializeOwned>(&self, value: &T) -> WasmRustResult<T> {
+        let mut json = serde_json::to_value(value)?;
+        if self.staging != self.target {
+            replace_prefix(
+                &mut json,
+                &self.staging.to_string_lossy(),
+                self.output_dir.trim_end_matches(['/', '\\']),
+            );
+        }
+        Ok(serde_json::from_value(json)?)
+    }
+
+    /// Replaces `output_dir` with the staged outputs. Without `prune`, files
+    /// of the current outputs the build did not write are first hard-linked
+    /// (or copied, where links are not supported) into the staged tree,
+    /// except `.gz`/`.br` variants of files it did write.
+    ///
+    /// On Linux the two directories are exchanged in one `renameat2` call,
+    /// so `output_dir` always exists and readers never see a mix of old and
+    /// new files. Elsewhere, or on filesystems without `RENAME_EXCHANGE`,
+    /// the old directory is renamed aside and the staged one into place,
+    /// leaving `output_dir` missing for a moment; the old one is restored if
+    /// the second rename fails, and by the next build if the process dies
+    /// in between.
+    pub fn commit(mut self) -> WasmRustResult<()> {
+        if self.staging == self.target {
+            return Ok(());
+        }
+        if !self.prune {
+            link_missing(&self.target, &self.staging)?;
+        }
+
+        match exchange(&self.staging, &self.target) {
+            Ok(()) => {
+                self.committed = true;
+                // The staging path now holds the previous outputs.
+                let _ = fs::remove_dir_all(&self.staging);
+                return Ok(());
+            }
+            Err(err)
+                if !matches!(
+                    err.kind(),
+                    io::ErrorKind::Unsupported | io::ErrorKind::InvalidInput
+                ) =>
+            {
+                return Err(err.into());
+            }
+            Err(_) => {}
+        }
+
+        let previous = self.staging.with_file_name(
+            self.staging
+                .file_name()
+                .unwrap_or_default()
+                .to_string_lossy()
+                .replace(STAGING_MARKER, PREVIOUS_MARKER),
+        );
+        if previous.exists() {
+            fs::remove_dir_all(&previous)?;
+        }
+        fs::rename(&self.target, &previous)?;
+        if let Err(err) = fs::rename(&self.staging, &self.target) {
+            let _ = fs::rename(&previous, &self.target);
+            return Err(err.into());
+        }
+        self.committed = true;
+        let _ = fs::remove_dir_all(&previous);
+        Ok(())
+    }
+}
+
+impl Drop for StagedOutput {
+    fn drop(&mut self) {
+        if !self.committed && self.staging != self.target {
+            let _ = fs::remove_dir_all(&self.staging);
+        }
+    }
+}
+
+/// Clears out staging directories of builds into `target` that were killed
+/// before they committed. A directory renamed aside by a commit that died
+/// before the staged one took its place is moved back first; `target` is
+/// then the empty directory the build just created.
+fn recover(target: &Path, parent: &Path, name: &str) -> WasmRustResult<()> {
+    let staging_prefix = format!(".{name}{STAGING_MARKER}");
+    let previous_prefix = format!(".{name}{PREVIOUS_MARKER}");
+    for entry in fs::read_dir(parent)? {
+        let entry = entry?;
+        let file_name = entry.file_name().to_string_lossy().to_string();
+        let path = entry.path();
+        if file_name.starts_with(&previous_prefix) && is_empty_dir(target) {
+            fs::remove_dir(target)?;
+            fs::rename(&path, target)?;
+        } else if file_name.starts_with(&staging_prefix) || file_name.starts_with(&previous_prefix)
+        {
+            fs::remove_dir_all(&path)?;
+        }
+    }
+    Ok(())
+}
+
+fn is_empty_dir(path: &Path) -> bool {
+    fs::read_dir(path).is_ok_and(|mut entries| entries.next().is_none())
+}
+
+/// Adds every file under `from` that has no counterpart under `to`.
+fn link_missing(from: &Path, to: &Path) -> WasmRustResult<()> {
+    // What the build wrote here, before anything is carried over.
+    let produced = fs::read_dir(to)?
+        .map(|entry| entry.map(|entry| entry.file_name()))
+        .collect::<io::Result<BTreeSet<_>>>()?;
+    for entry in fs::read_dir(from)? {
+        let entry = entry?;
+        let name = entry.file_name();
+        let to_path = to.join(&name);
+        if entry.file_type()?.is_dir() {
+            if !produced.contains(&name) {
+                fs::create_dir(&to_path)?;
+            }
+            if to_path.is_dir() {
+                link_missing(&entry.path(), &to_path)?;
+            }
+        } else if !produced.contains(&name)
+            && !is_stale_variant(&name, &produced)
+            && fs::hard_link(entry.path(), &to_path).is_err()
+        {
+            fs::copy(entry.path(), &to_path)?;
+        }
+    }
+    Ok(())
+}
+
+/// A precompressed variant of a file the build rewrote, which no longer
+/// matches it.
+fn is_stale_variant(name: &OsStr, produced: &BTreeSet<OsString>) -> bool {
+    let name = Path::new(name);
+    name.extension()
+        .is_some_and(|ext| ext == "gz" || ext == "br")
+        && name.file_stem().is_some_and(|base| produced.contains(base))
+}
+
+/// Swaps the directories at `a` and `b` atomically.
+#[cfg(target_os = "linux")]
+fn exchange(a: &Path, b: &Path) -> io::Result<()> {
+    use std::ffi::CString;
+    use std::os::unix::ffi::OsStrExt;
+
+    let a = CString::new(a.as_os_str().as_bytes())?;
+    let b = CString::new(b.as_os_str().as_bytes())?;
+    // Called through syscall(2) since older glibc has no renameat2 wrapper.
+    // SAFETY: both paths are valid NUL-terminated strings for the call.
+    let status = unsafe {
+        libc::syscall(
+            libc::SYS_renameat2,
+            libc::AT_FDCWD,
+            a.as_ptr(),
+            libc::AT_FDCWD,
+            b.as_ptr(),
+            libc::RENAME_EXCHANGE,
+        )
+    };
+    if status == 0 {
+        Ok(())
+    } else {
+        Err(io::Error::last_os_error())
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+fn exchange(_a: &Path, _b: &Path) -> io::Result<()> {
+    Err(io::ErrorKind::Unsupported.into())
+}
+
+fn replace_prefix(value: &mut Value, from: &str, to: &str) {
+    match value {
+        Value::String(text) => {
+            if let Some(rest) = text.strip_prefix(from) {
+                *text = format!("{to}{rest}");
+            }
+        }
+        Value::Array(items) => {
+            for item in items {
+                replace_prefix(item, from, to);
+            }
+        }
+        Value::Object(fields) => {
+            for field in fields.values_mut() {
+                replace_prefix(field, from, to);
+            }
+        }
+        _ => {}
+    }
+}
@@ -0,0 +1,92 @@
+use crate::plan::PlannedCommand;
+use crate::runner::{CommandOutput, CommandRunner};
+use std::io;
+use std::sync::{Mutex, MutexGuard};
+
+#[derive(Debug, Clone)]
+enum Response {
+    Output(CommandOutput),
+    Missing,
+}
+
+/// Runs nothing: records every command and answers with scripted output,
+/// so tests can check exact invocations and simulate failing or missing
+/// tools. Only built for the crate's own tests and with the `testing`
+/// feature. Responses are keyed by a command line prefix (`"rustup"`,
+/// `"rustup target list"`) and the longest matching prefix wins; commands
+/// without a scripted response succeed with no output.
+#[derive(Debug, Default)]
+pub struct RecordingRunner {
+    responses: Mutex<Vec<(Vec<String>, Response)>>,
+    invocations: Mutex<Vec<PlannedCommand>>,
+}
+
+impl RecordingRunner {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Answers later commands starting with `command_line` with `output`.
+    pub fn respond(&self, command_line: &str, output: CommandOutput) {
+        self.script(command_line, Response::Output(output));
+    }
+
+    /// Makes `program` behave as if it were not installed.
+    pub fn missing(&self, program: &str) {
+        self.script(program, Response::Missing);
+    }
+
+    /// Every command run so far, in order.
+    pub fn invocations(&self) -> Vec<PlannedCommand> {
+        lock(&self.invocations).clone()
+    }
+
+    /// The commands run so far for `program`.
+    pub fn invocations_of(&self, program: &str) -> Vec<PlannedCommand> {
+        lock(&self.invocations)
+            .iter()
+            .filter(|command| command.program == program)
+            .cloned()
+            .collect()
+    }
+
+    pub fn clear(&self) {
+        lock(&self.invocations).clear();
+    }
+
+    fn script(&self, command_line: &str, response: Response) {
+        let prefix = command_line.split_whitespace().map(String::from).collect();
+        lock(&self.responses).push((prefix, response));
+    }
+
+    fn response(&self, command: &PlannedCommand) -> Option<Response> {
+        lock(&self.responses)
+            .iter()
+            .filter(|(prefix, _)| {
+                prefix.first() == Some(&command.program) && command.args.starts_with(&prefix[1..])
+            })
+            // The last of equally long prefixes, i.e. the latest one scripted.
+            .max_by_key(|(prefix, _)| prefix.len())
+            .map(|(_, response)| response.clone())
+    }
+}
+
+impl CommandRunner for RecordingRunner {
+    fn run(&self, command: &PlannedCommand) -> io::Result<CommandOutput> {
+        lock(&self.invocations).push(command.clone());
+        match self.response(command) {
+            Some(Response::Output(output)) => Ok(output),
+            Some(Response::Missing) => Err(io::Error::new(
+                io::ErrorKind::NotFound,
+                format!("{} is not installed", command.program),
+            )),
+            None => Ok(CommandOutput::ok("")),
+        }
+    }
+}
+
+fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    mutex
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+}
@@ -0,0 +1,433 @@
+use crate::plan::PlannedCommand;
+use crate::runner::{CommandOutput, CommandRunner};
+use crate::{WasmRustError, WasmRustResult};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+pub const WASM_TARGET: &str = "wasm32-unknown-unknown";
+
+/// Toolchain files rustup honors, in the order it checks them.
+pub const TOOLCHAIN_FILES: &[&str] = &["rust-toolchain.toml", "rust-toolchain"];
+
+/// The `[toolchain]` table of a `rust-toolchain.toml`.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct ToolchainFile {
+    pub channel: Option<String>,
+    #[serde(default)]
+    pub components: Vec<String>,
+    #[serde(default)]
+    pub targets: Vec<String>,
+}
+
+#[derive(Deserialize)]
+struct ToolchainFileToml {
+    #[serde(default)]
+    toolchain: ToolchainFile,
+}
+
+impl ToolchainFile {
+    /// Parses either the TOML form or the legacy single-line channel form.
+    pub fn parse(content: &str) -> WasmRustResult<Self> {
+        let trimmed = content.trim();
+        if !trimmed.is_empty() && !trimmed.contains('[') && !trimmed.contains('=') {
+            return Ok(Self {
+                channel: Some(trimmed.to_string()),
+                ..Default::default()
+            });
+        }
+        Ok(toml::from_str::<ToolchainFileToml>(content)?.toolchain)
+    }
+
+    /// Looks for a toolchain file in the project and its parents, like rustup.
+    pub fn find(project_path: &Path) -> Option<PathBuf> {
+        let start = project_path
+            .canonicalize()
+            .unwrap_or_else(|_| project_path.to_path_buf());
+        start.ancestors().find_map(|dir| {
+            TOOLCHAIN_FILES
+                .iter()
+                .map(|name| dir.join(name))
+                .find(|path| path.is_file())
+        })
+    }
+
+    pub fn load(project_path: &Path) -> WasmRustResult<Option<(PathBuf, Self)>> {
+        match Self::find(project_path) {
+            Some(path) => {
+                let file = Self::parse(&fs::read_to_string(&path)?)?;
+                Ok(Some((path, file)))
+            }
+            None => Ok(None),
+        }
+    }
+}
+
+/// Where the effective toolchain came from, highest precedence first.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub enum ToolchainSource {
+    /// `toolchain` option on `CompileConfig` or wasmrun.toml
+    Requested,
+    /// `RUSTUP_TOOLCHAIN`
+    Environment,
+    /// `rustup override set` for the project directory
+    DirectoryOverride,
+    ToolchainFile,
+    RustupDefault,
+    /// No rustup; whatever `cargo` and `rustc` are on PATH
+    #[default]
+    System,
+}
+
+impl fmt::Display for ToolchainSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let description = match self {
+            ToolchainSource::Requested => "requested",
+            ToolchainSource::Environment => "RUSTUP_TOOLCHAIN",
+            ToolchainSource::DirectoryOverride => "directory override",
+            ToolchainSource::ToolchainFile => "toolchain file",
+            ToolchainSource::RustupDefault => "rustup default",
+            ToolchainSource::System => "system",
+        };
+        f.write_str(description)
+    }
+}
+
+/// The toolchain a project builds with.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct Toolchain {
+    /// rustup toolchain name, `None` when rustup does not manage the toolchain
+    pub name: Option<String>,
+    pub source: ToolchainSource,
+    /// Toolchain file pinning the project, if any
+    pub file: Option<String>,
+    /// Targets the toolchain file asks for
+    pub targets: Vec<String>,
+    /// Components the toolchain file asks for
+    pub components: Vec<String>,
+}
+
+impl Toolchain {
+    /// Resolves the toolchain cargo would use in `project_path`. A
+    /// `requested` toolchain (`nightly` or `+nightly`) wins; otherwise rustup
+    /// is asked, so overrides and toolchain files apply as they would for
+    /// cargo itself.
+    pub fn resolve(
+        runner: &dyn CommandRunner,
+        project_path: &Path,
+        requested: Option<&str>,
+    ) -> WasmRustResult<Self> {
+        let file = ToolchainFile::load(project_path)?;
+        let (file_path, file) = match file {
+            Some((path, file)) => (Some(path.to_string_lossy().to_string()), Some(file)),
+            None => (None, None),
+        };
+
+        let (name, source) = if let Some(requested) = requested {
+            (
+                Some(requested.trim_start_matches('+').to_string()),
+                ToolchainSource::Requested,
+            )
+        } else if let Some((name, source)) = active_toolchain(runner, project_path) {
+            (Some(name), source)
+        } else if let Ok(name) = std::env::var("RUSTUP_TOOLCHAIN") {
+            (Some(name), ToolchainSource::Environment)
+        } else if let Some(channel) = file.as_ref().and_then(|f| f.channel.clone()) {
+            (Some(channel), ToolchainSource::ToolchainFile)
+        } else {
+            (None, ToolchainSource::System)
+        };
+
+        let file = file.unwrap_or_default();
+        Ok(Self {
+            name,
+            source,
+            file: file_path,
+            targets: file.targets,
+            components: file.components,
+        })
+    }
+
+    /// `+name` for cargo when the toolchain was requested explicitly; other
+    /// sources are already picked up by cargo's rustup proxy.
+    pub fn cargo_arg(&self) -> Option<String> {
+        match (&self.source, &self.name) {
+            (ToolchainSource::Requested, Some(name)) => Some(format!("+{name}")),
+            _ => None,
+        }
+    }
+
+    /// Environment for tools that run cargo themselves (wasm-pack, trunk).
+    pub fn env(&self) -> Vec<(String, String)> {
+        match (&self.source, &self.name) {
+            (ToolchainSource::Requested, Some(name)) => {
+                vec![("RUSTUP_TOOLCHAIN".to_string(), name.clone())]
+            }
+            _ => Vec::new(),
+        }
+    }
+
+    /// A requested toolchain can only be applied through rustup.
+    pub fn validate(&self, runner: &dyn CommandRunner) -> WasmRustResult<()> {
+        if self.source == ToolchainSource::Requested && !rustup_available(runner) {
+            return Err(WasmRustError::InvalidConfiguration(format!(
+                "toolchain '{}' was requested but rustup is not installed",
+                self.name.as_deref().unwrap_or_default()
+            )));
+        }
+        Ok(())
+    }
+
+    pub fn is_target_installed(&self, runner: &dyn CommandRunner, target: &str) -> bool {
+        self.detect_target(runner, target).installed
+    }
+
+    /// Asks rustup when it manages the toolchain. Without rustup (distro
+    /// packages, Nix) the sysroot is inspected, and as a last resort a
+    /// `#![no_std]` crate is compiled for the target.
+    pub fn detect_target(&self, runner: &dyn CommandRunner, target: &str) -> TargetStatus {
+        let status = |installed, method| TargetStatus {
+            target: target.to_string(),
+            installed,
+            method,
+        };
+
+        if let Some(installed) = self.rustup_list(runner, &["target", "list", "--installed"]) {
+            return status(
+                installed.iter().any(|t| t == target),
+                TargetDetection::Rustup,
+            );
+        }
+        if let Some(sysroot) = self.rustc_sysroot(runner) {
+            if target_in_sysroot(&sysroot, target) {
+                return status(true, TargetDetection::Sysroot);
+            }
+        }
+        status(self.probe_compile(runner, target), TargetDetection::Probe)
+    }
+
+    pub fn is_component_installed(&self, runner: &dyn CommandRunner, component: &str) -> bool {
+        let prefix = format!("{component}-");
+        match self.rustup_list(runner, &["component", "list", "--installed"]) {
+            Some(installed) => installed
+                .iter()
+                .any(|c| c == component || c.starts_with(&prefix)),
+            // rust-src is the one component with a fixed place in the sysroot.
+            None => {
+                component == "rust-src"
+                    && self
+                        .rustc_sysroot(runner)
+                        .is_some_and(|sysroot| sysroot.join("lib/rustlib/src/rust").is_dir())
+            }
+        }
+    }
+
+    pub fn ensure_target(&self, runner: &dyn CommandRunner, target: &str) -> WasmRustResult<()> {
+        let status = self.detect_target(runner, target);
+        if status.installed {
+            return Ok(());
+        }
+        if status.method != TargetDetection::Rustup {
+            return Err(WasmRustError::ToolNotFound(format!(
+                "{target} is not available for {self} (checked via {}) and rustup is not \
+                 installed to add it; install the target's standard library with your \
+                 package manager",
+                status.method
+            )));
+        }
+        self.rustup_add(runner, &["target", "add", target])
+    }
+
+    pub fn ensure_component(
+        &self,
+        runner: &dyn CommandRunner,
+        component: &str,
+    ) -> WasmRustResult<()> {
+        if self.is_component_installed(runner, component) {
+            return Ok(());
+        }
+        self.rustup_add(runner, &["component", "add", component])
+    }
+
+    pub(crate) fn rustup_args(&self, args: &[&str]) -> Vec<String> {
+        let mut args: Vec<String> = args.iter().map(|a| a.to_string()).collect();
+        if let Some(name) = &self.name {
+            args.extend(["--toolchain".to_string(), name.clone()]);
+        }
+        args
+    }
+
+    /// Pins the `rustc` proxy to this toolchain, which it would otherwise
+    /// resolve from wherever wasmrust runs rather than from the project.
+    fn rustc_env(&self) -> Vec<(String, String)> {
+        self.name
+            .iter()
+            .map(|name| ("RUSTUP_TOOLCHAIN".to_string(), name.clone()))
+            .collect()
+    }
+
+    fn rustc_sysroot(&self, runner: &dyn CommandRunner) -> Option<PathBuf> {
+        let args = vec!["--print".to_string(), "sysroot".to_string()];
+        let output = run_successful(runner, "rustc", args, None, &self.rustc_env())?;
+        let sysroot = String::from_utf8_lossy(&output.stdout).trim().to_string();
+        (!sysroot.is_empty()).then(|| PathBuf::from(sysroot))
+    }
+
+    fn probe_compile(&self, runner: &dyn CommandRunner, target: &str) -> bool {
+        let Ok(dir) = tempfile::Builder::new().prefix("wasmrust-probe-").tempdir() else {
+            return false;
+        };
+        let source = dir.path().join("probe.rs");
+        if fs::write(&source, "#![no_std]\n").is_err() {
+            return false;
+        }
+
+        let mut args: Vec<String> = [
+            "--target",
+            target,
+            "--crate-type",
+            "lib",
+            "--emit",
+            "metadata",
+            "--out-dir",
+        ]
+        .map(String::from)
+        .to_vec();
+        args.push(dir.path().to_string_lossy().to_string());
+        args.push(source.to_string_lossy().to_string());
+        run_successful(runner, "rustc", args, None, &self.rustc_env()).is_some()
+    }
+
+    fn rustup_list(&self, runner: &dyn CommandRunner, args: &[&str]) -> Option<Vec<String>> {
+        let output = run_successful(runner, "rustup", self.rustup_args(args), None, &[])?;
+        Some(
+            String::from_utf8_lossy(&output.stdout)
+                .lines()
+                .map(|line| line.trim().to_string())
+                .collect(),
+        )
+    }
+
+    fn rustup_add(&self, runner: &dyn CommandRunner, args: &[&str]) -> WasmRustResult<()> {
+        let args = self.rustup_args(args);
+        let command = PlannedCommand {
+            program: "rustup".to_string(),
+            args: args.clone(),
+            ..Default::default()
+        };
+        let output = runner.run(&command).map_err(|_| {
+            WasmRustError::ToolNotFound(format!(
+                "rustup is required to run `rustup {}`",
+                args.join(" ")
+            ))
+        })?;
+
+        if !output.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            return Err(WasmRustError::CompilationFailed(format!(
+                "`rustup {}` failed: {stderr}",
+                args.join(" ")
+            )));
+        }
+        Ok(())
+    }
+}
+
+/// How a target's availability was determined.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub enum TargetDetection {
+    #[default]
+    Rustup,
+    /// `lib/rustlib/<target>` under `rustc --print sysroot`
+    Sysroot,
+    /// Compiling an empty `#![no_std]` crate for the target
+    Probe,
+}
+
+impl fmt::Display for TargetDetection {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let description = match self {
+            TargetDetection::Rustup => "rustup",
+            TargetDetection::Sysroot => "rustc sysroot",
+            TargetDetection::Probe => "probe compile",
+        };
+        f.write_str(description)
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct TargetStatus {
+    pub target: String,
+    pub installed: bool,
+    pub method: TargetDetection,
+}
+
+/// Whether a sysroot ships the standard library for `target`.
+pub fn target_in_sysroot(sysroot: &Path, target: &str) -> bool {
+    sysroot
+        .join("lib/rustlib")
+        .join(target)
+        .join("lib")
+        .is_dir()
+}
+
+impl fmt::Display for Toolchain {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.name {
+            Some(name) => write!(f, "{name} ({})", self.source),
+            None => write!(f, "{}", self.source),
+        }
+    }
+}
+
+/// Parses `rustup show active-toolchain`, e.g.
+/// `nightly-x86_64-unknown-linux-gnu (overridden by '/app/rust-toolchain.toml')`.
+pub fn parse_active_toolchain(output: &str) -> Option<(String, ToolchainSource)> {
+    let line = output.lines().find(|line| !line.trim().is_empty())?.trim();
+    let (name, reason) = match line.split_once(' ') {
+        Some((name, reason)) => (name, reason),
+        None => (line, ""),
+    };
+
+    let source = if reason.contains("RUSTUP_TOOLCHAIN") || reason.contains("environment") {
+        ToolchainSource::Environment
+    } else if reason.contains("rust-toolchain") {
+        ToolchainSource::ToolchainFile
+    } else if reason.contains("override") {
+        ToolchainSource::DirectoryOverride
+    } else {
+        ToolchainSource::RustupDefault
+    };
+    Some((name.to_string(), source))
+}
+
+fn active_toolchain(
+    runner: &dyn CommandRunner,
+    project_path: &Path,
+) -> Option<(String, ToolchainSource)> {
+    let args = vec!["show".to_string(), "active-toolchain".to_string()];
+    let output = run_successful(runner, "rustup", args, Some(project_path), &[])?;
+    parse_active_toolchain(&String::from_utf8_lossy(&output.stdout))
+}
+
+fn rustup_available(runner: &dyn CommandRunner) -> bool {
+    run_successful(runner, "rustup", vec!["--version".to_string()], None, &[]).is_some()
+}
+
+/// Output of `program`, or `None` when it could not run or failed.
+fn run_successful(
+    runner: &dyn CommandRunner,
+    program: &str,
+    args: Vec<String>,
+    cwd: Option<&Path>,
+    env: &[(String, String)],
+) -> Option<CommandOutput> {
+    let command = PlannedCommand {
+        program: program.to_string(),
+        args,
+        cwd: cwd.map(Path::to_path_buf),
+        env: env.to_vec(),
+    };
+    runner.run(&command).ok().filter(CommandOutput::success)
+}
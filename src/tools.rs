@@ -0,0 +1,261 @@
+use crate::plan::PlannedCommand;
+use crate::runner::CommandRunner;
+use crate::{BuildStrategy, WasmRustResult};
+use semver::Version;
+use serde::{Deserialize, Serialize};
+use std::env;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// External tools whose versions are reported by `inspect` and `check-deps`.
+pub const PROBED_TOOLS: &[&str] = &[
+    "cargo",
+    "rustc",
+    "wasm-pack",
+    "trunk",
+    "wasm-opt",
+    "wasm-bindgen",
+];
+
+/// The oldest release of a tool a build strategy works with.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ToolRequirement {
+    pub tool: &'static str,
+    pub minimum: &'static str,
+    pub strategies: &'static [BuildStrategy],
+}
+
+const ALL_STRATEGIES: &[BuildStrategy] = &[
+    BuildStrategy::Cargo,
+    BuildStrategy::WasmPack,
+    BuildStrategy::Trunk,
+];
+
+/// cargo/rustc 1.70 for sparse registries and `--profile`, wasm-pack 0.12
+/// for `--profiling` and cargo args after `--`, trunk 0.18 for
+/// `--cargo-profile` and the positional target.
+pub const TOOL_REQUIREMENTS: &[ToolRequirement] = &[
+    ToolRequirement {
+        tool: "cargo",
+        minimum: "1.70.0",
+        strategies: ALL_STRATEGIES,
+    },
+    ToolRequirement {
+        tool: "rustc",
+        minimum: "1.70.0",
+        strategies: ALL_STRATEGIES,
+    },
+    ToolRequirement {
+        tool: "wasm-pack",
+        minimum: "0.12.0",
+        strategies: &[BuildStrategy::WasmPack],
+    },
+    ToolRequirement {
+        tool: "trunk",
+        minimum: "0.18.0",
+        strategies: &[BuildStrategy::Trunk],
+    },
+];
+
+impl ToolRequirement {
+    pub fn minimum_version(&self) -> Version {
+        Version::parse(self.minimum).expect("tool requirements are valid semver")
+    }
+}
+
+/// The requirement for `tool`, whichever strategy declares it.
+pub fn tool_requirement(tool: &str) -> Option<&'static ToolRequirement> {
+    TOOL_REQUIREMENTS.iter().find(|req| req.tool == tool)
+}
+
+/// The minimum version of `tool` when building with `strategy`.
+pub fn minimum_version(tool: &str, strategy: &BuildStrategy) -> Option<Version> {
+    tool_requirement(tool)
+        .filter(|req| req.strategies.contains(strategy))
+        .map(ToolRequirement::minimum_version)
+}
+
+/// What was found for one tool: where it lives and which version it reports.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct ToolInfo {
+    pub name: String,
+    #[serde(default)]
+    pub path: Option<PathBuf>,
+    #[serde(default)]
+    pub version: Option<Version>,
+    /// First line of `--version`, kept for versions that do not parse
+    #[serde(default)]
+    pub version_output: Option<String>,
+}
+
+impl ToolInfo {
+    /// Runs `name --version` through `runner`, with `envs` so rustup
+    /// proxies report the toolchain a build will actually use. A tool the
+    /// runner cannot find is reported as missing.
+    pub fn probe(runner: &dyn CommandRunner, name: &str, envs: &[(String, String)]) -> Self {
+        let command = PlannedCommand {
+            program: name.to_string(),
+            args: vec!["--version".to_string()],
+            env: envs.to_vec(),
+            ..Default::default()
+        };
+        let output = match runner.run(&command) {
+            Ok(output) => output,
+            Err(err) => {
+                return Self {
+                    name: name.to_string(),
+                    // Present but not runnable, e.g. without execute permission.
+                    path: (err.kind() != io::ErrorKind::NotFound)
+                        .then(|| find_in_path(name))
+                        .flatten(),
+                    ..Default::default()
+                };
+            }
+        };
+        // Found through the OS lookup rather than a PATH entry.
+        let path = find_in_path(name).or_else(|| Some(PathBuf::from(name)));
+        if !output.success() {
+            return Self {
+                name: name.to_string(),
+                path,
+                ..Default::default()
+            };
+        }
+
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        let text = if stdout.trim().is_empty() {
+            String::from_utf8_lossy(&output.stderr).to_string()
+        } else {
+            stdout.to_string()
+        };
+        let version_output = text
+            .lines()
+            .map(str::trim)
+            .find(|line| !line.is_empty())
+            .map(str::to_string);
+
+        Self {
+            name: name.to_string(),
+            path,
+            version: version_output.as_deref().and_then(parse_tool_version),
+            version_output,
+        }
+    }
+
+    pub fn is_available(&self) -> bool {
+        self.path.is_some()
+    }
+
+    /// Whether the reported version predates `minimum`. Pre-release
+    /// toolchains count as their release (`1.80.0-nightly` is `1.80.0`),
+    /// and an unparseable version is never flagged.
+    pub fn is_older_than(&self, minimum: &Version) -> bool {
+        self.version.as_ref().is_some_and(|version| {
+            Version::new(version.major, version.minor, version.patch) < *minimum
+        })
+    }
+}
+
+impl fmt::Display for ToolInfo {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match (&self.version, &self.version_output) {
+            (Some(version), _) => write!(f, "{} {version}", self.name),
+            (None, Some(output)) => write!(f, "{} ({output})", self.name),
+            (None, None) => write!(f, "{}", self.name),
+        }
+    }
+}
+
+/// Pulls the version out of a `--version` line: `cargo 1.79.0 (ffa9cf99a
+/// 2024-06-03)`, `wasm-bindgen 0.2.92`, `trunk v0.20.3`. Short forms such
+/// as wasm-opt's `wasm-opt version 116 (version_116)` are padded to
+/// `116.0.0`.
+pub fn parse_tool_version(output: &str) -> Option<Version> {
+    output.split_whitespace().find_map(|token| {
+        let token = token
+            .trim_matches(|c: char| matches!(c, '(' | ')' | ',' | ';'))
+            .trim_start_matches('v');
+        if let Ok(version) = Version::parse(token) {
+            return Some(version);
+        }
+
+        let parts: Vec<&str> = token.split('.').collect();
+        if parts.len() > 2
+            || parts
+                .iter()
+                .any(|p| p.is_empty() || !p.bytes().all(|b| b.is_ascii_digit()))
+        {
+            return None;
+        }
+        let number = |index: usize| parts.get(index).map_or(Some(0), |p| p.parse().ok());
+        Some(Version::new(number(0)?, number(1)?, 0))
+    })
+}
+
+/// The lock file a project builds with: its own, or its workspace's.
+pub fn find_cargo_lock(project_path: &Path) -> Option<PathBuf> {
+    let project_path = project_path
+        .canonicalize()
+        .unwrap_or_else(|_| project_path.to_path_buf());
+    project_path
+        .ancestors()
+        .map(|dir| dir.join("Cargo.lock"))
+        .find(|path| path.is_file())
+}
+
+#[derive(Deserialize)]
+struct CargoLock {
+    #[serde(default)]
+    package: Vec<LockedPackage>,
+}
+
+#[derive(Deserialize)]
+struct LockedPackage {
+    name: String,
+    version: String,
+}
+
+/// The version of `package` pinned in the project's Cargo.lock, or `None`
+/// when there is no lock file or it does not contain the package. With
+/// several locked versions the newest wins.
+pub fn locked_version(project_path: &Path, package: &str) -> WasmRustResult<Option<Version>> {
+    let Some(lock_path) = find_cargo_lock(project_path) else {
+        return Ok(None);
+    };
+    let lock: CargoLock = toml::from_str(&fs::read_to_string(lock_path)?)?;
+    Ok(lock
+        .package
+        .iter()
+        .filter(|locked| locked.name == package)
+        .filter_map(|locked| Version::parse(&locked.version).ok())
+        .max())
+}
+
+/// Number of packages in the project's Cargo.lock, the most crates a build
+/// can compile.
+pub(crate) fn locked_package_count(project_path: &Path) -> Option<usize> {
+    let lock = fs::read_to_string(find_cargo_lock(project_path)?).ok()?;
+    let lock: CargoLock = toml::from_str(&lock).ok()?;
+    Some(lock.package.len())
+}
+
+/// The command that installs the `wasm-bindgen` CLI matching `version`.
+pub fn wasm_bindgen_install_command(version: &Version) -> String {
+    format!("cargo install -f wasm-bindgen-cli --version {version}")
+}
+
+fn find_in_path(name: &str) -> Option<PathBuf> {
+    let paths = env::var_os("PATH")?;
+    env::split_paths(&paths).find_map(|dir| executable_in(&dir, name))
+}
+
+fn executable_in(dir: &Path, name: &str) -> Option<PathBuf> {
+    let candidates = if cfg!(target_os = "windows") {
+        vec![dir.join(format!("{name}.exe")), dir.join(name)]
+    } else {
+        vec![dir.join(name)]
+    };
+    candidates.into_iter().find(|path| path.is_file())
+}
@@ -0,0 +1,121 @@
+use crate::WasmRustResult;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Config file names trunk looks for, in order.
+pub const TRUNK_CONFIG_FILES: &[&str] = &["Trunk.toml", "trunk.toml"];
+
+pub const DEFAULT_TRUNK_TARGET: &str = "index.html";
+pub const DEFAULT_TRUNK_DIST: &str = "dist";
+
+/// The parts of `Trunk.toml` that affect where and how a build is written.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct TrunkConfig {
+    #[serde(default)]
+    pub build: TrunkBuildConfig,
+    #[serde(default)]
+    pub hooks: Vec<TrunkHook>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct TrunkBuildConfig {
+    /// HTML entry point, relative to the project
+    #[serde(default)]
+    pub target: Option<String>,
+    #[serde(default)]
+    pub dist: Option<String>,
+    #[serde(default)]
+    pub public_url: Option<String>,
+}
+
+/// A `[[hooks]]` entry; trunk runs these itself around its build stages.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct TrunkHook {
+    /// `pre_build`, `build` or `post_build`
+    pub stage: String,
+    pub command: String,
+    #[serde(default)]
+    pub command_arguments: Vec<String>,
+}
+
+impl fmt::Display for TrunkHook {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "[{}] {}", self.stage, self.command)?;
+        for argument in &self.command_arguments {
+            write!(f, " {argument}")?;
+        }
+        Ok(())
+    }
+}
+
+impl TrunkConfig {
+    pub fn find(project_path: &Path) -> Option<PathBuf> {
+        TRUNK_CONFIG_FILES
+            .iter()
+            .map(|name| project_path.join(name))
+            .find(|path| path.is_file())
+    }
+
+    /// Reads the project's trunk config, or `None` when it has none.
+    pub fn load(project_path: &Path) -> WasmRustResult<Option<Self>> {
+        match Self::find(project_path) {
+            Some(path) => Ok(Some(toml::from_str(&fs::read_to_string(path)?)?)),
+            None => Ok(None),
+        }
+    }
+
+    pub fn target(&self) -> &str {
+        self.build.target.as_deref().unwrap_or(DEFAULT_TRUNK_TARGET)
+    }
+
+    pub fn dist(&self) -> &str {
+        self.build.dist.as_deref().unwrap_or(DEFAULT_TRUNK_DIST)
+    }
+
+    /// Name of the page trunk writes for the configured target.
+    pub fn html_file_name(&self) -> String {
+        Path::new(self.target())
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_else(|| DEFAULT_TRUNK_TARGET.to_string())
+    }
+
+    /// Locates the page a trunk build wrote into `dist`: the target's own
+    /// name, trunk's usual `index.html`, or the only other HTML file.
+    pub fn find_html(&self, dist: &Path) -> Option<PathBuf> {
+        let named = [self.html_file_name(), DEFAULT_TRUNK_TARGET.to_string()]
+            .into_iter()
+            .map(|name| dist.join(name))
+            .find(|path| path.is_file());
+        if named.is_some() {
+            return named;
+        }
+
+        let mut pages: Vec<PathBuf> = fs::read_dir(dist)
+            .ok()?
+            .flatten()
+            .map(|entry| entry.path())
+            .filter(|path| path.extension().is_some_and(|ext| ext == "html"))
+            .collect();
+        pages.sort();
+        (pages.len() == 1).then(|| pages.remove(0))
+    }
+}
+
+/// Trunk empties its dist directory before writing, so it may only target
+/// `output_dir` directly when that cannot take the project with it.
+pub(crate) fn can_write_directly(project_path: &Path, output_dir: &Path) -> bool {
+    match (project_path.canonicalize(), resolve_output_dir(output_dir)) {
+        (Ok(project), Ok(output)) => !project.starts_with(&output),
+        _ => false,
+    }
+}
+
+/// Absolute form of `output_dir`, which a dry run may not have created yet.
+pub(crate) fn resolve_output_dir(output_dir: &Path) -> std::io::Result<PathBuf> {
+    output_dir
+        .canonicalize()
+        .or_else(|_| Ok(std::env::current_dir()?.join(output_dir)))
+}
@@ -0,0 +1,587 @@
+use crate::{WasmRustError, WasmRustResult};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+use std::path::Path;
+
+const WASM_MAGIC: &[u8; 4] = b"\0asm";
+/// Core module binary format version; components use other values.
+const WASM_VERSION: &[u8; 4] = b"\x01\0\0\0";
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord)]
+pub enum ExternalKind {
+    Function,
+    Table,
+    Memory,
+    Global,
+    Tag,
+}
+
+impl fmt::Display for ExternalKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            ExternalKind::Function => "func",
+            ExternalKind::Table => "table",
+            ExternalKind::Memory => "memory",
+            ExternalKind::Global => "global",
+            ExternalKind::Tag => "tag",
+        };
+        f.write_str(name)
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct WasmSection {
+    /// Standard section name, or `custom:<name>` for custom sections
+    pub name: String,
+    pub size: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct WasmFunction {
+    pub index: u32,
+    pub name: String,
+    pub body_size: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct WasmExport {
+    pub name: String,
+    pub kind: ExternalKind,
+    /// Rendered type, e.g. `(i32, i32) -> i32` or `memory 17..`
+    pub signature: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct WasmImport {
+    pub module: String,
+    pub name: String,
+    pub kind: ExternalKind,
+    pub signature: String,
+}
+
+impl WasmImport {
+    pub fn qualified_name(&self) -> String {
+        format!("{}::{}", self.module, self.name)
+    }
+}
+
+/// Structural summary of a WebAssembly binary: what it contains, what it
+/// imports and exports, and which post-MVP features it relies on.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct WasmModule {
+    pub size: u64,
+    pub sections: Vec<WasmSection>,
+    pub functions: Vec<WasmFunction>,
+    pub exports: Vec<WasmExport>,
+    pub imports: Vec<WasmImport>,
+    pub features: BTreeSet<String>,
+}
+
+impl WasmModule {
+    pub fn from_file(path: &Path) -> WasmRustResult<Self> {
+        let bytes = std::fs::read(path)?;
+        Self::parse(&bytes).map_err(|e| match e {
+            WasmRustError::InvalidWasm(reason) => {
+                WasmRustError::InvalidWasm(format!("{}: {reason}", path.display()))
+            }
+            other => other,
+        })
+    }
+
+    pub fn parse(bytes: &[u8]) -> WasmRustResult<Self> {
+        if !is_wasm(bytes) {
+            return match bytes.get(4..8) {
+                Some(version) if bytes.starts_with(WASM_MAGIC) => Err(invalid(format!(
+                    "unsupported binary version {}",
+                    u32::from_le_bytes(version.try_into().unwrap_or_default())
+                ))),
+                _ => Err(invalid("missing \\0asm header")),
+            };
+        }
+
+        let mut reader = Reader::new(&bytes[8..]);
+        let mut module = WasmModule {
+            size: bytes.len() as u64,
+            ..Default::default()
+        };
+
+        let mut types: Vec<String> = Vec::new();
+        let mut function_types: Vec<u32> = Vec::new();
+        let mut imported_functions = 0u32;
+        let mut tables: Vec<String> = Vec::new();
+        let mut memories: Vec<String> = Vec::new();
+        let mut globals: Vec<String> = Vec::new();
+        let mut tags: Vec<String> = Vec::new();
+        let mut body_sizes: Vec<u64> = Vec::new();
+        let mut function_names: BTreeMap<u32, String> = BTreeMap::new();
+        let mut raw_exports: Vec<(String, ExternalKind, u32)> = Vec::new();
+
+        while !reader.is_empty() {
+            let id = reader.u8()?;
+            let size = reader.u32()? as usize;
+            let mut section = Reader::new(reader.bytes(size)?);
+            let header_size = 1 + leb_len(size as u64);
+
+            let name = match id {
+                0 => {
+                    let custom_name = section.name()?;
+                    match custom_name.as_str() {
+                        "name" => parse_function_names(&mut section, &mut function_names),
+                        "target_features" => {
+                            parse_target_features(&mut section, &mut module.features)
+                        }
+                        _ => {}
+                    }
+                    format!("custom:{custom_name}")
+                }
+                1 => {
+                    types = parse_types(&mut section, &mut module.features)?;
+                    "type".to_string()
+                }
+                2 => {
+                    for _ in 0..section.u32()? {
+                        let import_module = section.name()?;
+                        let import_name = section.name()?;
+                        let (kind, signature) = match section.u8()? {
+                            0x00 => {
+                                let type_index = section.u32()?;
+                                function_types.push(type_index);
+                                imported_functions += 1;
+                                (ExternalKind::Function, type_name(&types, type_index))
+                            }
+                            0x01 => {
+                                let table = parse_table_type(&mut section)?;
+                                tables.push(table.clone());
+                                (ExternalKind::Table, table)
+                            }
+                            0x02 => {
+                                let memory = parse_limits(&mut section, &mut module.features)?;
+                                memories.push(memory.clone());
+                                (ExternalKind::Memory, format!("memory {memory}"))
+                            }
+                            0x03 => {
+                                let global = parse_global_type(&mut section)?;
+                                if global.starts_with("mut ") {
+                                    module.features.insert("mutable-globals".to_string());
+                                }
+                                globals.push(global.clone());
+                                (ExternalKind::Global, global)
+                            }
+                            0x04 => {
+                                section.u8()?;
+                                let tag = type_name(&types, section.u32()?);
+                                tags.push(tag.clone());
+                                (ExternalKind::Tag, tag)
+                            }
+                            other => return Err(invalid(format!("unknown import kind {other}"))),
+                        };
+                        module.imports.push(WasmImport {
+                            module: import_module,
+                            name: import_name,
+                            kind,
+                            signature,
+                        });
+                    }
+                    "import".to_string()
+                }
+                3 => {
+                    for _ in 0..section.u32()? {
+                        function_types.push(section.u32()?);
+                    }
+                    "function".to_string()
+                }
+                4 => {
+                    for _ in 0..section.u32()? {
+                        tables.push(parse_table_type(&mut section)?);
+                    }
+                    "table".to_string()
+                }
+                5 => {
+                    for _ in 0..section.u32()? {
+                        memories.push(parse_limits(&mut section, &mut module.features)?);
+                    }
+                    "memory".to_string()
+                }
+                6 => {
+                    // Globals are only needed to describe exports; stop at the
+                    // first initializer we cannot decode rather than failing.
+                    for _ in 0..section.u32()? {
+                        let Ok(global) = parse_global_type(&mut section) else {
+                            break;
+                        };
+                        if skip_const_expr(&mut section).is_err() {
+                            globals.push(global);
+                            break;
+                        }
+                        globals.push(global);
+                    }
+                    "global".to_string()
+                }
+                7 => {
+                    for _ in 0..section.u32()? {
+                        let name = section.name()?;
+                        let kind = match section.u8()? {
+                            0x00 => ExternalKind::Function,
+                            0x01 => ExternalKind::Table,
+                            0x02 => ExternalKind::Memory,
+                            0x03 => ExternalKind::Global,
+                            0x04 => ExternalKind::Tag,
+                            other => return Err(invalid(format!("unknown export kind {other}"))),
+                        };
+                        raw_exports.push((name, kind, section.u32()?));
+                    }
+                    "export".to_string()
+                }
+                8 => "start".to_string(),
+                9 => "element".to_string(),
+                10 => {
+                    for _ in 0..section.u32()? {
+                        let body_size = section.u32()?;
+                        section.bytes(body_size as usize)?;
+                        body_sizes.push(body_size as u64);
+                    }
+                    "code".to_string()
+                }
+                11 => "data".to_string(),
+                12 => {
+                    module.features.insert("bulk-memory".to_string());
+                    "datacount".to_string()
+                }
+                13 => {
+                    module.features.insert("exception-handling".to_string());
+                    "tag".to_string()
+                }
+                other => format!("unknown:{other}"),
+            };
+
+            module.sections.push(WasmSection {
+                name,
+                size: (header_size + size) as u64,
+            });
+        }
+
+        if memories.len() > 1 {
+            module.features.insert("multi-memory".to_string());
+        }
+        if tables.len() > 1 || tables.iter().any(|t| !t.starts_with("funcref")) {
+            module.features.insert("reference-types".to_string());
+        }
+
+        for (offset, body_size) in body_sizes.into_iter().enumerate() {
+            let index = imported_functions + offset as u32;
+            module.functions.push(WasmFunction {
+                index,
+                name: String::new(),
+                body_size,
+            });
+        }
+
+        for (name, kind, index) in raw_exports {
+            let signature = match kind {
+                ExternalKind::Function => function_types
+                    .get(index as usize)
+                    .map(|type_index| type_name(&types, *type_index))
+                    .unwrap_or_default(),
+                ExternalKind::Table => tables.get(index as usize).cloned().unwrap_or_default(),
+                ExternalKind::Memory => memories
+                    .get(index as usize)
+                    .map(|m| format!("memory {m}"))
+                    .unwrap_or_default(),
+                ExternalKind::Global => {
+                    let global = globals.get(index as usize).cloned().unwrap_or_default();
+                    if global.starts_with("mut ") {
+                        module.features.insert("mutable-globals".to_string());
+                    }
+                    global
+                }
+                ExternalKind::Tag => tags.get(index as usize).cloned().unwrap_or_default(),
+            };
+
+            if kind == ExternalKind::Function {
+                function_names.entry(index).or_insert_with(|| name.clone());
+            }
+
+            module.exports.push(WasmExport {
+                name,
+                kind,
+                signature,
+            });
+        }
+
+        for function in &mut module.functions {
+            function.name = function_names
+                .get(&function.index)
+                .cloned()
+                .unwrap_or_else(|| format!("func[{}]", function.index));
+        }
+
+        Ok(module)
+    }
+
+    pub fn section_size(&self, name: &str) -> u64 {
+        self.sections
+            .iter()
+            .filter(|s| s.name == name)
+            .map(|s| s.size)
+            .sum()
+    }
+}
+
+/// Whether `bytes` start with the header of a core WebAssembly module.
+pub fn is_wasm(bytes: &[u8]) -> bool {
+    bytes.len() >= 8 && &bytes[..4] == WASM_MAGIC && &bytes[4..8] == WASM_VERSION
+}
+
+fn invalid(reason: impl Into<String>) -> WasmRustError {
+    WasmRustError::InvalidWasm(reason.into())
+}
+
+fn leb_len(mut value: u64) -> usize {
+    let mut len = 1;
+    while value >= 0x80 {
+        value >>= 7;
+        len += 1;
+    }
+    len
+}
+
+fn type_name(types: &[String], index: u32) -> String {
+    types
+        .get(index as usize)
+        .cloned()
+        .unwrap_or_else(|| format!("type[{index}]"))
+}
+
+fn parse_types(
+    reader: &mut Reader,
+    features: &mut BTreeSet<String>,
+) -> WasmRustResult<Vec<String>> {
+    let mut types = Vec::new();
+    for _ in 0..reader.u32()? {
+        let form = reader.u8()?;
+        if form != 0x60 {
+            return Err(invalid(format!("unsupported type form 0x{form:02x}")));
+        }
+
+        let params = parse_value_types(reader)?;
+        let results = parse_value_types(reader)?;
+        if results.len() > 1 {
+            features.insert("multi-value".to_string());
+        }
+        if params.iter().chain(&results).any(|t| t == "v128") {
+            features.insert("simd128".to_string());
+        }
+
+        let results = match results.len() {
+            0 => "()".to_string(),
+            1 => results[0].clone(),
+            _ => format!("({})", results.join(", ")),
+        };
+        types.push(format!("({}) -> {results}", params.join(", ")));
+    }
+    Ok(types)
+}
+
+fn parse_value_types(reader: &mut Reader) -> WasmRustResult<Vec<String>> {
+    let mut types = Vec::new();
+    for _ in 0..reader.u32()? {
+        types.push(parse_value_type(reader)?);
+    }
+    Ok(types)
+}
+
+fn parse_value_type(reader: &mut Reader) -> WasmRustResult<String> {
+    let value_type = match reader.u8()? {
+        0x7F => "i32",
+        0x7E => "i64",
+        0x7D => "f32",
+        0x7C => "f64",
+        0x7B => "v128",
+        0x70 => "funcref",
+        0x6F => "externref",
+        0x63 | 0x64 => {
+            reader.s64()?;
+            "ref"
+        }
+        other => return Err(invalid(format!("unknown value type 0x{other:02x}"))),
+    };
+    Ok(value_type.to_string())
+}
+
+fn parse_limits(reader: &mut Reader, features: &mut BTreeSet<String>) -> WasmRustResult<String> {
+    let flags = reader.u8()?;
+    let min = reader.u64()?;
+    let max = if flags & 0x01 != 0 {
+        Some(reader.u64()?)
+    } else {
+        None
+    };
+
+    let mut limits = match max {
+        Some(max) => format!("{min}..{max}"),
+        None => format!("{min}.."),
+    };
+    if flags & 0x02 != 0 {
+        features.insert("threads".to_string());
+        limits.push_str(" shared");
+    }
+    if flags & 0x04 != 0 {
+        features.insert("memory64".to_string());
+        limits.push_str(" i64");
+    }
+    Ok(limits)
+}
+
+fn parse_table_type(reader: &mut Reader) -> WasmRustResult<String> {
+    let element = parse_value_type(reader)?;
+    let mut ignored = BTreeSet::new();
+    let limits = parse_limits(reader, &mut ignored)?;
+    Ok(format!("{element} {limits}"))
+}
+
+fn parse_global_type(reader: &mut Reader) -> WasmRustResult<String> {
+    let value_type = parse_value_type(reader)?;
+    Ok(if reader.u8()? == 0x01 {
+        format!("mut {value_type}")
+    } else {
+        value_type
+    })
+}
+
+fn skip_const_expr(reader: &mut Reader) -> WasmRustResult<()> {
+    loop {
+        match reader.u8()? {
+            0x0B => return Ok(()),
+            0x41 | 0x42 => {
+                reader.s64()?;
+            }
+            0x43 => {
+                reader.bytes(4)?;
+            }
+            0x44 => {
+                reader.bytes(8)?;
+            }
+            0x23 | 0xD2 => {
+                reader.u32()?;
+            }
+            0xD0 => {
+                reader.s64()?;
+            }
+            0x6A | 0x6B | 0x6C | 0x7C | 0x7D | 0x7E => {}
+            other => return Err(invalid(format!("unsupported const opcode 0x{other:02x}"))),
+        }
+    }
+}
+
+// Best effort: a malformed name section should not make the module unreadable.
+fn parse_function_names(reader: &mut Reader, names: &mut BTreeMap<u32, String>) {
+    let _ = (|| -> WasmRustResult<()> {
+        while !reader.is_empty() {
+            let id = reader.u8()?;
+            let size = reader.u32()? as usize;
+            let mut subsection = Reader::new(reader.bytes(size)?);
+            if id != 1 {
+                continue;
+            }
+            for _ in 0..subsection.u32()? {
+                let index = subsection.u32()?;
+                names.insert(index, subsection.name()?);
+            }
+        }
+        Ok(())
+    })();
+}
+
+fn parse_target_features(reader: &mut Reader, features: &mut BTreeSet<String>) {
+    let _ = (|| -> WasmRustResult<()> {
+        for _ in 0..reader.u32()? {
+            let prefix = reader.u8()?;
+            let feature = reader.name()?;
+            if prefix == b'+' || prefix == b'=' {
+                features.insert(feature);
+            }
+        }
+        Ok(())
+    })();
+}
+
+struct Reader<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn new(data: &'a [u8]) -> Self {
+        Self { data, pos: 0 }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.pos >= self.data.len()
+    }
+
+    fn u8(&mut self) -> WasmRustResult<u8> {
+        let byte = *self
+            .data
+            .get(self.pos)
+            .ok_or_else(|| invalid("unexpected end of data"))?;
+        self.pos += 1;
+        Ok(byte)
+    }
+
+    fn bytes(&mut self, len: usize) -> WasmRustResult<&'a [u8]> {
+        let end = self
+            .pos
+            .checked_add(len)
+            .filter(|end| *end <= self.data.len())
+            .ok_or_else(|| invalid("section extends past end of data"))?;
+        let slice = &self.data[self.pos..end];
+        self.pos = end;
+        Ok(slice)
+    }
+
+    fn u64(&mut self) -> WasmRustResult<u64> {
+        let mut result = 0u64;
+        let mut shift = 0;
+        loop {
+            let byte = self.u8()?;
+            if shift >= 64 {
+                return Err(invalid("LEB128 value too large"));
+            }
+            result |= u64::from(byte & 0x7F) << shift;
+            if byte & 0x80 == 0 {
+                return Ok(result);
+            }
+            shift += 7;
+        }
+    }
+
+    fn u32(&mut self) -> WasmRustResult<u32> {
+        u32::try_from(self.u64()?).map_err(|_| invalid("LEB128 value exceeds u32"))
+    }
+
+    fn s64(&mut self) -> WasmRustResult<i64> {
+        let mut result = 0i64;
+        let mut shift = 0;
+        loop {
+            let byte = self.u8()?;
+            if shift >= 64 {
+                return Err(invalid("LEB128 value too large"));
+            }
+            result |= i64::from(byte & 0x7F) << shift;
+            shift += 7;
+            if byte & 0x80 == 0 {
+                if shift < 64 && byte & 0x40 != 0 {
+                    result |= -1 << shift;
+                }
+                return Ok(result);
+            }
+        }
+    }
+
+    fn name(&mut self) -> WasmRustResult<String> {
+        let len = self.u32()? as usize;
+        let bytes = self.bytes(len)?;
+        String::from_utf8(bytes.to_vec()).map_err(|_| invalid("name is not valid UTF-8"))
+    }
+}
@@ -247,6 +247,7 @@ fn main() {
             target_type: "wasm".to_string(),
             verbose: true,
             watch: false,
+            ..Default::default()
         };
 
         match builder.build(&config) {
@@ -279,6 +280,57 @@ fn main() {
     }
 }
 
+mod fake_builds {
+    use std::fs;
+    use std::path::{Path, PathBuf};
+    use std::sync::Arc;
+    use wasmrust::{
+        CommandOutput, RecordingRunner, WasmRustPlugin, DEFAULT_PROBE_TTL, WASM_TARGET,
+    };
+
+    /// A library crate in `dir/fake-wasm` whose release build is already in
+    /// `target`, as if cargo had run.
+    pub fn fake_project(dir: &Path) -> PathBuf {
+        let project = dir.join("fake-wasm");
+        let built = project.join("target").join(WASM_TARGET).join("release");
+        fs::create_dir_all(project.join("src")).unwrap();
+        fs::create_dir_all(&built).unwrap();
+        fs::write(
+            project.join("Cargo.toml"),
+            "[package]\nname = \"fake-wasm\"\nversion = \"0.1.0\"\n",
+        )
+        .unwrap();
+        fs::write(project.join("src/lib.rs"), "").unwrap();
+        fs::write(built.join("fake_wasm.wasm"), b"\0asm\x01\0\0\0").unwrap();
+        project
+    }
+
+    /// A plugin that runs nothing, on a stable toolchain with the wasm target
+    /// installed.
+    pub fn fake_plugin() -> (WasmRustPlugin, Arc<RecordingRunner>) {
+        let runner = Arc::new(RecordingRunner::new());
+        runner.respond(
+            "rustup show active-toolchain",
+            CommandOutput::ok("stable-x86_64-unknown-linux-gnu (default)\n"),
+        );
+        runner.respond(
+            "rustup target list",
+            CommandOutput::ok(&format!("{WASM_TARGET}\n")),
+        );
+        let plugin = WasmRustPlugin::with_runner(runner.clone(), DEFAULT_PROBE_TTL);
+        (plugin, runner)
+    }
+
+    /// How many `cargo build`s `runner` has run.
+    pub fn cargo_builds(runner: &RecordingRunner) -> usize {
+        runner
+            .invocations_of("cargo")
+            .iter()
+            .filter(|command| command.args.first().is_some_and(|arg| arg == "build"))
+            .count()
+    }
+}
+
 mod standalone_tests {
     use std::fs;
     use tempfile::TempDir;
@@ -344,3 +396,2872 @@ wasm-bindgen = "0.2"
         }
     }
 }
+
+mod budget_tests {
+    use std::fs;
+    use tempfile::TempDir;
+    use wasmrust::{evaluate_budgets, BudgetStatus, CompileResult, SizeBudgets};
+
+    fn write_output(dir: &std::path::Path, wasm_len: usize, js_len: usize) -> CompileResult {
+        let wasm_path = dir.join("app_bg.wasm");
+        let js_path = dir.join("app.js");
+        fs::write(&wasm_path, vec![0u8; wasm_len]).unwrap();
+        fs::write(&js_path, vec![b'a'; js_len]).unwrap();
+
+        CompileResult {
+            wasm_path: wasm_path.to_string_lossy().to_string(),
+            js_path: Some(js_path.to_string_lossy().to_string()),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_budgets_within_limits() {
+        let temp_dir = TempDir::new().unwrap();
+        let result = write_output(temp_dir.path(), 100, 50);
+
+        let budgets = SizeBudgets {
+            max_wasm_bytes: Some(1_000),
+            max_total_bundle_bytes: Some(1_000),
+            ..Default::default()
+        };
+
+        let report = evaluate_budgets(&budgets, &result).unwrap();
+        assert_eq!(report.checks.len(), 2);
+        assert!(report.checks.iter().all(|c| c.status == BudgetStatus::Ok));
+        assert_eq!(report.checks[1].actual, 150);
+    }
+
+    #[test]
+    fn test_budgets_warning_and_exceeded() {
+        let temp_dir = TempDir::new().unwrap();
+        let result = write_output(temp_dir.path(), 950, 100);
+
+        let budgets = SizeBudgets {
+            max_wasm_bytes: Some(1_000),
+            max_total_bundle_bytes: Some(1_000),
+            max_gzip_bytes: Some(10_000),
+        };
+
+        let report = evaluate_budgets(&budgets, &result).unwrap();
+        let warnings = report.warnings();
+        assert_eq!(warnings.len(), 1);
+        assert_eq!(warnings[0].budget, "max_wasm_bytes");
+        assert_eq!(warnings[0].artifact, "app_bg.wasm");
+
+        let violations = report.violations();
+        assert_eq!(violations.len(), 1);
+        assert_eq!(violations[0].budget, "max_total_bundle_bytes");
+        assert_eq!(violations[0].actual, 1_050);
+        assert!(report.is_exceeded());
+    }
+
+    #[test]
+    fn test_budgets_config_overrides_project() {
+        let project = SizeBudgets {
+            max_wasm_bytes: Some(10),
+            max_gzip_bytes: Some(20),
+            ..Default::default()
+        };
+        let config = SizeBudgets {
+            max_wasm_bytes: Some(30),
+            ..Default::default()
+        };
+
+        let merged = config.or(&project);
+        assert_eq!(merged.max_wasm_bytes, Some(30));
+        assert_eq!(merged.max_gzip_bytes, Some(20));
+        assert_eq!(merged.max_total_bundle_bytes, None);
+    }
+}
+
+mod wasm_fixtures {
+    pub struct TestFunc {
+        pub name: &'static str,
+        pub params: Vec<u8>,
+        pub results: Vec<u8>,
+        pub body_len: usize,
+    }
+
+    pub fn func(name: &'static str, params: &[u8], results: &[u8], body_len: usize) -> TestFunc {
+        TestFunc {
+            name,
+            params: params.to_vec(),
+            results: results.to_vec(),
+            body_len,
+        }
+    }
+
+    fn leb(mut value: usize, out: &mut Vec<u8>) {
+        loop {
+            let byte = (value & 0x7F) as u8;
+            value >>= 7;
+            if value == 0 {
+                out.push(byte);
+                return;
+            }
+            out.push(byte | 0x80);
+        }
+    }
+
+    fn name(value: &str, out: &mut Vec<u8>) {
+        leb(value.len(), out);
+        out.extend_from_slice(value.as_bytes());
+    }
+
+    fn section(id: u8, body: Vec<u8>, out: &mut Vec<u8>) {
+        out.push(id);
+        leb(body.len(), out);
+        out.extend(body);
+    }
+
+    /// Encodes a module exporting `funcs` (one type each), importing
+    /// `imports` as `() -> ()` functions and optionally exporting a memory.
+    pub fn encode_module(funcs: &[TestFunc], imports: &[(&str, &str)], memory: bool) -> Vec<u8> {
+        let mut out = b"\0asm\x01\0\0\0".to_vec();
+
+        let mut types = Vec::new();
+        leb(funcs.len() + 1, &mut types);
+        for f in funcs {
+            types.push(0x60);
+            leb(f.params.len(), &mut types);
+            types.extend(&f.params);
+            leb(f.results.len(), &mut types);
+            types.extend(&f.results);
+        }
+        types.extend([0x60, 0x00, 0x00]);
+        section(1, types, &mut out);
+
+        if !imports.is_empty() {
+            let mut body = Vec::new();
+            leb(imports.len(), &mut body);
+            for (module, field) in imports {
+                name(module, &mut body);
+                name(field, &mut body);
+                body.push(0x00);
+                leb(funcs.len(), &mut body);
+            }
+            section(2, body, &mut out);
+        }
+
+        let mut functions = Vec::new();
+        leb(funcs.len(), &mut functions);
+        for index in 0..funcs.len() {
+            leb(index, &mut functions);
+        }
+        section(3, functions, &mut out);
+
+        if memory {
+            section(5, vec![0x01, 0x00, 0x11], &mut out);
+        }
+
+        let mut exports = Vec::new();
+        leb(funcs.len() + usize::from(memory), &mut exports);
+        for (index, f) in funcs.iter().enumerate() {
+            name(f.name, &mut exports);
+            exports.push(0x00);
+            leb(imports.len() + index, &mut exports);
+        }
+        if memory {
+            name("memory", &mut exports);
+            exports.extend([0x02, 0x00]);
+        }
+        section(7, exports, &mut out);
+
+        let mut code = Vec::new();
+        leb(funcs.len(), &mut code);
+        for f in funcs {
+            leb(f.body_len, &mut code);
+            code.push(0x00);
+            code.extend(std::iter::repeat_n(0x01, f.body_len - 2));
+            code.push(0x0B);
+        }
+        section(10, code, &mut out);
+
+        out
+    }
+}
+
+mod diff_tests {
+    use super::wasm_fixtures::{encode_module, func};
+    use std::fs;
+    use tempfile::TempDir;
+    use wasmrust::{
+        diff_builds, BuildManifest, ExternalKind, ManifestArtifact, WasmModule, MANIFEST_FILE_NAME,
+    };
+
+    const I32: u8 = 0x7F;
+    const I64: u8 = 0x7E;
+
+    #[test]
+    fn test_parse_module_surface() {
+        let bytes = encode_module(
+            &[func("add", &[I32, I32], &[I32], 10)],
+            &[("env", "log")],
+            true,
+        );
+        let module = WasmModule::parse(&bytes).unwrap();
+
+        assert_eq!(module.size, bytes.len() as u64);
+        assert_eq!(module.imports.len(), 1);
+        assert_eq!(module.imports[0].qualified_name(), "env::log");
+
+        let add = module.exports.iter().find(|e| e.name == "add").unwrap();
+        assert_eq!(add.kind, ExternalKind::Function);
+        assert_eq!(add.signature, "(i32, i32) -> i32");
+
+        let memory = module.exports.iter().find(|e| e.name == "memory").unwrap();
+        assert_eq!(memory.kind, ExternalKind::Memory);
+        assert_eq!(memory.signature, "memory 17..");
+
+        assert_eq!(module.functions.len(), 1);
+        assert_eq!(module.functions[0].name, "add");
+        assert_eq!(module.functions[0].body_size, 10);
+    }
+
+    #[test]
+    fn test_parse_rejects_non_wasm() {
+        assert!(WasmModule::parse(b"not a wasm module").is_err());
+    }
+
+    #[test]
+    fn test_parse_rejects_malformed_modules() {
+        let error = |bytes: &[u8]| WasmModule::parse(bytes).unwrap_err().to_string();
+        let module = |sections: &[u8]| [b"\0asm\x01\0\0\0".as_slice(), sections].concat();
+
+        assert!(error(b"\0asm\x01\0").contains("missing \\0asm header"));
+        // A component rather than a core module.
+        assert!(error(b"\0asm\x0d\0\x01\0").contains("unsupported binary version 65549"));
+
+        for (sections, reason) in [
+            (&[1, 10, 0x60][..], "section extends past end of data"),
+            (&[1, 0x80], "unexpected end of data"),
+            (&[1, 1, 5], "unexpected end of data"),
+            (
+                &[1, 0xFF, 0xFF, 0xFF, 0xFF, 0x7F],
+                "LEB128 value exceeds u32",
+            ),
+            (
+                &[
+                    1, 0x80, 0x80, 0x80, 0x80, 0x80, 0x80, 0x80, 0x80, 0x80, 0x80, 0x01,
+                ],
+                "LEB128 value too large",
+            ),
+            (&[0, 3, 2, 0xFF, 0xFE], "name is not valid UTF-8"),
+        ] {
+            assert!(error(&module(sections)).contains(reason), "{sections:?}");
+        }
+
+        // Cut anywhere, a module fails to parse or parses a prefix; it never panics.
+        let bytes = encode_module(
+            &[func("add", &[I32, I32], &[I32], 10)],
+            &[("env", "log")],
+            true,
+        );
+        for len in 0..bytes.len() {
+            let _ = WasmModule::parse(&bytes[..len]);
+        }
+    }
+
+    #[test]
+    fn test_diff_wasm_files() {
+        let temp_dir = TempDir::new().unwrap();
+        let old_path = temp_dir.path().join("old.wasm");
+        let new_path = temp_dir.path().join("new.wasm");
+
+        fs::write(
+            &old_path,
+            encode_module(
+                &[
+                    func("add", &[I32, I32], &[I32], 10),
+                    func("legacy", &[], &[], 4),
+                ],
+                &[("env", "log")],
+                false,
+            ),
+        )
+        .unwrap();
+        fs::write(
+            &new_path,
+            encode_module(
+                &[
+                    func("add", &[I64, I64], &[I64], 30),
+                    func("fresh", &[], &[I32, I32], 4),
+                ],
+                &[],
+                false,
+            ),
+        )
+        .unwrap();
+
+        let diff = diff_builds(&old_path, &new_path).unwrap();
+        assert_eq!(diff.modules.len(), 1);
+        let module = &diff.modules[0];
+
+        assert_eq!(module.exports_added.len(), 1);
+        assert_eq!(module.exports_added[0].name, "fresh");
+        assert_eq!(module.exports_removed.len(), 1);
+        assert_eq!(module.exports_removed[0].name, "legacy");
+        assert_eq!(module.exports_changed.len(), 1);
+        assert_eq!(module.exports_changed[0].name, "add");
+        assert_eq!(module.imports_removed.len(), 1);
+        assert_eq!(module.features_added, vec!["multi-value".to_string()]);
+
+        let add = module.functions.iter().find(|f| f.name == "add").unwrap();
+        assert_eq!(add.delta(), 20);
+        assert!(module.sections.iter().any(|s| s.name == "code"));
+    }
+
+    #[test]
+    fn test_diff_manifests_reports_artifact_changes() {
+        let temp_dir = TempDir::new().unwrap();
+        let wasm = encode_module(&[func("run", &[], &[], 4)], &[], false);
+
+        for (dir, extra) in [("old", "old.js"), ("new", "new.css")] {
+            let out = temp_dir.path().join(dir);
+            fs::create_dir_all(&out).unwrap();
+            fs::write(out.join("app_bg.wasm"), &wasm).unwrap();
+            fs::write(out.join(extra), "x").unwrap();
+
+            let manifest = BuildManifest {
+                wasm_path: Some("app_bg.wasm".to_string()),
+                artifacts: vec![
+                    ManifestArtifact {
+                        path: "app_bg.wasm".to_string(),
+                        size: wasm.len() as u64,
+                        ..Default::default()
+                    },
+                    ManifestArtifact {
+                        path: extra.to_string(),
+                        size: 1,
+                        ..Default::default()
+                    },
+                ],
+                ..Default::default()
+            };
+            manifest.write(&out).unwrap();
+        }
+
+        let diff = diff_builds(
+            &temp_dir.path().join("old").join(MANIFEST_FILE_NAME),
+            &temp_dir.path().join("new"),
+        )
+        .unwrap();
+
+        assert_eq!(diff.artifacts_added, vec!["new.css".to_string()]);
+        assert_eq!(diff.artifacts_removed, vec!["old.js".to_string()]);
+        assert_eq!(diff.modules.len(), 1);
+        assert_eq!(diff.modules[0].size_delta(), 0);
+        assert!(!diff.modules[0].has_interface_changes());
+    }
+}
+
+mod compat_tests {
+    use super::wasm_fixtures::{encode_module, func};
+    use std::fs;
+    use tempfile::TempDir;
+    use wasmrust::{
+        check_compatibility, parse_typescript_declarations, CompatChangeKind, CompileResult,
+        ExportSurface, WasmModule,
+    };
+
+    const I32: u8 = 0x7F;
+    const F64: u8 = 0x7C;
+
+    fn surface(bytes: &[u8]) -> ExportSurface {
+        ExportSurface::from_module(&WasmModule::parse(bytes).unwrap())
+    }
+
+    #[test]
+    fn test_surface_captures_functions_and_memory() {
+        let surface = surface(&encode_module(
+            &[
+                func("add", &[I32, I32], &[I32], 4),
+                func("__wbindgen_malloc", &[I32], &[I32], 4),
+            ],
+            &[],
+            true,
+        ));
+
+        let functions: Vec<_> = surface.function_exports().collect();
+        assert_eq!(functions, vec![("add", "(i32, i32) -> i32")]);
+        let memories: Vec<_> = surface.memory_exports().collect();
+        assert_eq!(memories, vec![("memory", "memory 17..")]);
+    }
+
+    #[test]
+    fn test_compatibility_classification() {
+        let baseline = surface(&encode_module(
+            &[
+                func("add", &[I32, I32], &[I32], 4),
+                func("remove_me", &[], &[], 4),
+                func("stable", &[], &[], 4),
+            ],
+            &[],
+            true,
+        ));
+        let current = surface(&encode_module(
+            &[
+                func("add", &[F64, F64], &[F64], 4),
+                func("stable", &[], &[], 8),
+                func("new_api", &[], &[], 4),
+            ],
+            &[],
+            true,
+        ));
+
+        let report = check_compatibility(&baseline, &current);
+        assert!(report.is_breaking());
+
+        let mut breaking: Vec<_> = report
+            .breaking
+            .iter()
+            .map(|c| (c.item.as_str(), c.kind))
+            .collect();
+        breaking.sort_by_key(|(item, _)| *item);
+        assert_eq!(
+            breaking,
+            vec![
+                ("func add", CompatChangeKind::Changed),
+                ("func remove_me", CompatChangeKind::Removed),
+            ]
+        );
+
+        assert_eq!(report.additive.len(), 1);
+        assert_eq!(report.additive[0].item, "func new_api");
+
+        assert!(check_compatibility(&current, &current).is_unchanged());
+    }
+
+    #[test]
+    fn test_typescript_declarations() {
+        let dts = r#"/* tslint:disable */
+/* eslint-disable */
+/**
+* @param {string} name
+*/
+export function greet(name: string): void;
+export class Calculator {
+  free(): void;
+  constructor();
+  add(a: number, b: number): number;
+  scale(factor: number): number;
+  scale(x: number, y: number): number;
+}
+export function __wbg_set_panic_hook(): void;
+export type InitInput = RequestInfo | URL | Response | BufferSource | WebAssembly.Module;
+export interface InitOutput {
+  readonly memory: WebAssembly.Memory;
+  readonly __wbindgen_malloc: (a: number) => number;
+}
+export type SyncInitInput = BufferSource | WebAssembly.Module;
+export function initSync(module: { module: SyncInitInput } | SyncInitInput): InitOutput;
+export default function __wbg_init (module_or_path?: InitInput): Promise<InitOutput>;
+"#;
+        let declarations = parse_typescript_declarations(dts);
+
+        assert_eq!(
+            declarations.get("greet").map(String::as_str),
+            Some("function greet(name: string): void")
+        );
+        assert_eq!(
+            declarations.get("Calculator.add").map(String::as_str),
+            Some("add(a: number, b: number): number")
+        );
+        assert!(declarations.contains_key("Calculator.constructor"));
+        assert!(!declarations.contains_key("Calculator.scale"));
+        assert!(declarations.contains_key("Calculator.scale(factor: number): number"));
+        assert!(declarations.contains_key("Calculator.scale(x: number, y: number): number"));
+
+        let keys: Vec<_> = declarations.keys().map(String::as_str).collect();
+        assert_eq!(
+            keys,
+            vec![
+                "Calculator",
+                "Calculator.add",
+                "Calculator.constructor",
+                "Calculator.free",
+                "Calculator.scale(factor: number): number",
+                "Calculator.scale(x: number, y: number): number",
+                "greet",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_surface_from_build_includes_typescript() {
+        let temp_dir = TempDir::new().unwrap();
+        let wasm_path = temp_dir.path().join("app_bg.wasm");
+        let js_path = temp_dir.path().join("app.js");
+        fs::write(
+            &wasm_path,
+            encode_module(&[func("greet", &[I32, I32], &[], 4)], &[], false),
+        )
+        .unwrap();
+        fs::write(&js_path, "").unwrap();
+        fs::write(
+            temp_dir.path().join("app.d.ts"),
+            "export function greet(name: string): void;\n",
+        )
+        .unwrap();
+
+        let surface = ExportSurface::from_result(&CompileResult {
+            wasm_path: wasm_path.to_string_lossy().to_string(),
+            js_path: Some(js_path.to_string_lossy().to_string()),
+            ..Default::default()
+        })
+        .unwrap();
+
+        assert!(surface.exports.contains_key("func greet"));
+        assert!(surface.typescript.contains_key("greet"));
+
+        let snapshot = temp_dir.path().join("api/snapshot.json");
+        surface.write(&snapshot).unwrap();
+        assert_eq!(ExportSurface::load(&snapshot).unwrap(), surface);
+    }
+}
+
+mod compress_tests {
+    use std::fs;
+    use tempfile::TempDir;
+    use wasmrust::{precompress_outputs, BuildManifest, CompileResult, PrecompressConfig};
+
+    fn webapp_output(dir: &std::path::Path) -> CompileResult {
+        fs::write(dir.join("app_bg.wasm"), vec![0u8; 4096]).unwrap();
+        fs::write(dir.join("app.js"), "export function run() {}\n".repeat(50)).unwrap();
+        fs::write(dir.join("index.html"), "<html><body></body></html>").unwrap();
+        fs::write(dir.join("logo.png"), [0x89, b'P', b'N', b'G']).unwrap();
+
+        CompileResult {
+            wasm_path: dir.to_string_lossy().to_string(),
+            js_path: Some(dir.join("index.html").to_string_lossy().to_string()),
+            is_webapp: true,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_precompress_writes_variants() {
+        let temp_dir = TempDir::new().unwrap();
+        let mut result = webapp_output(temp_dir.path());
+
+        let compressed = precompress_outputs(&PrecompressConfig::default(), &result).unwrap();
+        assert_eq!(compressed.len(), 3);
+
+        let wasm = compressed
+            .iter()
+            .find(|c| c.path.ends_with("app_bg.wasm"))
+            .unwrap();
+        let gzip_size = fs::metadata(temp_dir.path().join("app_bg.wasm.gz"))
+            .unwrap()
+            .len();
+        assert_eq!(wasm.gzip_size, Some(gzip_size));
+        assert!(wasm.brotli_size.unwrap() < wasm.size);
+        assert!(temp_dir.path().join("index.html.br").exists());
+        assert!(!temp_dir.path().join("logo.png.gz").exists());
+
+        // A second run must not compress the compressed variants again.
+        result.compressed = precompress_outputs(&PrecompressConfig::default(), &result).unwrap();
+        assert_eq!(result.compressed.len(), 3);
+
+        let manifest = BuildManifest::from_result(&result, temp_dir.path()).unwrap();
+        assert_eq!(manifest.artifacts.len(), 4);
+        let wasm_artifact = manifest.artifact("app_bg.wasm").unwrap();
+        assert_eq!(wasm_artifact.gzip_size, Some(gzip_size));
+    }
+
+    #[test]
+    fn test_precompress_respects_config() {
+        let temp_dir = TempDir::new().unwrap();
+        let result = webapp_output(temp_dir.path());
+
+        let config = PrecompressConfig {
+            brotli: false,
+            gzip_level: 1,
+            ..Default::default()
+        };
+        let compressed = precompress_outputs(&config, &result).unwrap();
+        assert!(compressed.iter().all(|c| c.brotli_size.is_none()));
+        assert!(!temp_dir.path().join("app_bg.wasm.br").exists());
+
+        let invalid = PrecompressConfig {
+            gzip_level: 12,
+            ..Default::default()
+        };
+        assert!(matches!(
+            precompress_outputs(&invalid, &result),
+            Err(wasmrust::WasmRustError::InvalidConfiguration(_))
+        ));
+    }
+}
+
+mod hashing_tests {
+    use std::fs;
+    use std::path::Path;
+    use std::sync::Arc;
+    use tempfile::TempDir;
+    use wasmrust::{
+        apply_content_hashes, content_hash, diff_builds, hashed_file_name, BuildManifest,
+        CommandOutput, CommandRunner, CompileConfig, CompileResult, PlannedCommand,
+        RecordingRunner, WasmRustPlugin, CONTENT_HASH_LENGTH, DEFAULT_PROBE_TTL,
+    };
+
+    fn bindgen_output(dir: &Path, wasm: &[u8]) -> CompileResult {
+        fs::write(dir.join("app_bg.wasm"), wasm).unwrap();
+        fs::write(
+            dir.join("app.js"),
+            "const url = new URL('app_bg.wasm', import.meta.url);\n",
+        )
+        .unwrap();
+        fs::write(dir.join("app.d.ts"), "export function run(): void;\n").unwrap();
+        fs::write(
+            dir.join("app_bg.wasm.d.ts"),
+            "export const memory: WebAssembly.Memory;\n",
+        )
+        .unwrap();
+        fs::write(
+            dir.join("package.json"),
+            r#"{"files":["app_bg.wasm","app.js","app.d.ts"],"module":"app.js"}"#,
+        )
+        .unwrap();
+        fs::write(
+            dir.join("index.html"),
+            r#"<script type="module">import init from "./app.js"; init();</script>"#,
+        )
+        .unwrap();
+
+        CompileResult {
+            wasm_path: dir.join("app_bg.wasm").to_string_lossy().to_string(),
+            js_path: Some(dir.join("app.js").to_string_lossy().to_string()),
+            additional_files: vec![
+                dir.join("app.d.ts").to_string_lossy().to_string(),
+                dir.join("package.json").to_string_lossy().to_string(),
+            ],
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_hashed_file_name() {
+        let hash = content_hash(b"wasm");
+        assert_eq!(hash.len(), CONTENT_HASH_LENGTH);
+        assert_eq!(hash, content_hash(b"wasm"));
+        assert_ne!(hash, content_hash(b"other"));
+
+        assert_eq!(hashed_file_name("app_bg.wasm", "abc"), "app_bg-abc.wasm");
+        assert_eq!(
+            hashed_file_name("app_bg.wasm.d.ts", "abc"),
+            "app_bg-abc.wasm.d.ts"
+        );
+        assert_eq!(hashed_file_name("LICENSE", "abc"), "LICENSE-abc");
+    }
+
+    #[test]
+    fn test_apply_content_hashes_rewrites_references() {
+        let temp_dir = TempDir::new().unwrap();
+        let dir = temp_dir.path();
+        let mut result = bindgen_output(dir, b"\0asm\x01\0\0\0");
+
+        let mapping = apply_content_hashes(&mut result, dir).unwrap();
+
+        let wasm_name = &mapping["app_bg.wasm"];
+        let js_name = &mapping["app.js"];
+        assert!(result.wasm_path.ends_with(wasm_name.as_str()));
+        assert!(result.js_path.as_ref().unwrap().ends_with(js_name.as_str()));
+        assert!(!dir.join("app_bg.wasm").exists());
+        assert!(dir.join(&mapping["app.d.ts"]).exists());
+        assert!(dir.join(&mapping["app_bg.wasm.d.ts"]).exists());
+
+        let js = fs::read_to_string(dir.join(js_name)).unwrap();
+        assert!(js.contains(wasm_name.as_str()));
+        // The glue is hashed after its references were rewritten.
+        assert!(js_name.contains(&content_hash(js.as_bytes())));
+
+        let html = fs::read_to_string(dir.join("index.html")).unwrap();
+        assert!(html.contains(&format!("./{js_name}")));
+        let package = fs::read_to_string(dir.join("package.json")).unwrap();
+        assert!(package.contains(&mapping["app.d.ts"]));
+        assert!(!package.contains("\"app.js\""));
+        assert!(result
+            .additional_files
+            .iter()
+            .any(|f| f.ends_with(mapping["app.d.ts"].as_str())));
+    }
+
+    #[test]
+    fn test_diff_matches_hashed_artifacts() {
+        let old_dir = TempDir::new().unwrap();
+        let new_dir = TempDir::new().unwrap();
+
+        for (dir, wasm) in [
+            (old_dir.path(), b"\0asm\x01\0\0\0".to_vec()),
+            (new_dir.path(), b"\0asm\x01\0\0\0\0\x01\0".to_vec()),
+        ] {
+            let mut result = bindgen_output(dir, &wasm);
+            result.hashed_files = apply_content_hashes(&mut result, dir).unwrap();
+            BuildManifest::from_result(&result, dir)
+                .unwrap()
+                .write(dir)
+                .unwrap();
+        }
+
+        let diff = diff_builds(old_dir.path(), new_dir.path()).unwrap();
+        assert!(diff.artifacts_added.is_empty());
+        assert!(diff.artifacts_removed.is_empty());
+        assert_eq!(diff.modules.len(), 1);
+        assert_eq!(diff.modules[0].artifact, "app_bg.wasm");
+        assert_eq!(diff.modules[0].size_delta(), 3);
+    }
+
+    /// Answers like [`RecordingRunner`], and writes what wasm-pack would
+    /// into the `--out-dir` of `wasm-pack build`.
+    struct FakeWasmPack(RecordingRunner);
+
+    impl CommandRunner for FakeWasmPack {
+        fn run(&self, command: &PlannedCommand) -> std::io::Result<CommandOutput> {
+            let out_dir = command.args.iter().position(|arg| arg == "--out-dir");
+            if let (true, Some(out_dir)) = (command.program == "wasm-pack", out_dir) {
+                let out_dir = Path::new(&command.args[out_dir + 1]);
+                fs::create_dir_all(out_dir)?;
+                fs::write(out_dir.join("app_bg.wasm"), b"\0asm\x01\0\0\0")?;
+                fs::write(
+                    out_dir.join("app.js"),
+                    "export default () => fetch('/app_bg.wasm');\n",
+                )?;
+            }
+            self.0.run(command)
+        }
+    }
+
+    #[test]
+    fn test_hashed_glue_matches_its_public_url_contents() {
+        let temp_dir = TempDir::new().unwrap();
+        let project = temp_dir.path().join("app");
+        fs::create_dir_all(project.join("src")).unwrap();
+        fs::write(
+            project.join("Cargo.toml"),
+            "[package]\nname = \"app\"\nversion = \"0.1.0\"\n\n[lib]\ncrate-type = [\"cdylib\"]\n\n\
+             [dependencies]\nwasm-bindgen = \"0.2\"\n",
+        )
+        .unwrap();
+        fs::write(project.join("src/lib.rs"), "").unwrap();
+
+        let runner = RecordingRunner::new();
+        runner.respond(
+            "wasm-pack --version",
+            CommandOutput::ok("wasm-pack 0.13.1\n"),
+        );
+        let plugin = WasmRustPlugin::with_runner(Arc::new(FakeWasmPack(runner)), DEFAULT_PROBE_TTL);
+        let result = plugin
+            .compile(&CompileConfig {
+                project_path: project.to_string_lossy().to_string(),
+                output_dir: temp_dir.path().join("out").to_string_lossy().to_string(),
+                content_hash: true,
+                public_url: Some("/apps/demo/".to_string()),
+                ..Default::default()
+            })
+            .unwrap();
+
+        let js_path = result.js_path.unwrap();
+        let js = fs::read(&js_path).unwrap();
+        assert!(js_path.ends_with(&format!("app-{}.js", content_hash(&js))));
+        let wasm_name = &result.hashed_files["app_bg.wasm"];
+        assert!(String::from_utf8(js)
+            .unwrap()
+            .contains(&format!("'/apps/demo/{wasm_name}'")));
+    }
+}
+
+mod integrity_tests {
+    use std::fs;
+    use tempfile::TempDir;
+    use wasmrust::{apply_integrity_to_html, sri_hash, WasmRustError};
+
+    #[test]
+    fn test_sri_hash_format() {
+        // Digest of the empty string, from the SRI specification examples.
+        assert_eq!(
+            sri_hash(b""),
+            "sha384-OLBgp1GsljhM2TJ+sbHjaiH9txEUvgdDTAzHv2P24donTt6/529l+9Ua0vFImLlb"
+        );
+    }
+
+    #[test]
+    fn test_injects_integrity_attributes() {
+        let temp_dir = TempDir::new().unwrap();
+        let dir = temp_dir.path();
+        fs::write(dir.join("app-1a2b.js"), "export default function init() {}").unwrap();
+        fs::write(dir.join("app_bg-1a2b.wasm"), b"\0asm\x01\0\0\0").unwrap();
+        fs::write(dir.join("style.css"), "body { margin: 0 }").unwrap();
+        fs::write(
+            dir.join("index.html"),
+            r#"<!DOCTYPE html>
+<html>
+<head>
+<link rel="stylesheet" href="style.css">
+<link rel="preload" href="/app_bg-1a2b.wasm" as="fetch" type="application/wasm" crossorigin integrity="sha384-stale">
+<link rel="icon" href="favicon.ico">
+</head>
+<body>
+<script type="module">import init from '/app-1a2b.js'; init('/app_bg-1a2b.wasm');</script>
+</body>
+</html>"#,
+        )
+        .unwrap();
+
+        let report = apply_integrity_to_html(&dir.join("index.html"), dir, "/").unwrap();
+        let html = fs::read_to_string(dir.join("index.html")).unwrap();
+
+        let css = sri_hash(b"body { margin: 0 }");
+        let js = sri_hash(b"export default function init() {}");
+        let wasm = sri_hash(b"\0asm\x01\0\0\0");
+        assert!(html.contains(&format!(r#"href="style.css" integrity="{css}">"#)));
+        assert!(html.contains(&format!(r#"crossorigin integrity="{wasm}">"#)));
+        assert!(!html.contains("sha384-stale"));
+        assert!(html.contains(&format!(
+            r#"<link rel="modulepreload" href="/app-1a2b.js" integrity="{js}">"#
+        )));
+        // The wasm already has a preload; it must not get a second one.
+        assert_eq!(html.matches("app_bg-1a2b.wasm\" as=").count(), 1);
+        assert_eq!(report.resources.len(), 3);
+
+        let inline = sri_hash(b"import init from '/app-1a2b.js'; init('/app_bg-1a2b.wasm');");
+        assert!(report.csp.contains("'wasm-unsafe-eval'"));
+        assert!(report.csp.contains(&format!("'{inline}'")));
+        assert!(report.csp_meta_tag().starts_with("<meta http-equiv"));
+    }
+
+    #[test]
+    fn test_unhashable_references_fail() {
+        let temp_dir = TempDir::new().unwrap();
+        let dir = temp_dir.path();
+        fs::write(
+            dir.join("index.html"),
+            r#"<script src="https://cdn.example.com/lib.js"></script>
+<script type="module">import init from './pkg/app.js'; init();</script>"#,
+        )
+        .unwrap();
+
+        match apply_integrity_to_html(&dir.join("index.html"), dir, "/") {
+            Err(WasmRustError::UnhashableReferences { references, .. }) => {
+                assert_eq!(
+                    references,
+                    vec!["https://cdn.example.com/lib.js", "./pkg/app.js"]
+                );
+            }
+            other => panic!("expected UnhashableReferences, got {other:?}"),
+        }
+        // Nothing is written when the page cannot be fully pinned.
+        assert!(!fs::read_to_string(dir.join("index.html"))
+            .unwrap()
+            .contains("integrity"));
+    }
+}
+
+mod public_url_tests {
+    use std::fs;
+    use std::path::Path;
+    use std::sync::Arc;
+    use tempfile::TempDir;
+    use wasmrust::{
+        apply_integrity_to_html, apply_public_url, normalize_public_url, sri_hash, CommandOutput,
+        CommandRunner, CompileConfig, CompileResult, PlannedCommand, RecordingRunner, TargetType,
+        WasmRustPlugin, DEFAULT_PROBE_TTL, WASM_TARGET,
+    };
+
+    #[test]
+    fn test_normalize_public_url() {
+        assert_eq!(normalize_public_url(""), "/");
+        assert_eq!(normalize_public_url("/"), "/");
+        assert_eq!(normalize_public_url("apps/demo"), "/apps/demo/");
+        assert_eq!(normalize_public_url("/apps/demo/"), "/apps/demo/");
+        assert_eq!(normalize_public_url("./"), "./");
+        assert_eq!(
+            normalize_public_url("https://cdn.example.com/demo"),
+            "https://cdn.example.com/demo/"
+        );
+    }
+
+    #[test]
+    fn test_rewrites_root_relative_references() {
+        let temp_dir = TempDir::new().unwrap();
+        let dir = temp_dir.path();
+        fs::write(dir.join("app_bg.wasm"), b"\0asm\x01\0\0\0").unwrap();
+        fs::write(
+            dir.join("app.js"),
+            "const url = '/app_bg.wasm';\nconst api = '/api/data.json';\n",
+        )
+        .unwrap();
+        fs::write(
+            dir.join("index.html"),
+            r#"<script type="module" src="/app.js"></script><a href="//example.com/">x</a>"#,
+        )
+        .unwrap();
+
+        let result = CompileResult {
+            wasm_path: dir.join("app_bg.wasm").to_string_lossy().to_string(),
+            js_path: Some(dir.join("app.js").to_string_lossy().to_string()),
+            additional_files: vec![dir.join("index.html").to_string_lossy().to_string()],
+            ..Default::default()
+        };
+
+        let rewritten = apply_public_url(&result, dir, "/apps/demo/").unwrap();
+        assert_eq!(rewritten.len(), 2);
+
+        let js = fs::read_to_string(dir.join("app.js")).unwrap();
+        assert!(js.contains("'/apps/demo/app_bg.wasm'"));
+        // Paths that are not build outputs are left alone.
+        assert!(js.contains("'/api/data.json'"));
+        let html = fs::read_to_string(dir.join("index.html")).unwrap();
+        assert!(html.contains(r#"src="/apps/demo/app.js""#));
+        assert!(html.contains(r#"href="//example.com/""#));
+
+        // Already rewritten references stay stable, and integrity resolves them.
+        assert!(apply_public_url(&result, dir, "/apps/demo/")
+            .unwrap()
+            .is_empty());
+        let report = apply_integrity_to_html(&dir.join("index.html"), dir, "/apps/demo/").unwrap();
+        assert_eq!(report.resources[0].reference, "/apps/demo/app.js");
+    }
+
+    /// Answers like [`RecordingRunner`], and writes what trunk would into
+    /// the `--dist` directory of `trunk build`.
+    struct FakeTrunk(RecordingRunner);
+
+    impl CommandRunner for FakeTrunk {
+        fn run(&self, command: &PlannedCommand) -> std::io::Result<CommandOutput> {
+            let dist = command.args.iter().position(|arg| arg == "--dist");
+            if let (true, Some(dist)) = (command.program == "trunk", dist) {
+                let dist = Path::new(&command.args[dist + 1]);
+                fs::write(
+                    dist.join("app-1a2b.js"),
+                    "export default function init() {}",
+                )?;
+                fs::write(dist.join("app_bg-1a2b.wasm"), b"\0asm\x01\0\0\0")?;
+                fs::write(
+                    dist.join("index.html"),
+                    r#"<script type="module">import init from '/apps/x/app-1a2b.js'; init('/apps/x/app_bg-1a2b.wasm');</script>"#,
+                )?;
+            }
+            self.0.run(command)
+        }
+    }
+
+    #[test]
+    fn test_trunk_public_url_reaches_integrity() {
+        let temp_dir = TempDir::new().unwrap();
+        let project = temp_dir.path().join("trunk-app");
+        fs::create_dir_all(project.join("src")).unwrap();
+        fs::write(
+            project.join("Cargo.toml"),
+            "[package]\nname = \"trunk-app\"\nversion = \"0.1.0\"\n\n[dependencies]\nyew = \"0.21\"\n",
+        )
+        .unwrap();
+        fs::write(project.join("src/main.rs"), "fn main() {}").unwrap();
+        fs::write(project.join("index.html"), "<html></html>").unwrap();
+        fs::write(
+            project.join("Trunk.toml"),
+            "[build]\npublic_url = \"/apps/x/\"\n",
+        )
+        .unwrap();
+
+        let runner = RecordingRunner::new();
+        runner.respond("trunk --version", CommandOutput::ok("trunk 0.21.4\n"));
+        runner.respond(
+            "rustup target list",
+            CommandOutput::ok(&format!("{WASM_TARGET}\n")),
+        );
+        let plugin = WasmRustPlugin::with_runner(Arc::new(FakeTrunk(runner)), DEFAULT_PROBE_TTL);
+        let output_dir = temp_dir.path().join("out");
+        let result = plugin
+            .compile(&CompileConfig {
+                project_path: project.to_string_lossy().to_string(),
+                output_dir: output_dir.to_string_lossy().to_string(),
+                target_type: TargetType::WebApp,
+                integrity: true,
+                ..Default::default()
+            })
+            .unwrap();
+
+        assert_eq!(result.public_url.as_deref(), Some("/apps/x/"));
+        let references: Vec<&str> = result.integrity[0]
+            .resources
+            .iter()
+            .map(|resource| resource.reference.as_str())
+            .collect();
+        assert_eq!(
+            references,
+            ["/apps/x/app-1a2b.js", "/apps/x/app_bg-1a2b.wasm"]
+        );
+        let html = fs::read_to_string(output_dir.join("index.html")).unwrap();
+        assert!(html.contains(&sri_hash(b"export default function init() {}")));
+    }
+}
+
+mod trunk_tests {
+    use std::fs;
+    use std::path::Path;
+    use tempfile::TempDir;
+    use wasmrust::{TrunkConfig, WasmRustPlugin};
+
+    #[test]
+    fn test_load_example_trunk_config() {
+        let trunk = TrunkConfig::load(Path::new("examples/complex-yew"))
+            .unwrap()
+            .unwrap();
+        assert_eq!(trunk.target(), "index.html");
+        assert_eq!(trunk.dist(), "dist");
+        assert!(trunk.hooks.is_empty());
+
+        assert!(TrunkConfig::load(Path::new("examples/simple-rust"))
+            .unwrap()
+            .is_none());
+    }
+
+    #[test]
+    fn test_custom_target_and_hooks() {
+        let temp_dir = TempDir::new().unwrap();
+        let project = temp_dir.path();
+        fs::write(
+            project.join("trunk.toml"),
+            r#"
+[build]
+target = "web/app.html"
+dist = "public"
+public_url = "/apps/demo"
+
+[[hooks]]
+stage = "pre_build"
+command = "sh"
+command_arguments = ["-c", "npm run css"]
+"#,
+        )
+        .unwrap();
+
+        let trunk = TrunkConfig::load(project).unwrap().unwrap();
+        assert_eq!(trunk.html_file_name(), "app.html");
+        assert_eq!(trunk.dist(), "public");
+        assert_eq!(trunk.hooks[0].to_string(), "[pre_build] sh -c npm run css");
+
+        let dist = project.join("public");
+        fs::create_dir_all(&dist).unwrap();
+        fs::write(dist.join("app.html"), "<html></html>").unwrap();
+        assert_eq!(trunk.find_html(&dist), Some(dist.join("app.html")));
+
+        fs::write(
+            project.join("Cargo.toml"),
+            "[package]\nname = \"hooked\"\nversion = \"0.1.0\"\n\n[dependencies]\nyew = \"0.21\"\n",
+        )
+        .unwrap();
+        let info = WasmRustPlugin::new()
+            .inspect_project(project.to_str().unwrap())
+            .unwrap();
+        assert_eq!(info.trunk_hooks, trunk.hooks);
+    }
+}
+
+mod feature_tests {
+    use std::fs;
+    use tempfile::TempDir;
+    use wasmrust::{CompileConfig, WasmRustPlugin};
+
+    #[test]
+    fn test_feature_args() {
+        assert!(CompileConfig::default().feature_args().is_empty());
+
+        let config = CompileConfig {
+            features: vec!["web".to_string(), "hydrate".to_string()],
+            no_default_features: true,
+            ..Default::default()
+        };
+        assert_eq!(
+            config.feature_args(),
+            vec!["--features", "web,hydrate", "--no-default-features"]
+        );
+
+        let config = CompileConfig {
+            all_features: true,
+            ..Default::default()
+        };
+        assert_eq!(config.feature_args(), vec!["--all-features"]);
+    }
+
+    #[test]
+    fn test_inspect_lists_features() {
+        let temp_dir = TempDir::new().unwrap();
+        let project = temp_dir.path();
+        fs::write(
+            project.join("Cargo.toml"),
+            r#"[package]
+name = "featured"
+version = "0.1.0"
+
+[features]
+default = ["console"]
+console = []
+web = ["dep:web-sys"]
+"#,
+        )
+        .unwrap();
+
+        let info = WasmRustPlugin::new()
+            .inspect_project(project.to_str().unwrap())
+            .unwrap();
+        assert_eq!(info.features.len(), 3);
+        assert_eq!(info.features["default"], vec!["console"]);
+        assert_eq!(info.features["web"], vec!["dep:web-sys"]);
+    }
+}
+
+mod profile_tests {
+    use wasmrust::OptimizationLevel;
+
+    #[test]
+    fn test_profile_directories() {
+        assert_eq!(OptimizationLevel::Debug.profile_dir(), "debug");
+        assert_eq!(OptimizationLevel::Size.profile_dir(), "release");
+        assert_eq!(OptimizationLevel::Profiling.profile_dir(), "release");
+
+        let custom = OptimizationLevel::Custom("wasm-release".to_string());
+        assert_eq!(custom.profile_dir(), "wasm-release");
+        assert_eq!(custom.cargo_args(), vec!["--profile", "wasm-release"]);
+        assert_eq!(
+            OptimizationLevel::Custom("dev".to_string()).profile_dir(),
+            "debug"
+        );
+
+        assert!(OptimizationLevel::Release.cargo_env().is_empty());
+        assert!(OptimizationLevel::Profiling.cargo_env().contains(&(
+            "CARGO_PROFILE_RELEASE_DEBUG".to_string(),
+            "true".to_string()
+        )));
+    }
+
+    #[test]
+    fn test_ffi_optimization_byte() {
+        assert_eq!(
+            OptimizationLevel::from_ffi(0, None),
+            OptimizationLevel::Debug
+        );
+        assert_eq!(
+            OptimizationLevel::from_ffi(3, None),
+            OptimizationLevel::Profiling
+        );
+        assert_eq!(
+            OptimizationLevel::from_ffi(4, Some("wasm-release".to_string())),
+            OptimizationLevel::Custom("wasm-release".to_string())
+        );
+        // A custom level without a profile name falls back to release.
+        assert_eq!(
+            OptimizationLevel::from_ffi(4, None),
+            OptimizationLevel::Release
+        );
+        assert_eq!(
+            OptimizationLevel::from_ffi(9, None),
+            OptimizationLevel::Release
+        );
+    }
+}
+
+mod toolchain_tests {
+    use std::fs;
+    use std::path::PathBuf;
+    use tempfile::TempDir;
+    use wasmrust::{
+        parse_active_toolchain, CommandOutput, PlannedCommand, ProcessRunner, RecordingRunner,
+        TargetDetection, Toolchain, ToolchainFile, ToolchainSource, WASM_TARGET,
+    };
+
+    #[test]
+    fn test_parse_toolchain_files() {
+        let file = ToolchainFile::parse(
+            r#"[toolchain]
+channel = "nightly-2024-05-01"
+components = ["rust-src"]
+targets = ["wasm32-unknown-unknown"]
+"#,
+        )
+        .unwrap();
+        assert_eq!(file.channel.as_deref(), Some("nightly-2024-05-01"));
+        assert_eq!(file.components, vec!["rust-src"]);
+        assert_eq!(file.targets, vec!["wasm32-unknown-unknown"]);
+
+        let legacy = ToolchainFile::parse("1.79.0\n").unwrap();
+        assert_eq!(legacy.channel.as_deref(), Some("1.79.0"));
+    }
+
+    #[test]
+    fn test_toolchain_file_found_in_parent() {
+        let temp_dir = TempDir::new().unwrap();
+        let crate_dir = temp_dir.path().join("crates/app");
+        fs::create_dir_all(&crate_dir).unwrap();
+        fs::write(
+            temp_dir.path().join("rust-toolchain.toml"),
+            "[toolchain]\nchannel = \"nightly\"\ncomponents = [\"rust-src\"]\n",
+        )
+        .unwrap();
+
+        let found = ToolchainFile::find(&crate_dir).unwrap();
+        assert!(found.ends_with("rust-toolchain.toml"));
+
+        // An explicit toolchain wins, but the file's components still apply.
+        let toolchain = Toolchain::resolve(&ProcessRunner, &crate_dir, Some("+beta")).unwrap();
+        assert_eq!(toolchain.name.as_deref(), Some("beta"));
+        assert_eq!(toolchain.source, ToolchainSource::Requested);
+        assert_eq!(toolchain.components, vec!["rust-src"]);
+        assert_eq!(toolchain.cargo_arg().as_deref(), Some("+beta"));
+        assert_eq!(
+            toolchain.env(),
+            vec![("RUSTUP_TOOLCHAIN".to_string(), "beta".to_string())]
+        );
+    }
+
+    #[test]
+    fn test_parse_active_toolchain() {
+        assert_eq!(
+            parse_active_toolchain("stable-x86_64-unknown-linux-gnu (default)\n"),
+            Some((
+                "stable-x86_64-unknown-linux-gnu".to_string(),
+                ToolchainSource::RustupDefault
+            ))
+        );
+        assert_eq!(
+            parse_active_toolchain(
+                "nightly-x86_64-unknown-linux-gnu (overridden by '/app/rust-toolchain.toml')"
+            )
+            .map(|(_, source)| source),
+            Some(ToolchainSource::ToolchainFile)
+        );
+        assert_eq!(
+            parse_active_toolchain("beta (environment override by RUSTUP_TOOLCHAIN)")
+                .map(|(_, source)| source),
+            Some(ToolchainSource::Environment)
+        );
+        assert_eq!(
+            parse_active_toolchain("1.79.0 (directory override for '/app')")
+                .map(|(_, source)| source),
+            Some(ToolchainSource::DirectoryOverride)
+        );
+        assert_eq!(parse_active_toolchain(""), None);
+
+        // Toolchains resolved by rustup are left for cargo's proxy to apply.
+        let toolchain = Toolchain {
+            name: Some("stable".to_string()),
+            source: ToolchainSource::RustupDefault,
+            ..Default::default()
+        };
+        assert_eq!(toolchain.cargo_arg(), None);
+        assert!(toolchain.env().is_empty());
+    }
+
+    #[test]
+    fn test_rustc_probes_use_the_toolchain() {
+        let temp_dir = TempDir::new().unwrap();
+        let runner = RecordingRunner::new();
+        runner.missing("rustup");
+        runner.respond(
+            "rustc --print sysroot",
+            CommandOutput::ok(&format!("{}\n", temp_dir.path().display())),
+        );
+        runner.respond(
+            "rustc --target",
+            CommandOutput::failed(1, "can't find crate"),
+        );
+        let toolchain = Toolchain {
+            name: Some("nightly".to_string()),
+            source: ToolchainSource::ToolchainFile,
+            ..Default::default()
+        };
+
+        for _ in 0..2 {
+            let status = toolchain.detect_target(&runner, WASM_TARGET);
+            assert!(!status.installed);
+            assert_eq!(status.method, TargetDetection::Probe);
+        }
+        let rustc = runner.invocations_of("rustc");
+        assert_eq!(rustc.len(), 4);
+        for command in &rustc {
+            assert_eq!(
+                command.env,
+                [("RUSTUP_TOOLCHAIN".to_string(), "nightly".to_string())]
+            );
+        }
+
+        // Each probe compiles in a scratch directory of its own.
+        let out_dir = |command: &PlannedCommand| {
+            let at = command
+                .args
+                .iter()
+                .position(|arg| arg == "--out-dir")
+                .unwrap();
+            PathBuf::from(&command.args[at + 1])
+        };
+        assert_ne!(out_dir(&rustc[1]), out_dir(&rustc[3]));
+        assert!(!out_dir(&rustc[1]).exists());
+    }
+}
+
+mod target_detection_tests {
+    use std::fs;
+    use tempfile::TempDir;
+    use wasmrust::{target_in_sysroot, TargetDetection, WasmRustPlugin, WASM_TARGET};
+
+    #[test]
+    fn test_target_in_sysroot() {
+        let temp_dir = TempDir::new().unwrap();
+        let sysroot = temp_dir.path();
+        assert!(!target_in_sysroot(sysroot, WASM_TARGET));
+
+        // A target directory without its libraries does not count.
+        fs::create_dir_all(sysroot.join("lib/rustlib").join(WASM_TARGET)).unwrap();
+        assert!(!target_in_sysroot(sysroot, WASM_TARGET));
+
+        fs::create_dir_all(sysroot.join("lib/rustlib").join(WASM_TARGET).join("lib")).unwrap();
+        assert!(target_in_sysroot(sysroot, WASM_TARGET));
+    }
+
+    #[test]
+    fn test_check_dependencies_reports_method() {
+        let plugin = WasmRustPlugin::new();
+        let status = plugin.wasm_target_status();
+        assert_eq!(status.target, WASM_TARGET);
+
+        let missing = plugin.check_dependencies();
+        let target_entry = missing.iter().find(|m| m.starts_with(WASM_TARGET));
+        if status.installed {
+            assert!(target_entry.is_none());
+        } else {
+            assert!(target_entry
+                .unwrap()
+                .contains(&format!("checked via {}", status.method)));
+        }
+
+        assert_eq!(TargetDetection::Sysroot.to_string(), "rustc sysroot");
+    }
+}
+
+mod tool_version_tests {
+    use wasmrust::{
+        minimum_version, parse_tool_version, tool_requirement, BuildStrategy, ToolInfo,
+        WasmRustPlugin,
+    };
+
+    #[test]
+    fn test_parse_tool_version() {
+        let parsed = |output: &str| parse_tool_version(output).map(|v| v.to_string());
+
+        assert_eq!(
+            parsed("cargo 1.79.0 (ffa9cf99a 2024-06-03)").as_deref(),
+            Some("1.79.0")
+        );
+        assert_eq!(
+            parsed("rustc 1.81.0-nightly (d7f6ebace 2024-06-16)").as_deref(),
+            Some("1.81.0-nightly")
+        );
+        assert_eq!(parsed("wasm-pack 0.12.1").as_deref(), Some("0.12.1"));
+        assert_eq!(parsed("trunk v0.20.3").as_deref(), Some("0.20.3"));
+        assert_eq!(
+            parsed("wasm-opt version 116 (version_116)").as_deref(),
+            Some("116.0.0")
+        );
+        assert_eq!(parsed("wasm-bindgen 0.2.92").as_deref(), Some("0.2.92"));
+        assert_eq!(parsed("unknown tool"), None);
+    }
+
+    #[test]
+    fn test_minimum_versions_per_strategy() {
+        assert!(minimum_version("wasm-pack", &BuildStrategy::WasmPack).is_some());
+        assert!(minimum_version("wasm-pack", &BuildStrategy::Trunk).is_none());
+        assert!(minimum_version("trunk", &BuildStrategy::Trunk).is_some());
+        assert!(minimum_version("trunk", &BuildStrategy::Cargo).is_none());
+        assert!(minimum_version("cargo", &BuildStrategy::Cargo).is_some());
+        assert!(minimum_version("wasm-opt", &BuildStrategy::Cargo).is_none());
+
+        let minimum = tool_requirement("trunk").unwrap().minimum_version();
+        let tool = |output: &str| ToolInfo {
+            name: "trunk".to_string(),
+            version: parse_tool_version(output),
+            version_output: Some(output.to_string()),
+            ..Default::default()
+        };
+        assert!(tool("trunk 0.16.0").is_older_than(&minimum));
+        assert!(!tool(&format!("trunk {minimum}")).is_older_than(&minimum));
+        // Unparseable versions are reported but never flagged.
+        assert!(!tool("trunk (dev build)").is_older_than(&minimum));
+
+        // A nightly of the minimum release satisfies it.
+        let rustc = tool_requirement("rustc").unwrap().minimum_version();
+        let nightly = ToolInfo {
+            name: "rustc".to_string(),
+            version: parse_tool_version(&format!("rustc {rustc}-nightly")),
+            ..Default::default()
+        };
+        assert!(!nightly.is_older_than(&rustc));
+    }
+
+    #[test]
+    fn test_probe_tools() {
+        let plugin = WasmRustPlugin::new();
+
+        let missing = plugin.tool_info("wasmrust-no-such-tool");
+        assert!(!missing.is_available());
+        assert_eq!(missing.version, None);
+
+        let cargo = plugin.tool_info("cargo");
+        assert!(cargo.is_available());
+        assert!(cargo.version.is_some());
+        assert!(cargo.to_string().starts_with("cargo "));
+    }
+}
+
+mod wasm_bindgen_version_tests {
+    use std::fs;
+    use tempfile::TempDir;
+    use wasmrust::{locked_version, wasm_bindgen_install_command, WasmRustPlugin};
+
+    const CARGO_LOCK: &str = r#"
+version = 3
+
+[[package]]
+name = "wasm-bindgen"
+version = "0.2.87"
+
+[[package]]
+name = "wasm-bindgen"
+version = "0.2.92"
+
+[[package]]
+name = "wasm-bindgen-macro"
+version = "0.2.93"
+"#;
+
+    #[test]
+    fn test_locked_version_from_workspace_root() {
+        let temp_dir = TempDir::new().unwrap();
+        let member = temp_dir.path().join("crates/app");
+        fs::create_dir_all(&member).unwrap();
+        assert_eq!(locked_version(&member, "wasm-bindgen").unwrap(), None);
+
+        fs::write(temp_dir.path().join("Cargo.lock"), CARGO_LOCK).unwrap();
+        let locked = locked_version(&member, "wasm-bindgen").unwrap().unwrap();
+        assert_eq!(locked.to_string(), "0.2.92");
+        assert_eq!(
+            wasm_bindgen_install_command(&locked),
+            "cargo install -f wasm-bindgen-cli --version 0.2.92"
+        );
+        assert_eq!(locked_version(&member, "js-sys").unwrap(), None);
+    }
+
+    #[test]
+    fn test_inspect_reports_wasm_bindgen_cli() {
+        let temp_dir = TempDir::new().unwrap();
+        let project = temp_dir.path();
+        fs::create_dir_all(project.join("src")).unwrap();
+        fs::write(
+            project.join("Cargo.toml"),
+            "[package]\nname = \"app\"\nversion = \"0.1.0\"\n\n[dependencies]\nwasm-bindgen = \"0.2\"\n",
+        )
+        .unwrap();
+        fs::write(project.join("src/lib.rs"), "").unwrap();
+        fs::write(project.join("Cargo.lock"), CARGO_LOCK).unwrap();
+
+        let plugin = WasmRustPlugin::new();
+        let info = plugin.inspect_project(project.to_str().unwrap()).unwrap();
+        let check = info
+            .dependencies
+            .required
+            .iter()
+            .chain(&info.dependencies.optional)
+            .find(|dep| dep.name == "wasm-bindgen")
+            .unwrap();
+        assert!(check.reason.contains("0.2.92"));
+
+        let cli = plugin.tool_info("wasm-bindgen");
+        if !check.available {
+            assert_eq!(
+                check.fix.as_deref(),
+                Some("cargo install -f wasm-bindgen-cli --version 0.2.92")
+            );
+        }
+        if cli.is_available() {
+            assert!(info
+                .dependencies
+                .required
+                .iter()
+                .any(|dep| dep.name == "wasm-bindgen"));
+        }
+    }
+}
+
+mod probe_cache_tests {
+    use std::path::Path;
+    use std::time::Duration;
+    use wasmrust::{ProbeCache, WasmRustPlugin, DEFAULT_PROBE_TTL};
+
+    #[test]
+    fn test_probe_cache_reuses_and_expires() {
+        let cache = ProbeCache::new(Duration::from_secs(60));
+        assert!(cache.is_empty());
+
+        let first = cache.tool("wasmrust-no-such-tool", &[]);
+        assert!(!first.is_available());
+        assert_eq!(cache.len(), 1);
+        assert_eq!(cache.tool("wasmrust-no-such-tool", &[]), first);
+        assert_eq!(cache.len(), 1);
+
+        // Different environments are probed separately.
+        let envs = [("RUSTUP_TOOLCHAIN".to_string(), "stable".to_string())];
+        cache.tool("wasmrust-no-such-tool", &envs);
+        assert_eq!(cache.len(), 2);
+
+        cache.clear();
+        assert!(cache.is_empty());
+
+        let uncached = ProbeCache::new(Duration::ZERO);
+        uncached.tool("wasmrust-no-such-tool", &[]);
+        assert!(uncached.is_empty());
+    }
+
+    #[test]
+    fn test_plugin_clones_share_probes() {
+        let plugin = WasmRustPlugin::new();
+        assert_eq!(plugin.probe_cache().ttl(), DEFAULT_PROBE_TTL);
+
+        let cargo = plugin.tool_info("cargo");
+        let toolchain = plugin
+            .probe_cache()
+            .toolchain(Path::new("."), None)
+            .unwrap();
+        assert_eq!(
+            plugin
+                .probe_cache()
+                .toolchain(Path::new("."), None)
+                .unwrap(),
+            toolchain
+        );
+
+        let clone = plugin.clone();
+        assert_eq!(clone.probe_cache().len(), 2);
+        assert_eq!(clone.tool_info("cargo"), cargo);
+
+        clone.refresh_probes();
+        assert!(plugin.probe_cache().is_empty());
+    }
+}
+
+mod project_model_tests {
+    use std::fs;
+    use std::path::Path;
+    use tempfile::TempDir;
+    use wasmrust::{BuildStrategy, ProjectModel, ProjectType, WasmRustPlugin};
+
+    #[test]
+    fn test_example_projects() {
+        let model = |name: &str| ProjectModel::load(&Path::new("examples").join(name)).unwrap();
+
+        let yew = model("complex-yew");
+        assert_eq!(yew.project_type, ProjectType::WebApplication);
+        assert_eq!(yew.build_strategy, BuildStrategy::Trunk);
+        assert_eq!(yew.frameworks, vec!["yew", "trunk"]);
+        assert!(yew.has_index_html);
+        assert!(yew.locked_wasm_bindgen.is_some());
+
+        let web = model("simple-web");
+        assert_eq!(web.project_type, ProjectType::WasmBindgen);
+        assert_eq!(web.build_strategy, BuildStrategy::WasmPack);
+        assert!(web.is_cdylib());
+
+        let plain = model("simple-rust");
+        assert_eq!(plain.project_type, ProjectType::StandardWasm);
+        assert_eq!(plain.build_strategy, BuildStrategy::Cargo);
+        assert_eq!(plain.package_name(), plain.name.replace('-', "_"));
+    }
+
+    #[test]
+    fn test_detection_uses_parsed_dependencies() {
+        let temp_dir = TempDir::new().unwrap();
+        let project = temp_dir.path();
+        fs::write(
+            project.join("Cargo.toml"),
+            r#"[package]
+name = "renamed"
+version = "0.1.0"
+description = "Not a yew app, built without trunk"
+
+[lib]
+crate-type = ["cdylib"]
+
+[target.'cfg(target_arch = "wasm32")'.dependencies]
+web = { package = "web-sys", version = "0.3" }
+
+[dev-dependencies]
+wasm-bindgen-test = "0.3"
+"#,
+        )
+        .unwrap();
+
+        let model = ProjectModel::load(project).unwrap();
+        assert!(model.dependencies.contains("web-sys"));
+        assert!(!model.dependencies.contains("wasm-bindgen-test"));
+        assert!(model.frameworks.is_empty());
+        assert_eq!(model.project_type, ProjectType::WasmBindgen);
+
+        // A page next to a bindgen cdylib makes it a web application.
+        fs::create_dir(project.join("static")).unwrap();
+        let model = ProjectModel::load(project).unwrap();
+        assert_eq!(model.static_dirs, vec!["static"]);
+        assert_eq!(model.project_type, ProjectType::WebApplication);
+        assert_eq!(model.build_strategy, BuildStrategy::WasmPack);
+
+        fs::write(project.join("Trunk.toml"), "").unwrap();
+        let model = ProjectModel::load(project).unwrap();
+        let info = WasmRustPlugin::new()
+            .inspect_project(project.to_str().unwrap())
+            .unwrap();
+        assert_eq!(model.build_strategy, BuildStrategy::Trunk);
+        assert_eq!(info.build_strategy, model.build_strategy);
+        assert_eq!(info.project_type, model.project_type);
+        assert_eq!(info.frameworks, vec!["trunk"]);
+    }
+}
+
+mod detection_evidence_tests {
+    use std::fs;
+    use std::path::Path;
+    use tempfile::TempDir;
+    use wasmrust::{Decision, EvidenceSource, ProjectModel, WasmRustPlugin};
+
+    #[test]
+    fn test_evidence_matches_decisions() {
+        let info = WasmRustPlugin::new()
+            .inspect_project("examples/complex-yew")
+            .unwrap();
+
+        for decision in [Decision::ProjectType, Decision::BuildStrategy] {
+            let entries: Vec<_> = info
+                .evidence
+                .iter()
+                .filter(|e| e.decision == decision)
+                .collect();
+            assert_eq!(entries.len(), 3);
+            assert_eq!(entries.iter().filter(|e| e.accepted).count(), 1);
+        }
+
+        let chosen = |decision| {
+            info.evidence
+                .iter()
+                .find(|e| e.decision == decision && e.accepted)
+                .unwrap()
+        };
+        assert_eq!(
+            chosen(Decision::ProjectType).candidate,
+            format!("{:?}", info.project_type)
+        );
+        assert!(chosen(Decision::ProjectType)
+            .sources
+            .contains(&EvidenceSource::Dependency("yew".to_string())));
+        assert_eq!(
+            chosen(Decision::BuildStrategy).sources,
+            vec![EvidenceSource::File("Trunk.toml".to_string())]
+        );
+
+        let plain = ProjectModel::load(Path::new("examples/simple-rust")).unwrap();
+        let rejected_trunk = plain
+            .evidence
+            .iter()
+            .find(|e| e.candidate == "Trunk")
+            .unwrap();
+        assert!(!rejected_trunk.accepted);
+        assert_eq!(
+            rejected_trunk.to_string(),
+            "build strategy Trunk rejected: only web applications build with trunk"
+        );
+    }
+
+    #[test]
+    fn test_page_evidence_for_bindgen_cdylib() {
+        let temp_dir = TempDir::new().unwrap();
+        let project = temp_dir.path();
+        fs::write(
+            project.join("Cargo.toml"),
+            "[package]\nname = \"page\"\nversion = \"0.1.0\"\n\n[lib]\ncrate-type = [\"cdylib\"]\n\n[dependencies]\nwasm-bindgen = \"0.2\"\n",
+        )
+        .unwrap();
+        fs::write(project.join("index.html"), "<html></html>").unwrap();
+        fs::create_dir(project.join("www")).unwrap();
+
+        let model = ProjectModel::load(project).unwrap();
+        let web_app = model
+            .evidence
+            .iter()
+            .find(|e| e.candidate == "WebApplication")
+            .unwrap();
+        assert!(web_app.accepted);
+        assert_eq!(
+            web_app.sources,
+            vec![
+                EvidenceSource::Dependency("wasm-bindgen".to_string()),
+                EvidenceSource::ConfigKey("lib.crate-type = cdylib".to_string()),
+                EvidenceSource::File("index.html".to_string()),
+                EvidenceSource::Directory("www".to_string()),
+            ]
+        );
+    }
+}
+
+mod build_plan_tests {
+    use tempfile::TempDir;
+    use wasmrust::{
+        BuildStrategy, CompileConfig, OptimizationLevel, PlannedCommand, ProjectType,
+        WasmRustError, WasmRustPlugin, MANIFEST_FILE_NAME, WASM_TARGET,
+    };
+
+    fn config(project: &str, output_dir: &std::path::Path) -> CompileConfig {
+        CompileConfig {
+            project_path: project.to_string(),
+            output_dir: output_dir.to_string_lossy().to_string(),
+            optimization: OptimizationLevel::Release,
+            features: vec!["web".to_string()],
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_plan_standard_wasm() {
+        let temp_dir = TempDir::new().unwrap();
+        let output_dir = temp_dir.path().join("out");
+        let plugin = WasmRustPlugin::new();
+
+        let plan = plugin
+            .plan(&config("examples/simple-rust", &output_dir))
+            .unwrap();
+        assert_eq!(plan.project_type, ProjectType::StandardWasm);
+        assert_eq!(plan.build_strategy, BuildStrategy::Cargo);
+
+        let build = plan.commands.last().unwrap();
+        assert_eq!(build.program, "cargo");
+        assert!(build.args.ends_with(
+            &[
+                "build",
+                "--target",
+                WASM_TARGET,
+                "--release",
+                "--features",
+                "web"
+            ]
+            .map(String::from)
+        ));
+        assert_eq!(
+            build.cwd.as_deref(),
+            Some(std::path::Path::new("examples/simple-rust"))
+        );
+        assert_eq!(
+            plan.outputs,
+            vec![
+                output_dir.join("simple_rust.wasm"),
+                output_dir.join(MANIFEST_FILE_NAME)
+            ]
+        );
+        // Planning runs nothing and writes nothing.
+        assert!(!output_dir.exists());
+    }
+
+    #[test]
+    fn test_plan_routing() {
+        let temp_dir = TempDir::new().unwrap();
+        let plugin = WasmRustPlugin::new();
+
+        let plan = plugin
+            .plan(&config("examples/simple-web", temp_dir.path()))
+            .unwrap();
+        assert_eq!(plan.build_strategy, BuildStrategy::WasmPack);
+        let wasm_pack = &plan.commands[0];
+        assert_eq!(wasm_pack.program, "wasm-pack");
+        assert!(wasm_pack
+            .args
+            .ends_with(&["--", "--features", "web"].map(String::from)));
+        assert!(plan.outputs[1].ends_with("simple_web.js"));
+
+        let custom = CompileConfig {
+            optimization: OptimizationLevel::Custom("bench".to_string()),
+            ..config("examples/simple-web", temp_dir.path())
+        };
+        assert!(matches!(
+            plugin.plan(&custom),
+            Err(WasmRustError::InvalidConfiguration(_))
+        ));
+
+        let plan = plugin
+            .plan(&config("examples/complex-yew", temp_dir.path()))
+            .unwrap();
+        assert_eq!(plan.project_type, ProjectType::WebApplication);
+        if plugin.is_tool_available("trunk") {
+            assert_eq!(plan.build_strategy, BuildStrategy::Trunk);
+        } else {
+            assert_eq!(plan.build_strategy, BuildStrategy::WasmPack);
+            assert!(plan.notes[0].contains("trunk is not installed"));
+            assert!(plan.outputs.contains(&temp_dir.path().join("index.html")));
+        }
+    }
+
+    #[test]
+    fn test_planned_command_display() {
+        let command = PlannedCommand {
+            program: "trunk".to_string(),
+            args: vec![
+                "build".to_string(),
+                "--dist".to_string(),
+                "/tmp/my out".to_string(),
+            ],
+            env: vec![("RUSTUP_TOOLCHAIN".to_string(), "nightly".to_string())],
+            ..Default::default()
+        };
+        assert_eq!(
+            command.to_string(),
+            "RUSTUP_TOOLCHAIN=nightly trunk build --dist '/tmp/my out'"
+        );
+    }
+}
+
+mod runner_tests {
+    use super::fake_builds::{fake_plugin, fake_project};
+
+    use tempfile::TempDir;
+    use wasmrust::{
+        CommandOutput, CompileConfig, OptimizationLevel, PlannedCommand, WasmRustError, WASM_TARGET,
+    };
+
+    fn config(project: &std::path::Path, output_dir: &std::path::Path) -> CompileConfig {
+        CompileConfig {
+            project_path: project.to_string_lossy().to_string(),
+            output_dir: output_dir.to_string_lossy().to_string(),
+            optimization: OptimizationLevel::Release,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_compile_records_cargo_invocation() {
+        let temp_dir = TempDir::new().unwrap();
+        let project = fake_project(temp_dir.path());
+
+        let (plugin, runner) = fake_plugin();
+        let output_dir = temp_dir.path().join("out");
+        let config = config(&project, &output_dir);
+        let result = plugin.compile(&config).unwrap();
+        assert!(result.wasm_path.ends_with("fake_wasm.wasm"));
+
+        // Alongside the `cargo --version` probe for the input fingerprint.
+        let builds: Vec<PlannedCommand> = runner
+            .invocations_of("cargo")
+            .into_iter()
+            .filter(|command| command.args != ["--version"])
+            .collect();
+        assert_eq!(
+            builds,
+            vec![PlannedCommand {
+                program: "cargo".to_string(),
+                args: ["build", "--target", WASM_TARGET, "--release"]
+                    .map(String::from)
+                    .to_vec(),
+                cwd: Some(project.clone()),
+                env: Vec::new(),
+            }]
+        );
+        // The dry run describes exactly what was executed.
+        let plan = plugin.plan(&config).unwrap();
+        assert_eq!(plan.commands.last(), runner.invocations_of("cargo").last());
+    }
+
+    #[test]
+    fn test_simulated_failures() {
+        let temp_dir = TempDir::new().unwrap();
+
+        let (plugin, runner) = fake_plugin();
+        runner.missing("wasm-pack");
+        assert!(!plugin.is_tool_available("wasm-pack"));
+        let result = plugin.compile(&config(
+            std::path::Path::new("examples/simple-web"),
+            temp_dir.path(),
+        ));
+        assert!(matches!(result, Err(WasmRustError::ToolNotFound(_))));
+        assert!(runner
+            .invocations_of("wasm-pack")
+            .iter()
+            .all(|command| command.args == ["--version"]));
+
+        let (plugin, runner) = fake_plugin();
+        runner.respond(
+            "wasm-pack build",
+            CommandOutput::failed(1, "error[E0425]: cannot find value `x`"),
+        );
+        let result = plugin.compile(&config(
+            std::path::Path::new("examples/simple-web"),
+            temp_dir.path(),
+        ));
+        match result {
+            Err(WasmRustError::CompilationFailed(message)) => {
+                assert!(message.contains("cannot find value"))
+            }
+            other => panic!("expected a compilation failure, got {other:?}"),
+        }
+        assert_eq!(runner.invocations_of("wasm-pack").len(), 2);
+    }
+}
+
+mod build_events_tests {
+    use super::fake_builds::{fake_plugin, fake_project};
+
+    use std::sync::Mutex;
+    use tempfile::TempDir;
+    use wasmrust::{
+        compiling_crate, phase_for_line, BuildEvent, BuildPhase, CommandOutput, CommandRunner,
+        CompileConfig, OutputStream, PlannedCommand, ProcessRunner, StepLimits,
+    };
+
+    #[test]
+    fn test_parse_tool_lines() {
+        assert_eq!(
+            compiling_crate("   Compiling serde v1.0.203"),
+            Some("serde")
+        );
+        assert_eq!(compiling_crate("[INFO]: 🌀  Compiling to Wasm..."), None);
+        assert_eq!(
+            phase_for_line("   Compiling wasm-bindgen v0.2.92"),
+            Some(BuildPhase::Compiling)
+        );
+        assert_eq!(
+            phase_for_line("[INFO]: ⬇️  Installing wasm-bindgen..."),
+            Some(BuildPhase::Bindgen)
+        );
+        assert_eq!(
+            phase_for_line("INFO calling wasm-bindgen for app"),
+            Some(BuildPhase::Bindgen)
+        );
+        // Dependency lines naming wasm-bindgen come before compilation.
+        assert_eq!(
+            phase_for_line("   Downloaded wasm-bindgen-shared v0.2.92"),
+            None
+        );
+        assert_eq!(
+            phase_for_line("       Fresh wasm-bindgen-macro v0.2.92"),
+            None
+        );
+        assert_eq!(
+            phase_for_line("[INFO]: Optimizing wasm binaries with `wasm-opt`..."),
+            Some(BuildPhase::Optimizing)
+        );
+        assert_eq!(
+            phase_for_line("INFO applying new distribution"),
+            Some(BuildPhase::Bundling)
+        );
+        assert_eq!(phase_for_line("warning: unused variable"), None);
+    }
+
+    #[test]
+    fn test_compile_reports_events() {
+        let temp_dir = TempDir::new().unwrap();
+        let project = fake_project(temp_dir.path());
+
+        let (plugin, runner) = fake_plugin();
+        runner.respond(
+            "cargo build",
+            CommandOutput {
+                status: Some(0),
+                stderr:
+                    b"   Compiling fake-wasm v0.1.0 (/tmp/fake-wasm)\n    Finished `release` profile\n"
+                        .to_vec(),
+                ..Default::default()
+            },
+        );
+
+        let config = CompileConfig {
+            project_path: project.to_string_lossy().to_string(),
+            output_dir: temp_dir.path().join("out").to_string_lossy().to_string(),
+            ..Default::default()
+        };
+        let events = Mutex::new(Vec::new());
+        plugin
+            .compile_with_events(&config, &|event| events.lock().unwrap().push(event.clone()))
+            .unwrap();
+        let events = events.into_inner().unwrap();
+
+        let phases: Vec<BuildPhase> = events
+            .iter()
+            .filter_map(|event| match event {
+                BuildEvent::Phase { phase } => Some(*phase),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(
+            phases,
+            vec![
+                BuildPhase::Resolving,
+                BuildPhase::Compiling,
+                BuildPhase::Bundling
+            ]
+        );
+        assert!(events.contains(&BuildEvent::Crate {
+            name: "fake-wasm".to_string(),
+            current: 1,
+            total: None,
+        }));
+        assert!(events.contains(&BuildEvent::Output {
+            stream: OutputStream::Stderr,
+            line: "    Finished `release` profile".to_string(),
+        }));
+        assert_eq!(events.last(), Some(&BuildEvent::Finished { success: true }));
+
+        // Notes reach the handler as events rather than stdout.
+        let events = Mutex::new(Vec::new());
+        assert!(
+            plugin
+                .compile_with_events(&config, &|event| events.lock().unwrap().push(event.clone()))
+                .unwrap()
+                .cached
+        );
+        assert!(events.into_inner().unwrap().iter().any(|event| matches!(
+            event,
+            BuildEvent::Message { text } if text.starts_with("Inputs unchanged")
+        )));
+
+        runner.respond("cargo build", CommandOutput::failed(101, "error: oops"));
+        let config = CompileConfig {
+            force: true,
+            ..config
+        };
+        let events = Mutex::new(Vec::new());
+        assert!(plugin
+            .compile_with_events(&config, &|event| events.lock().unwrap().push(event.clone()))
+            .is_err());
+        assert_eq!(
+            events.into_inner().unwrap().last(),
+            Some(&BuildEvent::Finished { success: false })
+        );
+    }
+
+    #[test]
+    fn test_process_runner_streams_lines() {
+        let command = PlannedCommand {
+            program: "rustc".to_string(),
+            args: vec!["--version".to_string()],
+            ..Default::default()
+        };
+        let mut lines = Vec::new();
+        let output = ProcessRunner
+            .run_streaming(&command, &StepLimits::default(), &mut |stream, line| {
+                lines.push((stream, line.to_string()))
+            })
+            .unwrap();
+        assert!(output.success());
+        assert_eq!(lines.len(), 1);
+        assert_eq!(lines[0].0, OutputStream::Stdout);
+        assert!(lines[0].1.starts_with("rustc "));
+        assert_eq!(output.stdout, format!("{}\n", lines[0].1).into_bytes());
+    }
+}
+
+mod cancellation_tests {
+    use std::io;
+    use std::sync::Arc;
+    use std::time::{Duration, Instant};
+    use wasmrust::{
+        CancellationToken, CommandOutput, CommandRunner, CompileConfig, PlannedCommand,
+        ProcessRunner, RecordingRunner, StepLimits, WasmRustError, WasmRustPlugin,
+        DEFAULT_PROBE_TTL,
+    };
+
+    fn shell(script: &str) -> PlannedCommand {
+        PlannedCommand {
+            program: "sh".to_string(),
+            args: vec!["-c".to_string(), script.to_string()],
+            ..Default::default()
+        }
+    }
+
+    #[cfg(target_os = "linux")]
+    fn is_running(pid: &str) -> bool {
+        std::fs::read_to_string(format!("/proc/{pid}/stat"))
+            .is_ok_and(|stat| !stat.contains(") Z "))
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn test_step_timeout_kills_process_tree() {
+        let limits = StepLimits {
+            timeout: Some(Duration::from_millis(300)),
+            ..Default::default()
+        };
+        let mut grandchild = String::new();
+        let started = Instant::now();
+        let err = ProcessRunner
+            .run_streaming(
+                &shell("sleep 30 & echo $!; wait"),
+                &limits,
+                &mut |_, line| grandchild = line.to_string(),
+            )
+            .unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::TimedOut);
+        assert!(started.elapsed() < Duration::from_secs(10));
+        assert!(!grandchild.is_empty());
+
+        #[cfg(target_os = "linux")]
+        {
+            let deadline = Instant::now() + Duration::from_secs(5);
+            while is_running(&grandchild) && Instant::now() < deadline {
+                std::thread::sleep(Duration::from_millis(20));
+            }
+            assert!(!is_running(&grandchild), "sleep {grandchild} survived");
+        }
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn test_leftover_process_holding_pipes_does_not_hang() {
+        // The background sleep inherits stdout and outlives the shell.
+        let started = Instant::now();
+        let mut lines = Vec::new();
+        let output = ProcessRunner
+            .run_streaming(
+                &shell("sleep 20 & echo done"),
+                &StepLimits::default(),
+                &mut |_, line| lines.push(line.to_string()),
+            )
+            .unwrap();
+        assert!(started.elapsed() < Duration::from_secs(10));
+        assert!(output.success());
+        assert_eq!(lines, ["done"]);
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn test_output_queued_at_exit_is_not_lost() {
+        // 4 MB of stderr, read far slower than the tool writes it.
+        let script = "head -c 4000000 /dev/zero | tr '\\0' x | fold -w 1000 >&2; exit 3";
+        let mut lines = 0;
+        let output = ProcessRunner
+            .run_streaming(&shell(script), &StepLimits::default(), &mut |_, line| {
+                assert_eq!(line.len(), 1000);
+                lines += 1;
+                std::thread::sleep(Duration::from_micros(250));
+            })
+            .unwrap();
+        assert_eq!(output.status, Some(3));
+        assert_eq!(lines, 4000);
+        assert_eq!(output.stderr.len(), 4_003_999);
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn test_cancel_running_step() {
+        let token = CancellationToken::new();
+        let limits = StepLimits {
+            cancel: Some(token.clone()),
+            ..Default::default()
+        };
+        let canceller = std::thread::spawn(move || {
+            std::thread::sleep(Duration::from_millis(100));
+            token.cancel();
+        });
+
+        let started = Instant::now();
+        let err = ProcessRunner
+            .run_streaming(&shell("sleep 30"), &limits, &mut |_, _| {})
+            .unwrap_err();
+        canceller.join().unwrap();
+        assert_eq!(err.kind(), io::ErrorKind::Interrupted);
+        assert!(started.elapsed() < Duration::from_secs(10));
+
+        // Steps that finish in time are unaffected.
+        let output = ProcessRunner
+            .run_streaming(
+                &shell("echo done"),
+                &StepLimits {
+                    timeout: Some(Duration::from_secs(30)),
+                    ..Default::default()
+                },
+                &mut |_, _| {},
+            )
+            .unwrap();
+        assert_eq!(output, CommandOutput::ok("done\n"));
+    }
+
+    #[test]
+    fn test_cancelled_compile() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let runner = Arc::new(RecordingRunner::new());
+        let plugin = WasmRustPlugin::with_runner(runner.clone(), DEFAULT_PROBE_TTL);
+
+        let token = CancellationToken::new();
+        token.cancel();
+        let config = CompileConfig {
+            project_path: "examples/simple-rust".to_string(),
+            output_dir: temp_dir.path().to_string_lossy().to_string(),
+            cancel: Some(token),
+            ..Default::default()
+        };
+        assert!(matches!(
+            plugin.compile(&config),
+            Err(WasmRustError::Cancelled)
+        ));
+        assert!(runner.invocations_of("cargo").iter().all(|command| command
+            .args
+            .first()
+            .map(String::as_str)
+            != Some("build")));
+    }
+}
+
+mod fingerprint_tests {
+    use super::fake_builds::{cargo_builds, fake_plugin, fake_project};
+    use super::wasm_fixtures::{encode_module, func};
+    use std::fs;
+
+    use tempfile::TempDir;
+    use wasmrust::{
+        build_environment, input_fingerprint, CompatConfig, CompileConfig, ExportSurface,
+        Toolchain, WasmModule,
+    };
+
+    #[test]
+    fn test_unchanged_inputs_reuse_build() {
+        let temp_dir = TempDir::new().unwrap();
+        let project = fake_project(temp_dir.path());
+        let (plugin, runner) = fake_plugin();
+        let config = CompileConfig {
+            project_path: project.to_string_lossy().to_string(),
+            output_dir: temp_dir.path().join("out").to_string_lossy().to_string(),
+            ..Default::default()
+        };
+
+        let first = plugin.compile(&config).unwrap();
+        assert!(!first.cached);
+        let second = plugin.compile(&config).unwrap();
+        assert!(second.cached);
+        assert_eq!(second.wasm_path, first.wasm_path);
+        assert_eq!(second.manifest_path, first.manifest_path);
+        assert_eq!(cargo_builds(&runner), 1);
+
+        // A source change, a forced build and a missing output all rebuild.
+        fs::write(project.join("src/lib.rs"), "pub fn f() {}\n").unwrap();
+        assert!(!plugin.compile(&config).unwrap().cached);
+        assert_eq!(cargo_builds(&runner), 2);
+
+        let forced = CompileConfig {
+            force: true,
+            ..config.clone()
+        };
+        assert!(!plugin.compile(&forced).unwrap().cached);
+        assert_eq!(cargo_builds(&runner), 3);
+
+        fs::remove_file(&first.wasm_path).unwrap();
+        assert!(!plugin.compile(&config).unwrap().cached);
+        assert_eq!(cargo_builds(&runner), 4);
+        assert!(plugin.compile(&config).unwrap().cached);
+    }
+
+    #[test]
+    fn test_reused_builds_are_checked_against_the_current_snapshot() {
+        let temp_dir = TempDir::new().unwrap();
+        let project = fake_project(temp_dir.path());
+        let (plugin, runner) = fake_plugin();
+        let config = CompileConfig {
+            project_path: project.to_string_lossy().to_string(),
+            output_dir: temp_dir.path().join("out").to_string_lossy().to_string(),
+            compat: CompatConfig {
+                snapshot: Some("api.json".to_string()),
+                allow_breaking: true,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+
+        plugin.compile(&config).unwrap();
+        assert!(project.join("api.json").is_file());
+        let unchanged = plugin.compile(&config).unwrap();
+        assert!(unchanged.cached);
+        assert!(unchanged.compat_report.unwrap().is_unchanged());
+
+        // The snapshot is not a build input; a reused build still reports
+        // against what it holds now.
+        let baseline = encode_module(&[func("greet", &[], &[], 4)], &[], false);
+        ExportSurface::from_module(&WasmModule::parse(&baseline).unwrap())
+            .write(&project.join("api.json"))
+            .unwrap();
+        let result = plugin.compile(&config).unwrap();
+        assert!(result.cached);
+        assert_eq!(result.compat_report.unwrap().breaking[0].item, "func greet");
+        assert_eq!(cargo_builds(&runner), 1);
+    }
+
+    #[test]
+    fn test_fingerprint_inputs() {
+        let temp_dir = TempDir::new().unwrap();
+        let project = fake_project(temp_dir.path());
+        let inputs = vec![project.join("Cargo.toml"), project.join("src")];
+        let config = CompileConfig {
+            project_path: project.to_string_lossy().to_string(),
+            ..Default::default()
+        };
+        let toolchain = Toolchain::default();
+        let fingerprint = |config: &CompileConfig| {
+            input_fingerprint(config, &inputs, &toolchain, &[], &[]).unwrap()
+        };
+
+        let base = fingerprint(&config);
+        assert_eq!(base.len(), 64);
+        // How a build runs does not matter, what it builds does.
+        let verbose = CompileConfig {
+            verbose: true,
+            force: true,
+            ..config.clone()
+        };
+        assert_eq!(fingerprint(&verbose), base);
+        let features = CompileConfig {
+            features: vec!["web".to_string()],
+            ..config.clone()
+        };
+        assert_ne!(fingerprint(&features), base);
+
+        fs::write(project.join("src/extra.rs"), "").unwrap();
+        assert_ne!(fingerprint(&config), base);
+
+        let base = fingerprint(&config);
+        let rustflags = [("RUSTFLAGS".to_string(), "-C opt-level=z".to_string())];
+        assert_ne!(
+            input_fingerprint(&config, &inputs, &toolchain, &[], &rustflags).unwrap(),
+            base
+        );
+        assert!(build_environment()
+            .iter()
+            .all(|(name, _)| name.starts_with("CARGO_")
+                || name.starts_with("RUSTUP_")
+                || name.starts_with("RUST")));
+    }
+
+    #[test]
+    fn test_html_and_path_dependencies_invalidate_the_cache() {
+        let temp_dir = TempDir::new().unwrap();
+        let project = fake_project(temp_dir.path());
+        let helper = temp_dir.path().join("helper");
+        fs::create_dir_all(helper.join("src")).unwrap();
+        fs::write(
+            helper.join("Cargo.toml"),
+            "[package]\nname = \"helper\"\nversion = \"0.1.0\"\n",
+        )
+        .unwrap();
+        fs::write(helper.join("src/lib.rs"), "").unwrap();
+        fs::write(
+            project.join("Cargo.toml"),
+            "[package]\nname = \"fake-wasm\"\nversion = \"0.1.0\"\n\n\
+             [dependencies]\nhelper = { path = \"../helper\" }\n",
+        )
+        .unwrap();
+        fs::write(project.join("index.html"), "<html></html>").unwrap();
+
+        let (plugin, runner) = fake_plugin();
+        let config = CompileConfig {
+            project_path: project.to_string_lossy().to_string(),
+            output_dir: temp_dir.path().join("out").to_string_lossy().to_string(),
+            ..Default::default()
+        };
+        plugin.compile(&config).unwrap();
+        assert!(plugin.compile(&config).unwrap().cached);
+
+        fs::write(project.join("index.html"), "<html><body></body></html>").unwrap();
+        assert!(!plugin.compile(&config).unwrap().cached);
+        assert!(plugin.compile(&config).unwrap().cached);
+
+        fs::write(helper.join("src/lib.rs"), "pub fn help() {}").unwrap();
+        assert!(!plugin.compile(&config).unwrap().cached);
+
+        fs::write(project.join("build.rs"), "fn main() {}").unwrap();
+        assert!(!plugin.compile(&config).unwrap().cached);
+
+        // A dependency that cannot be read turns the cache off.
+        fs::remove_file(helper.join("Cargo.toml")).unwrap();
+        assert!(!plugin.compile(&config).unwrap().cached);
+        assert!(!plugin.compile(&config).unwrap().cached);
+        assert_eq!(cargo_builds(&runner), 6);
+    }
+}
+
+mod staging_tests {
+    use super::fake_builds::{fake_plugin, fake_project};
+    use std::fs;
+    use std::path::Path;
+
+    use tempfile::TempDir;
+    use wasmrust::{
+        CommandOutput, CompileConfig, PrecompressConfig, MANIFEST_FILE_NAME, WASM_TARGET,
+    };
+
+    fn entries(dir: &Path) -> Vec<String> {
+        let mut names: Vec<String> = fs::read_dir(dir)
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().to_string_lossy().to_string())
+            .collect();
+        names.sort();
+        names
+    }
+
+    #[test]
+    fn test_failed_build_leaves_outputs_untouched() {
+        let temp_dir = TempDir::new().unwrap();
+        let project = fake_project(temp_dir.path());
+        let output_dir = temp_dir.path().join("out");
+        let (plugin, runner) = fake_plugin();
+        let config = CompileConfig {
+            project_path: project.to_string_lossy().to_string(),
+            output_dir: output_dir.to_string_lossy().to_string(),
+            force: true,
+            ..Default::default()
+        };
+
+        let result = plugin.compile(&config).unwrap();
+        // Paths are reported where the outputs end up, not where they were staged.
+        assert_eq!(
+            result.wasm_path,
+            output_dir.join("fake_wasm.wasm").to_string_lossy()
+        );
+        assert_eq!(
+            result.manifest_path.as_deref(),
+            Some(
+                output_dir
+                    .join(MANIFEST_FILE_NAME)
+                    .to_string_lossy()
+                    .as_ref()
+            )
+        );
+        fs::write(output_dir.join("notes.txt"), "mine").unwrap();
+        let before = entries(&output_dir);
+
+        runner.respond(
+            &format!("cargo build --target {WASM_TARGET}"),
+            CommandOutput::failed(101, "error[E0425]: cannot find value `x`"),
+        );
+        assert!(plugin.compile(&config).is_err());
+        assert_eq!(entries(&output_dir), before);
+        // No staging directory is left next to the outputs.
+        assert!(!entries(temp_dir.path())
+            .iter()
+            .any(|name| name.contains("wasmrust-staging")));
+    }
+
+    #[test]
+    fn test_prune_removes_stale_outputs() {
+        let temp_dir = TempDir::new().unwrap();
+        let project = fake_project(temp_dir.path());
+        let output_dir = temp_dir.path().join("out");
+        fs::create_dir_all(output_dir.join("assets")).unwrap();
+        fs::write(output_dir.join("old_bg.wasm"), "stale").unwrap();
+        fs::write(output_dir.join("assets/logo.svg"), "<svg/>").unwrap();
+        let (plugin, _) = fake_plugin();
+        let config = CompileConfig {
+            project_path: project.to_string_lossy().to_string(),
+            output_dir: output_dir.to_string_lossy().to_string(),
+            force: true,
+            ..Default::default()
+        };
+
+        plugin.compile(&config).unwrap();
+        assert_eq!(
+            entries(&output_dir),
+            [
+                "assets",
+                "fake_wasm.wasm",
+                "old_bg.wasm",
+                MANIFEST_FILE_NAME
+            ]
+        );
+        assert!(output_dir.join("assets/logo.svg").is_file());
+
+        let pruned = CompileConfig {
+            prune: true,
+            ..config
+        };
+        plugin.compile(&pruned).unwrap();
+        assert_eq!(entries(&output_dir), ["fake_wasm.wasm", MANIFEST_FILE_NAME]);
+        assert!(!entries(temp_dir.path())
+            .iter()
+            .any(|name| name.contains("wasmrust-staging")));
+    }
+
+    #[test]
+    fn test_leftovers_of_killed_builds_are_recovered() {
+        let temp_dir = TempDir::new().unwrap();
+        let project = fake_project(temp_dir.path());
+        let output_dir = temp_dir.path().join("out");
+        // A build killed while staging, and one killed between the two
+        // renames of a commit, which took the previous outputs aside.
+        fs::create_dir_all(temp_dir.path().join(".out.wasmrust-staging-4194301/pkg")).unwrap();
+        let previous = temp_dir.path().join(".out.wasmrust-previous-4194302");
+        fs::create_dir_all(&previous).unwrap();
+        fs::write(previous.join("notes.txt"), "mine").unwrap();
+        let (plugin, _) = fake_plugin();
+        let config = CompileConfig {
+            project_path: project.to_string_lossy().to_string(),
+            output_dir: output_dir.to_string_lossy().to_string(),
+            force: true,
+            ..Default::default()
+        };
+
+        plugin.compile(&config).unwrap();
+        assert_eq!(
+            entries(&output_dir),
+            ["fake_wasm.wasm", "notes.txt", MANIFEST_FILE_NAME]
+        );
+        assert_eq!(
+            entries(temp_dir.path()),
+            [
+                ".out.wasmrust-lock",
+                ".out.wasmrust-lock.pid",
+                "fake-wasm",
+                "out"
+            ]
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_untouched_outputs_are_linked_not_copied() {
+        use std::os::unix::fs::MetadataExt;
+
+        let temp_dir = TempDir::new().unwrap();
+        let project = fake_project(temp_dir.path());
+        let output_dir = temp_dir.path().join("out");
+        fs::create_dir_all(output_dir.join("assets")).unwrap();
+        fs::write(output_dir.join("assets/logo.svg"), "<svg/>").unwrap();
+        let inode = fs::metadata(output_dir.join("assets/logo.svg"))
+            .unwrap()
+            .ino();
+        let (plugin, _) = fake_plugin();
+        let config = CompileConfig {
+            project_path: project.to_string_lossy().to_string(),
+            output_dir: output_dir.to_string_lossy().to_string(),
+            force: true,
+            ..Default::default()
+        };
+
+        plugin.compile(&config).unwrap();
+        let carried = output_dir.join("assets/logo.svg");
+        assert_eq!(fs::read_to_string(&carried).unwrap(), "<svg/>");
+        assert_eq!(fs::metadata(&carried).unwrap().ino(), inode);
+    }
+
+    #[test]
+    fn test_precompressed_variants_of_rebuilt_files_are_dropped() {
+        let temp_dir = TempDir::new().unwrap();
+        let project = fake_project(temp_dir.path());
+        let output_dir = temp_dir.path().join("out");
+        fs::create_dir_all(&output_dir).unwrap();
+        fs::write(output_dir.join("logo.svg"), "<svg/>").unwrap();
+        fs::write(output_dir.join("logo.svg.gz"), "still matches").unwrap();
+        let (plugin, _) = fake_plugin();
+        let config = CompileConfig {
+            project_path: project.to_string_lossy().to_string(),
+            output_dir: output_dir.to_string_lossy().to_string(),
+            force: true,
+            precompress: Some(PrecompressConfig::default()),
+            ..Default::default()
+        };
+
+        plugin.compile(&config).unwrap();
+        assert!(output_dir.join("fake_wasm.wasm.gz").is_file());
+        assert!(output_dir.join("fake_wasm.wasm.br").is_file());
+
+        plugin
+            .compile(&CompileConfig {
+                precompress: None,
+                ..config
+            })
+            .unwrap();
+        assert_eq!(
+            entries(&output_dir),
+            [
+                "fake_wasm.wasm",
+                "logo.svg",
+                "logo.svg.gz",
+                MANIFEST_FILE_NAME
+            ]
+        );
+    }
+}
+
+mod lock_tests {
+    use super::fake_builds::{cargo_builds, fake_plugin, fake_project};
+    use std::fs::{self, File, OpenOptions};
+    use std::path::Path;
+    use std::sync::Mutex;
+    use std::thread;
+    use std::time::Duration;
+    use tempfile::TempDir;
+    use wasmrust::{
+        BuildEvent, BuildLockMode, CancellationToken, CompileConfig, WasmRustError, LOCK_FILE_NAME,
+    };
+
+    /// Holds the project's build lock the way another build would.
+    fn hold_lock(project: &Path, pid: u32) -> File {
+        let path = project.join(LOCK_FILE_NAME);
+        let file = OpenOptions::new()
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .open(&path)
+            .unwrap();
+        file.try_lock().unwrap();
+        fs::write(path.with_extension("lock.pid"), pid.to_string()).unwrap();
+        file
+    }
+
+    #[test]
+    fn test_fail_fast_names_the_holder() {
+        let temp_dir = TempDir::new().unwrap();
+        let project = fake_project(temp_dir.path());
+        let (plugin, runner) = fake_plugin();
+        let config = CompileConfig {
+            project_path: project.to_string_lossy().to_string(),
+            output_dir: temp_dir.path().join("out").to_string_lossy().to_string(),
+            lock: Some(BuildLockMode::FailFast),
+            ..Default::default()
+        };
+
+        let holder = hold_lock(&project, 4242);
+        let err = plugin.compile(&config).unwrap_err();
+        assert!(matches!(
+            err,
+            WasmRustError::BuildLocked {
+                pid: Some(4242),
+                ..
+            }
+        ));
+        assert!(err.to_string().contains("another build (pid 4242)"));
+        assert_eq!(cargo_builds(&runner), 0);
+
+        drop(holder);
+        plugin.compile(&config).unwrap();
+        assert_eq!(cargo_builds(&runner), 1);
+        // The PID sits next to the lock, which a holder never writes to.
+        let lock = project.join(LOCK_FILE_NAME);
+        assert_eq!(
+            fs::read_to_string(lock.with_extension("lock.pid")).unwrap(),
+            std::process::id().to_string()
+        );
+        assert_eq!(fs::read_to_string(lock).unwrap(), "");
+    }
+
+    #[test]
+    fn test_waiting_builds_build_or_coalesce() {
+        let temp_dir = TempDir::new().unwrap();
+        let project = fake_project(temp_dir.path());
+        let (plugin, runner) = fake_plugin();
+        let config = CompileConfig {
+            project_path: project.to_string_lossy().to_string(),
+            output_dir: temp_dir.path().join("out").to_string_lossy().to_string(),
+            force: true,
+            ..Default::default()
+        };
+        plugin.compile(&config).unwrap();
+        assert_eq!(cargo_builds(&runner), 1);
+
+        let compile_while_locked = |config: CompileConfig| {
+            let holder = hold_lock(&project, 4242);
+            let events = Mutex::new(Vec::new());
+            let result = thread::scope(|scope| {
+                let build = scope.spawn(|| {
+                    plugin.compile_with_events(&config, &|event| {
+                        events.lock().unwrap().push(event.clone())
+                    })
+                });
+                thread::sleep(Duration::from_millis(200));
+                assert!(!build.is_finished());
+                drop(holder);
+                build.join().unwrap().unwrap()
+            });
+            assert!(events.into_inner().unwrap().iter().any(|event| matches!(
+                event,
+                BuildEvent::Waiting {
+                    pid: Some(4242),
+                    ..
+                }
+            )));
+            result
+        };
+
+        // Waiting builds run once the lock is free; a forced one rebuilds.
+        assert!(!compile_while_locked(config.clone()).cached);
+        assert_eq!(cargo_builds(&runner), 2);
+
+        // Coalescing ones reuse what the other build left, forced or not.
+        let coalesced = compile_while_locked(CompileConfig {
+            lock: Some(BuildLockMode::Coalesce),
+            ..config
+        });
+        assert!(coalesced.cached);
+        assert_eq!(cargo_builds(&runner), 2);
+    }
+
+    #[test]
+    fn test_waiting_stops_on_cancel_or_timeout() {
+        let temp_dir = TempDir::new().unwrap();
+        let project = fake_project(temp_dir.path());
+        let (plugin, runner) = fake_plugin();
+        let config = CompileConfig {
+            project_path: project.to_string_lossy().to_string(),
+            output_dir: temp_dir.path().join("out").to_string_lossy().to_string(),
+            step_timeout: Some(Duration::from_millis(300)),
+            ..Default::default()
+        };
+        let _holder = hold_lock(&project, 4242);
+
+        let err = plugin.compile(&config).unwrap_err();
+        assert!(matches!(err, WasmRustError::TimedOut { .. }));
+        assert!(err
+            .to_string()
+            .starts_with("waiting for another build (pid 4242) timed out"));
+
+        let cancel = CancellationToken::new();
+        let cancelled = CompileConfig {
+            step_timeout: None,
+            cancel: Some(cancel.clone()),
+            ..config
+        };
+        let err = thread::scope(|scope| {
+            let build = scope.spawn(|| plugin.compile(&cancelled));
+            thread::sleep(Duration::from_millis(200));
+            cancel.cancel();
+            build.join().unwrap().unwrap_err()
+        });
+        assert!(matches!(err, WasmRustError::Cancelled));
+        assert_eq!(cargo_builds(&runner), 0);
+    }
+}
+
+mod output_guard_tests {
+    use super::fake_builds::{fake_plugin, fake_project};
+    use std::fs;
+    use std::path::{Path, PathBuf};
+
+    use tempfile::TempDir;
+    use wasmrust::{check_output_dir, CompileConfig, WasmRustError};
+
+    fn check(project: &Path, output_dir: impl AsRef<Path>) -> Result<(), String> {
+        check_output_dir(project, &output_dir.as_ref().to_string_lossy())
+            .map_err(|err| err.to_string())
+    }
+
+    #[test]
+    fn test_output_dirs_overlapping_the_project_are_rejected() {
+        let temp_dir = TempDir::new().unwrap();
+        let project = fake_project(temp_dir.path());
+
+        for (output_dir, problem) in [
+            (project.clone(), "is the project directory"),
+            (project.join("src/../"), "is the project directory"),
+            (project.join("src"), "is the project's src directory"),
+            (project.join("target"), "is the project's target directory"),
+            (
+                temp_dir.path().to_path_buf(),
+                "contains the project directory",
+            ),
+            (PathBuf::from("/"), "contains the project directory"),
+        ] {
+            let err = check(&project, &output_dir).unwrap_err();
+            assert!(err.contains(problem), "{}: {err}", output_dir.display());
+            assert!(err.contains("allow_unsafe_output_dir"));
+        }
+
+        // Relative paths may not climb out of the project with `..`.
+        let err = check(&project, "../outside-the-project").unwrap_err();
+        assert!(err.contains("leads out of the project directory"));
+
+        for output_dir in [
+            temp_dir.path().join("out"),
+            project.join("dist"),
+            project.join("pkg/web"),
+            project.join("target/site"),
+        ] {
+            check(&project, &output_dir).unwrap();
+        }
+    }
+
+    #[test]
+    fn test_compile_rejects_unsafe_output_dir_unless_allowed() {
+        let temp_dir = TempDir::new().unwrap();
+        let project = fake_project(temp_dir.path());
+        let (plugin, runner) = fake_plugin();
+        let config = CompileConfig {
+            project_path: project.to_string_lossy().to_string(),
+            output_dir: project.join("src").to_string_lossy().to_string(),
+            ..Default::default()
+        };
+
+        assert!(matches!(
+            plugin.plan(&config),
+            Err(WasmRustError::InvalidConfiguration(_))
+        ));
+        assert!(matches!(
+            plugin.compile(&config),
+            Err(WasmRustError::InvalidConfiguration(_))
+        ));
+        assert!(runner.invocations_of("cargo").is_empty());
+        assert_eq!(fs::read_dir(project.join("src")).unwrap().count(), 1);
+
+        fs::write(
+            project.join("wasmrun.toml"),
+            "[rust]\nallow_unsafe_output_dir = true\n",
+        )
+        .unwrap();
+        plugin.compile(&config).unwrap();
+        assert!(project.join("src/fake_wasm.wasm").is_file());
+        assert!(project.join("src/lib.rs").is_file());
+    }
+
+    /// `path` relative to the directory the tests run in.
+    fn from_cwd(path: &Path) -> String {
+        let cwd = std::env::current_dir().unwrap().canonicalize().unwrap();
+        let path = path.canonicalize().unwrap();
+        let ups = cwd.components().count() - 1;
+        let down = path.strip_prefix("/").unwrap().to_string_lossy();
+        format!("{}{down}", "../".repeat(ups))
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_relative_output_dirs_are_taken_from_the_current_dir() {
+        let temp_dir = TempDir::new().unwrap();
+        let project = fake_project(temp_dir.path());
+        fs::create_dir(project.join("dist")).unwrap();
+
+        let err = check(&project, from_cwd(&project.join("src"))).unwrap_err();
+        assert!(err.contains("is the project's src directory"), "{err}");
+        let err = check(&project, from_cwd(temp_dir.path()) + "/out").unwrap_err();
+        assert!(err.contains("leads out of the project directory"), "{err}");
+
+        let (plugin, _) = fake_plugin();
+        plugin
+            .compile(&CompileConfig {
+                project_path: project.to_string_lossy().to_string(),
+                output_dir: from_cwd(&project.join("dist")),
+                ..Default::default()
+            })
+            .unwrap();
+        assert!(project.join("dist/fake_wasm.wasm").is_file());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_parent_dirs_follow_symlinks() {
+        let temp_dir = TempDir::new().unwrap();
+        let project = fake_project(temp_dir.path());
+        fs::create_dir(project.join("src/nested")).unwrap();
+        let alias = temp_dir.path().join("alias");
+        std::os::unix::fs::symlink(project.join("src/nested"), &alias).unwrap();
+
+        // `alias/..` is the project's src, not the directory holding alias.
+        let err = check(&project, alias.join("..")).unwrap_err();
+        assert!(err.contains("is the project's src directory"), "{err}");
+        let err = check(&project, alias.join("../missing/..")).unwrap_err();
+        assert!(err.contains("is the project's src directory"), "{err}");
+    }
+}